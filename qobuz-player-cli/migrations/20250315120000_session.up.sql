@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS "session" (
+	"tracklist"	TEXT,
+	"position"	BIGINT,
+	"volume"	REAL
+);
@@ -0,0 +1,9 @@
+ALTER TABLE "session" RENAME TO "session_old";
+CREATE TABLE IF NOT EXISTS "session" (
+	"tracklist"	TEXT,
+	"position"	BIGINT,
+	"volume"	REAL
+);
+INSERT INTO "session" ("tracklist", "position", "volume")
+	SELECT "tracklist", "position", "volume" FROM "session_old" WHERE "zone" = 'default';
+DROP TABLE "session_old";
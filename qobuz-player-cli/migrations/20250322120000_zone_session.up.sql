@@ -0,0 +1,10 @@
+ALTER TABLE "session" RENAME TO "session_old";
+CREATE TABLE IF NOT EXISTS "session" (
+	"zone"	TEXT NOT NULL UNIQUE,
+	"tracklist"	TEXT,
+	"position"	BIGINT,
+	"volume"	REAL
+);
+INSERT INTO "session" ("zone", "tracklist", "position", "volume")
+	SELECT 'default', "tracklist", "position", "volume" FROM "session_old";
+DROP TABLE "session_old";
@@ -0,0 +1,3 @@
+ALTER TABLE "config" DROP COLUMN "replaygain_mode";
+ALTER TABLE "config" DROP COLUMN "replaygain_preamp";
+ALTER TABLE "config" DROP COLUMN "replaygain_prevent_clipping";
@@ -0,0 +1,3 @@
+ALTER TABLE "config" ADD COLUMN "replaygain_mode" TEXT;
+ALTER TABLE "config" ADD COLUMN "replaygain_preamp" REAL;
+ALTER TABLE "config" ADD COLUMN "replaygain_prevent_clipping" BOOLEAN;
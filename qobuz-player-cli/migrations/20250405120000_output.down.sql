@@ -0,0 +1,2 @@
+ALTER TABLE "config" DROP COLUMN "output";
+ALTER TABLE "session" DROP COLUMN "output";
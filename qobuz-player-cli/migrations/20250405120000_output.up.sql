@@ -0,0 +1,2 @@
+ALTER TABLE "config" ADD COLUMN "output" TEXT;
+ALTER TABLE "session" ADD COLUMN "output" TEXT;
@@ -0,0 +1 @@
+ALTER TABLE "session" ADD COLUMN "bit_perfect" BOOLEAN;
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS "equalizer" (
+	"output"	TEXT NOT NULL PRIMARY KEY,
+	"bands"	TEXT NOT NULL
+);
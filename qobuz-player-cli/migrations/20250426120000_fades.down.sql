@@ -0,0 +1,2 @@
+ALTER TABLE "config" DROP COLUMN "crossfade";
+ALTER TABLE "config" DROP COLUMN "soft_fades";
@@ -0,0 +1,2 @@
+ALTER TABLE "config" ADD COLUMN "crossfade" REAL;
+ALTER TABLE "config" ADD COLUMN "soft_fades" BOOLEAN;
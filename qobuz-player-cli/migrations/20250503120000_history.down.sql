@@ -0,0 +1,2 @@
+DROP INDEX "plays_started_at";
+DROP TABLE "plays";
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS "plays" (
+	"id"	INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	"track_id"	INTEGER NOT NULL,
+	"track_title"	TEXT NOT NULL,
+	"album_id"	TEXT,
+	"album_title"	TEXT,
+	"artist_id"	INTEGER,
+	"artist_name"	TEXT,
+	"started_at"	INTEGER NOT NULL,
+	"listened_ms"	INTEGER NOT NULL,
+	"skipped"	BOOLEAN NOT NULL
+);
+CREATE INDEX IF NOT EXISTS "plays_started_at" ON "plays" ("started_at");
@@ -0,0 +1,6 @@
+DROP TABLE "scrobbles";
+ALTER TABLE "config" DROP COLUMN "listenbrainz_token";
+ALTER TABLE "config" DROP COLUMN "listenbrainz_api_root";
+ALTER TABLE "config" DROP COLUMN "lastfm_api_key";
+ALTER TABLE "config" DROP COLUMN "lastfm_api_secret";
+ALTER TABLE "config" DROP COLUMN "lastfm_session_key";
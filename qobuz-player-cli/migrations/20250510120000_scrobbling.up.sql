@@ -0,0 +1,14 @@
+ALTER TABLE "config" ADD COLUMN "listenbrainz_token" TEXT;
+ALTER TABLE "config" ADD COLUMN "listenbrainz_api_root" TEXT;
+ALTER TABLE "config" ADD COLUMN "lastfm_api_key" TEXT;
+ALTER TABLE "config" ADD COLUMN "lastfm_api_secret" TEXT;
+ALTER TABLE "config" ADD COLUMN "lastfm_session_key" TEXT;
+CREATE TABLE IF NOT EXISTS "scrobbles" (
+	"id"	INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	"service"	TEXT NOT NULL,
+	"track_title"	TEXT NOT NULL,
+	"artist_name"	TEXT NOT NULL,
+	"album_title"	TEXT,
+	"duration_seconds"	INTEGER NOT NULL,
+	"listened_at"	INTEGER NOT NULL
+);
@@ -1,8 +1,28 @@
-use clap::{Parser, Subcommand};
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
 use dialoguer::{Input, Password};
+use qobuz_player_controls::{
+    equalizer::Equalizer,
+    fade::Fades,
+    history::HistoryStore,
+    local::LocalLibrary,
+    notification::Notification,
+    offline::OfflineCache,
+    output::{self, Output},
+    replaygain::{ReplayGain, ReplayGainMode},
+    session::Session,
+    tracklist::Tracklist,
+    Account, ClockTime, Player,
+};
 use snafu::prelude::*;
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
 
-use crate::database;
+use crate::{
+    database,
+    history::DatabaseHistory,
+    lastfm::LastFm,
+    scrobble::{self, Scrobblers},
+};
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
@@ -23,6 +43,35 @@ struct Cli {
     /// Disable the mpris interface.
     pub disable_mpris: bool,
 
+    #[clap(long, default_value_t = false)]
+    /// Start with an empty queue instead of resuming the previous session.
+    pub disable_resume: bool,
+
+    #[clap(long = "zone", value_parser = parse_zone)]
+    /// Add a playback zone as NAME or NAME=OUTPUT. Can be given several times.
+    zones: Vec<Zone>,
+
+    #[clap(long)]
+    /// Output for zones that don't name one, see the outputs command. (overrides any database value)
+    output: Option<Output>,
+
+    #[clap(long, default_value_t = false)]
+    /// Play streams unaltered to an ALSA hw output, like --output alsa:hw:0.
+    bit_perfect: bool,
+
+    #[clap(long, value_name = "ARTIST_ID")]
+    /// Start the first zone on a radio of an artist and similar artists instead of resuming.
+    radio: Option<u32>,
+
+    #[clap(long = "library", value_name = "PATH")]
+    /// Play audio files in a folder and its subfolders alongside Qobuz. Can be given several times.
+    libraries: Vec<PathBuf>,
+
+    #[clap(long, value_name = "MB", default_value_t = 10_000)]
+    /// Space the albums and playlists kept offline may take up, the least recently played tracks
+    /// are removed past it.
+    offline_cache_size: u64,
+
     #[clap(short, long, default_value_t = tracing::Level::ERROR)]
     /// Log level
     verbosity: tracing::Level,
@@ -39,10 +88,52 @@ struct Cli {
     command: Commands,
 }
 
+#[derive(Clone)]
+struct Zone {
+    name: String,
+    output: Option<Output>,
+}
+
+fn parse_zone(value: &str) -> Result<Zone, String> {
+    let (name, output) = match value.split_once('=') {
+        Some((name, output)) => (name, Some(output.parse()?)),
+        None => (value, None),
+    };
+
+    // Zone names end up in cookies and D-Bus bus names.
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic())
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(
+            "zone names must start with a letter and contain only letters, digits, '-' and '_'"
+                .to_string(),
+        );
+    }
+
+    Ok(Zone {
+        name: name.to_string(),
+        output,
+    })
+}
+
+/// The first zone name that is used by more than one zone.
+fn duplicate_zone(zones: &[Zone]) -> Option<&str> {
+    zones.iter().enumerate().find_map(|(index, zone)| {
+        zones[..index]
+            .iter()
+            .any(|other| other.name == zone.name)
+            .then_some(zone.name.as_str())
+    })
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Open the player
     Open {},
+    /// List the audio outputs found on this machine
+    Outputs {},
     /// Set configuration options
     Config {
         #[clap(subcommand)]
@@ -58,6 +149,45 @@ pub enum ConfigCommands {
     /// Save password to database.
     #[clap(value_parser)]
     Password {},
+    /// Save the default output to database.
+    Output {
+        /// auto, null, device:NAME, alsa:DEVICE, pulse:SINK, pipewire:TARGET, jack:PORTS or file:PATH
+        output: Output,
+    },
+    /// Save replaygain settings to database.
+    ReplayGain {
+        /// off, track or album
+        mode: ReplayGainMode,
+        #[clap(long, default_value_t = 0.0, allow_negative_numbers = true)]
+        /// Extra gain in dB for tracks with replaygain information.
+        preamp: f64,
+        #[clap(long, default_value_t = false)]
+        /// Don't lower the gain of tracks that would clip.
+        allow_clipping: bool,
+    },
+    /// Save crossfade and soft fade settings to database.
+    Fades {
+        /// Seconds tracks from different albums overlap, 0 to play gaplessly.
+        crossfade: f64,
+        #[clap(long, default_value_t = false)]
+        /// Fade in and out when playing, pausing and skipping.
+        soft: bool,
+    },
+    /// Save a ListenBrainz user token to database to submit listens.
+    Listenbrainz {
+        #[clap(long)]
+        /// Submit to another server with the ListenBrainz API instead.
+        api_root: Option<String>,
+        #[clap(long, default_value_t = false)]
+        /// Stop submitting listens and remove the token.
+        remove: bool,
+    },
+    /// Log in to Last.fm with an API account from last.fm/api and save the session to database.
+    Lastfm {
+        #[clap(long, default_value_t = false)]
+        /// Stop scrobbling and remove the session.
+        remove: bool,
+    },
 }
 
 #[derive(Debug, Snafu)]
@@ -68,6 +198,8 @@ pub enum Error {
     PlayerError { error: String },
     #[snafu(display("{error}"))]
     TerminalError { error: String },
+    #[snafu(display("{error}"))]
+    ScrobbleError { error: String },
 }
 
 impl From<qobuz_player_client::Error> for Error {
@@ -78,6 +210,14 @@ impl From<qobuz_player_client::Error> for Error {
     }
 }
 
+impl From<scrobble::Error> for Error {
+    fn from(error: scrobble::Error) -> Self {
+        Error::ScrobbleError {
+            error: error.to_string(),
+        }
+    }
+}
+
 impl From<qobuz_player_controls::error::Error> for Error {
     fn from(error: qobuz_player_controls::error::Error) -> Self {
         Error::PlayerError {
@@ -90,6 +230,16 @@ pub async fn run() -> Result<(), Error> {
     // PARSE CLI ARGS
     let cli = Cli::parse();
 
+    // Zones share a session row, a D-Bus name and a cookie value by name.
+    if let Some(zone) = duplicate_zone(&cli.zones) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!("zone '{zone}' is given more than once"),
+            )
+            .exit();
+    }
+
     tracing_subscriber::fmt()
         .with_max_level(cli.verbosity)
         .with_target(false)
@@ -115,37 +265,154 @@ pub async fn run() -> Result<(), Error> {
                 }
             };
 
+            let account = Account::new(username, password);
+
+            let zones = if cli.zones.is_empty() {
+                vec![Zone {
+                    name: "default".to_string(),
+                    output: None,
+                }]
+            } else {
+                cli.zones
+            };
+
+            let replay_gain = saved_replay_gain().await;
+            let equalizers = saved_equalizers().await;
+            let fades = saved_fades().await;
+            let history: Arc<dyn HistoryStore> = Arc::new(DatabaseHistory);
+            let scrobblers = Arc::new(Scrobblers::configured().await);
+            let offline_cache = open_offline_cache(cli.offline_cache_size).await;
+            let mut players = vec![];
+
+            if !scrobblers.is_empty() {
+                // Send what was listened to while offline last time.
+                let scrobblers = scrobblers.clone();
+                tokio::spawn(async move { scrobblers.flush().await });
+            }
+
+            for zone in zones {
+                let player = Player::new(&zone.name, account.clone())?;
+                player.set_replay_gain(replay_gain).await?;
+                player.set_equalizer_profiles(equalizers.clone()).await;
+                player.set_fades(fades).await?;
+                player.set_history_store(history.clone()).await;
+                if let Some(offline_cache) = &offline_cache {
+                    player.set_offline_cache(offline_cache.clone()).await;
+                }
+                database::create_session(player.zone()).await;
+
+                match zone.output.or(cli.output.clone()) {
+                    Some(output) => player.set_output(output).await?,
+                    None => {
+                        if let Some(output) = saved_output(&player).await {
+                            // A saved device may have been unplugged since, keep the default then.
+                            if let Err(error) = player.set_output(output).await {
+                                println!("{error}, using the default output");
+                            }
+                        }
+                    }
+                }
+
+                if cli.bit_perfect {
+                    player.set_bit_perfect(true).await?;
+                } else if database::get_session(player.zone())
+                    .await
+                    .bit_perfect
+                    .unwrap_or(false)
+                {
+                    if let Err(error) = player.set_bit_perfect(true).await {
+                        println!("{error}, bit-perfect playback is off");
+                    }
+                }
+
+                let starts_radio = cli.radio.is_some() && players.is_empty();
+
+                let session = if cli.disable_resume || starts_radio {
+                    None
+                } else {
+                    saved_session(&player).await
+                };
+
+                let receiver = player.notify_receiver();
+                let persisting_player = player.clone();
+                tokio::spawn(async move { persist_session(&persisting_player, receiver).await });
+
+                if !scrobblers.is_empty() {
+                    let receiver = player.notify_receiver();
+                    let scrobbling_player = player.clone();
+                    let scrobblers = scrobblers.clone();
+                    tokio::spawn(async move {
+                        scrobble::scrobble(&scrobbling_player, scrobblers, receiver).await
+                    });
+                }
+
+                let looping_player = player.clone();
+                tokio::spawn(async move {
+                    match looping_player.player_loop(session).await {
+                        Ok(_) => debug!("player loop exited successfully"),
+                        Err(error) => debug!("player loop error {error}"),
+                    }
+                });
+
+                players.push(player);
+            }
+
+            if !cli.libraries.is_empty() {
+                let players = players.clone();
+                let folders = cli.libraries;
+                tokio::spawn(async move { scan_libraries(players, folders).await });
+            }
+
+            if offline_cache.is_some() {
+                // Finish downloads that were cut short last time.
+                let player = players[0].clone();
+                tokio::spawn(async move {
+                    if let Err(error) = player.sync_offline().await {
+                        debug!("failed to download tracks for offline use: {error}");
+                    }
+                });
+            }
+
+            if let Some(artist_id) = cli.radio {
+                players[0].play_artist_radio(artist_id).await?;
+            }
+
             if !cli.disable_mpris {
+                let players = players.clone();
                 tokio::spawn(async {
-                    qobuz_player_mpris::init().await;
+                    qobuz_player_mpris::init(players).await;
                 });
             }
 
             if cli.web {
-                tokio::spawn(async { qobuz_player_web::init(cli.interface).await });
+                let players = players.clone();
+                tokio::spawn(async { qobuz_player_web::init(players, cli.interface).await });
             }
 
-            tokio::spawn(async {
-                match qobuz_player_controls::player_loop(username, password).await {
-                    Ok(_) => debug!("player loop exited successfully"),
-                    Err(error) => debug!("player loop error {error}"),
-                }
-            });
-
             if !(cli.disable_tui) {
-                qobuz_player_tui::init().await;
+                qobuz_player_tui::init(players.clone()).await;
 
                 debug!("tui exited, quitting");
-                qobuz_player_controls::quit().await?;
             } else {
                 debug!("waiting for ctrlc");
                 tokio::signal::ctrl_c()
                     .await
                     .expect("error waiting for ctrlc");
                 debug!("ctrlc received, quitting");
-                qobuz_player_controls::quit().await?;
             };
 
+            for player in players {
+                save_position(&player).await;
+                player.quit().await?;
+            }
+
+            Ok(())
+        }
+        Commands::Outputs {} => {
+            for output in output::available()? {
+                println!("{output}");
+            }
+
             Ok(())
         }
         Commands::Config { command } => match command {
@@ -175,6 +442,242 @@ pub async fn run() -> Result<(), Error> {
                 }
                 Ok(())
             }
+            ConfigCommands::Output { output } => {
+                database::set_output(output.to_string()).await;
+
+                println!("Output saved.");
+                Ok(())
+            }
+            ConfigCommands::ReplayGain {
+                mode,
+                preamp,
+                allow_clipping,
+            } => {
+                save_replay_gain(ReplayGain {
+                    mode,
+                    preamp,
+                    prevent_clipping: !allow_clipping,
+                })
+                .await;
+
+                println!("Replaygain settings saved.");
+                Ok(())
+            }
+            ConfigCommands::Fades { crossfade, soft } => {
+                save_fades(Fades::new(crossfade, soft)).await;
+
+                println!("Fade settings saved.");
+                Ok(())
+            }
+            ConfigCommands::Listenbrainz { api_root, remove } => {
+                if remove {
+                    database::set_listenbrainz(None, None).await;
+
+                    println!("ListenBrainz token removed.");
+                } else if let Ok(token) = Password::new()
+                    .with_prompt("Enter your ListenBrainz user token (hidden)")
+                    .interact()
+                {
+                    database::set_listenbrainz(Some(token), api_root).await;
+
+                    println!("ListenBrainz token saved.");
+                }
+                Ok(())
+            }
+            ConfigCommands::Lastfm { remove } => {
+                if remove {
+                    database::set_lastfm(None, None, None).await;
+
+                    println!("Last.fm session removed.");
+                    return Ok(());
+                }
+
+                let (Ok(api_key), Ok(api_secret), Ok(username), Ok(password)) = (
+                    Input::<String>::new()
+                        .with_prompt("Enter your Last.fm API key")
+                        .interact_text(),
+                    Password::new()
+                        .with_prompt("Enter your Last.fm shared secret (hidden)")
+                        .interact(),
+                    Input::<String>::new()
+                        .with_prompt("Enter your Last.fm username")
+                        .interact_text(),
+                    Password::new()
+                        .with_prompt("Enter your Last.fm password (hidden)")
+                        .interact(),
+                ) else {
+                    return Ok(());
+                };
+
+                let session_key =
+                    LastFm::session_key(&api_key, &api_secret, &username, &password).await?;
+                database::set_lastfm(Some(api_key), Some(api_secret), Some(session_key)).await;
+
+                println!("Last.fm session saved.");
+                Ok(())
+            }
         },
     }
 }
+
+async fn saved_session(player: &Player) -> Option<Session> {
+    let saved = database::get_session(player.zone()).await;
+    let tracklist = serde_json::from_str(&saved.tracklist?).ok()?;
+
+    Some(Session {
+        tracklist,
+        position: ClockTime::from_mseconds(saved.position.unwrap_or_default() as u64),
+        volume: saved.volume.unwrap_or_else(|| player.volume()),
+    })
+}
+
+/// The output last picked for a zone, or the configured default.
+async fn saved_output(player: &Player) -> Option<Output> {
+    database::get_session(player.zone())
+        .await
+        .output
+        .or(database::get_output().await.output)?
+        .parse()
+        .ok()
+}
+
+/// Equalizer settings saved for each output, skipping any that no longer parse.
+async fn saved_equalizers() -> HashMap<Output, Equalizer> {
+    database::get_equalizers()
+        .await
+        .into_iter()
+        .filter_map(|saved| Some((saved.output.parse().ok()?, saved.bands.parse().ok()?)))
+        .collect()
+}
+
+async fn saved_replay_gain() -> ReplayGain {
+    let saved = database::get_replay_gain().await;
+    let default = ReplayGain::default();
+
+    ReplayGain {
+        mode: saved
+            .replaygain_mode
+            .and_then(|mode| mode.parse().ok())
+            .unwrap_or(default.mode),
+        preamp: saved.replaygain_preamp.unwrap_or(default.preamp),
+        prevent_clipping: saved
+            .replaygain_prevent_clipping
+            .unwrap_or(default.prevent_clipping),
+    }
+}
+
+async fn save_replay_gain(replay_gain: ReplayGain) {
+    database::set_replay_gain(
+        replay_gain.mode.to_string(),
+        replay_gain.preamp,
+        replay_gain.prevent_clipping,
+    )
+    .await;
+}
+
+async fn saved_fades() -> Fades {
+    let saved = database::get_fades().await;
+    let default = Fades::default();
+
+    Fades::new(
+        saved.crossfade.unwrap_or(default.crossfade),
+        saved.soft_fades.unwrap_or(default.soft),
+    )
+}
+
+async fn save_fades(fades: Fades) {
+    database::set_fades(fades.crossfade, fades.soft).await;
+}
+
+/// Reads the local library in the background and hands it to the players once it is done,
+/// so starting up doesn't wait on large folders.
+async fn scan_libraries(players: Vec<Player>, folders: Vec<PathBuf>) {
+    let library = match tokio::task::spawn_blocking(move || LocalLibrary::scan(&folders)).await {
+        Ok(library) => Arc::new(library),
+        Err(error) => {
+            debug!("failed to scan the local library: {error}");
+            return;
+        }
+    };
+
+    debug!("found {} local tracks", library.len());
+
+    for player in players {
+        player.set_local_library(library.clone()).await;
+    }
+}
+
+/// The cache of music kept offline, next to the database. Players go without one if it can't
+/// be opened.
+async fn open_offline_cache(size_mb: u64) -> Option<Arc<OfflineCache>> {
+    let mut dir = dirs::data_local_dir().unwrap();
+    dir.push("qobuz-player");
+    dir.push("offline");
+
+    match OfflineCache::open(dir, size_mb * 1_000_000).await {
+        Ok(cache) => Some(Arc::new(cache)),
+        Err(error) => {
+            println!("{error}, music can not be kept offline");
+            None
+        }
+    }
+}
+
+async fn save_position(player: &Player) {
+    if let Some(position) = player.position() {
+        database::set_position(player.zone(), position.mseconds() as i64).await;
+    }
+}
+
+/// Writes the queue, position and volume to the database as they change.
+async fn persist_session(player: &Player, mut receiver: Receiver<Notification>) {
+    let mut saved_position = ClockTime::default();
+    let mut tracklist = player.tracklist_copy().await;
+
+    loop {
+        match receiver.recv().await {
+            Ok(Notification::QueueChanged { diff }) => {
+                if !tracklist.apply(diff) {
+                    tracklist = player.tracklist_copy().await;
+                }
+
+                save_tracklist(player, tracklist.tracklist()).await;
+            }
+            Ok(Notification::Volume { volume }) => {
+                database::set_volume(player.zone(), volume).await
+            }
+            Ok(Notification::Position { clock }) => {
+                // Position updates arrive several times a second, only write every ten seconds.
+                if clock.seconds().abs_diff(saved_position.seconds()) >= 10 {
+                    saved_position = clock;
+                    database::set_position(player.zone(), clock.mseconds() as i64).await;
+                }
+            }
+            Ok(Notification::Status { status: _ }) => save_position(player).await,
+            Ok(Notification::ReplayGain { replay_gain }) => save_replay_gain(replay_gain).await,
+            Ok(Notification::Fades { fades }) => save_fades(fades).await,
+            Ok(Notification::Output { output }) => {
+                database::set_session_output(player.zone(), output.to_string()).await
+            }
+            Ok(Notification::BitPerfect { bit_perfect }) => {
+                database::set_bit_perfect(player.zone(), bit_perfect).await
+            }
+            Ok(Notification::Equalizer { equalizer }) => {
+                database::set_equalizer(player.output().await.to_string(), equalizer.to_string())
+                    .await
+            }
+            Ok(Notification::Quit) | Err(RecvError::Closed) => break,
+            Err(RecvError::Lagged(_)) => {
+                tracklist = player.tracklist_copy().await;
+                save_tracklist(player, tracklist.tracklist()).await;
+            }
+            Ok(_) => {}
+        }
+    }
+}
+
+async fn save_tracklist(player: &Player, list: &Tracklist) {
+    if let Ok(tracklist) = serde_json::to_string(list) {
+        database::set_tracklist(player.zone(), tracklist).await;
+    }
+}
@@ -9,8 +9,8 @@ macro_rules! acquire {
 }
 
 macro_rules! query {
-    ($query:expr, $conn:ident, $value:ident) => {
-        sqlx::query!($query, $value)
+    ($query:expr, $conn:ident, $($value:ident),+) => {
+        sqlx::query!($query, $($value),+)
             .execute(&mut *$conn)
             .await
             .expect("database failure")
@@ -18,13 +18,21 @@ macro_rules! query {
 }
 
 macro_rules! get_one {
-    ($query:expr, $return_type:ident, $conn:ident) => {
-        sqlx::query_as!($return_type, $query)
+    ($query:expr, $return_type:ident, $conn:ident $(, $value:ident)*) => {
+        sqlx::query_as!($return_type, $query $(, $value)*)
             .fetch_one(&mut *$conn)
             .await
     };
 }
 
+macro_rules! get_all {
+    ($query:expr, $return_type:ident, $conn:ident $(, $value:ident)*) => {
+        sqlx::query_as!($return_type, $query $(, $value)*)
+            .fetch_all(&mut *$conn)
+            .await
+    };
+}
+
 static POOL: OnceLock<Pool<Sqlite>> = OnceLock::new();
 
 #[derive(Default, Debug)]
@@ -33,6 +41,85 @@ pub struct ApiConfig {
     pub password: Option<String>,
 }
 
+#[derive(Default, Debug)]
+pub struct SavedReplayGain {
+    pub replaygain_mode: Option<String>,
+    pub replaygain_preamp: Option<f64>,
+    pub replaygain_prevent_clipping: Option<bool>,
+}
+
+#[derive(Default, Debug)]
+pub struct SavedFades {
+    pub crossfade: Option<f64>,
+    pub soft_fades: Option<bool>,
+}
+
+#[derive(Default, Debug)]
+pub struct SavedScrobbling {
+    pub listenbrainz_token: Option<String>,
+    pub listenbrainz_api_root: Option<String>,
+    pub lastfm_api_key: Option<String>,
+    pub lastfm_api_secret: Option<String>,
+    pub lastfm_session_key: Option<String>,
+}
+
+#[derive(Default, Debug)]
+pub struct SavedOutput {
+    pub output: Option<String>,
+}
+
+#[derive(Default, Debug)]
+pub struct SavedEqualizer {
+    pub output: String,
+    pub bands: String,
+}
+
+#[derive(Default, Debug)]
+pub struct SavedSession {
+    pub tracklist: Option<String>,
+    pub position: Option<i64>,
+    pub volume: Option<f64>,
+    pub output: Option<String>,
+    pub bit_perfect: Option<bool>,
+}
+
+#[derive(Default, Debug)]
+pub struct SavedPlay {
+    pub track_id: i64,
+    pub track_title: String,
+    pub album_id: Option<String>,
+    pub album_title: Option<String>,
+    pub artist_id: Option<i64>,
+    pub artist_name: Option<String>,
+    pub started_at: i64,
+    pub listened_ms: i64,
+    pub skipped: bool,
+}
+
+#[derive(Default, Debug)]
+pub struct SavedPlayTotals {
+    pub plays: i64,
+    pub listened_ms: i64,
+}
+
+#[derive(Default, Debug)]
+pub struct SavedPlayCount {
+    pub id: String,
+    pub name: String,
+    pub plays: i64,
+    pub listened_ms: i64,
+}
+
+#[derive(Default, Debug)]
+pub struct SavedScrobble {
+    pub id: i64,
+    pub track_title: String,
+    pub artist_name: String,
+    pub album_title: Option<String>,
+    pub duration_seconds: i64,
+    pub listened_at: i64,
+}
+
 pub async fn init() {
     let database_url = if let Ok(url) = std::env::var("DATABASE_URL") {
         PathBuf::from(url.replace("sqlite://", ""))
@@ -112,7 +199,7 @@ pub async fn get_config() -> ApiConfig {
 
     get_one!(
         r#"
-            SELECT * FROM config
+            SELECT username, password FROM config
             WHERE ROWID = 1;
             "#,
         ApiConfig,
@@ -120,3 +207,452 @@ pub async fn get_config() -> ApiConfig {
     )
     .unwrap()
 }
+
+pub async fn set_replay_gain(mode: String, preamp: f64, prevent_clipping: bool) {
+    let mut conn = acquire!().unwrap();
+    query!(
+        r#"
+            UPDATE config
+            SET replaygain_mode=?1, replaygain_preamp=?2, replaygain_prevent_clipping=?3
+            WHERE ROWID = 1
+            "#,
+        conn,
+        mode,
+        preamp,
+        prevent_clipping
+    );
+}
+
+pub async fn get_replay_gain() -> SavedReplayGain {
+    let mut conn = acquire!().unwrap();
+
+    get_one!(
+        r#"
+            SELECT replaygain_mode, replaygain_preamp, replaygain_prevent_clipping FROM config
+            WHERE ROWID = 1;
+            "#,
+        SavedReplayGain,
+        conn
+    )
+    .unwrap()
+}
+
+pub async fn set_fades(crossfade: f64, soft_fades: bool) {
+    let mut conn = acquire!().unwrap();
+    query!(
+        r#"
+            UPDATE config
+            SET crossfade=?1, soft_fades=?2
+            WHERE ROWID = 1
+            "#,
+        conn,
+        crossfade,
+        soft_fades
+    );
+}
+
+pub async fn get_fades() -> SavedFades {
+    let mut conn = acquire!().unwrap();
+
+    get_one!(
+        r#"
+            SELECT crossfade, soft_fades FROM config
+            WHERE ROWID = 1;
+            "#,
+        SavedFades,
+        conn
+    )
+    .unwrap()
+}
+
+pub async fn set_listenbrainz(token: Option<String>, api_root: Option<String>) {
+    let mut conn = acquire!().unwrap();
+    query!(
+        r#"
+            UPDATE config
+            SET listenbrainz_token=?1, listenbrainz_api_root=?2
+            WHERE ROWID = 1
+            "#,
+        conn,
+        token,
+        api_root
+    );
+}
+
+pub async fn set_lastfm(
+    api_key: Option<String>,
+    api_secret: Option<String>,
+    session_key: Option<String>,
+) {
+    let mut conn = acquire!().unwrap();
+    query!(
+        r#"
+            UPDATE config
+            SET lastfm_api_key=?1, lastfm_api_secret=?2, lastfm_session_key=?3
+            WHERE ROWID = 1
+            "#,
+        conn,
+        api_key,
+        api_secret,
+        session_key
+    );
+}
+
+pub async fn get_scrobbling() -> SavedScrobbling {
+    let mut conn = acquire!().unwrap();
+
+    get_one!(
+        r#"
+            SELECT listenbrainz_token, listenbrainz_api_root,
+                lastfm_api_key, lastfm_api_secret, lastfm_session_key
+            FROM config
+            WHERE ROWID = 1;
+            "#,
+        SavedScrobbling,
+        conn
+    )
+    .unwrap()
+}
+
+pub async fn add_scrobble(service: &str, scrobble: SavedScrobble) {
+    let mut conn = acquire!().unwrap();
+
+    let SavedScrobble {
+        id: _,
+        track_title,
+        artist_name,
+        album_title,
+        duration_seconds,
+        listened_at,
+    } = scrobble;
+
+    query!(
+        r#"
+            INSERT INTO scrobbles (
+                service, track_title, artist_name, album_title, duration_seconds, listened_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        conn,
+        service,
+        track_title,
+        artist_name,
+        album_title,
+        duration_seconds,
+        listened_at
+    );
+}
+
+/// The oldest scrobbles still waiting to be sent to a service.
+pub async fn get_scrobbles(service: &str, limit: i64) -> Vec<SavedScrobble> {
+    let mut conn = acquire!().unwrap();
+
+    get_all!(
+        r#"
+            SELECT id, track_title, artist_name, album_title, duration_seconds, listened_at
+            FROM scrobbles
+            WHERE service = ?1
+            ORDER BY id
+            LIMIT ?2;
+            "#,
+        SavedScrobble,
+        conn,
+        service,
+        limit
+    )
+    .unwrap()
+}
+
+/// Removes the scrobbles of a service up to and including `last_id`.
+pub async fn remove_scrobbles(service: &str, last_id: i64) {
+    let mut conn = acquire!().unwrap();
+    query!(
+        r#"
+            DELETE FROM scrobbles
+            WHERE service = ?1 AND id <= ?2
+            "#,
+        conn,
+        service,
+        last_id
+    );
+}
+
+pub async fn set_output(output: String) {
+    let mut conn = acquire!().unwrap();
+    query!(
+        r#"
+            UPDATE config
+            SET output=?1
+            WHERE ROWID = 1
+            "#,
+        conn,
+        output
+    );
+}
+
+pub async fn get_output() -> SavedOutput {
+    let mut conn = acquire!().unwrap();
+
+    get_one!(
+        r#"
+            SELECT output FROM config
+            WHERE ROWID = 1;
+            "#,
+        SavedOutput,
+        conn
+    )
+    .unwrap()
+}
+
+pub async fn set_equalizer(output: String, bands: String) {
+    let mut conn = acquire!().unwrap();
+    query!(
+        r#"
+            INSERT INTO equalizer (output, bands) VALUES (?1, ?2)
+            ON CONFLICT(output) DO UPDATE SET bands=?2
+            "#,
+        conn,
+        output,
+        bands
+    );
+}
+
+pub async fn get_equalizers() -> Vec<SavedEqualizer> {
+    let mut conn = acquire!().unwrap();
+
+    get_all!(
+        r#"
+            SELECT output, bands FROM equalizer;
+            "#,
+        SavedEqualizer,
+        conn
+    )
+    .unwrap()
+}
+
+pub async fn create_session(zone: &str) {
+    let mut conn = acquire!().unwrap();
+    query!(
+        r#"
+            INSERT OR IGNORE INTO session (zone) VALUES (?1);
+            "#,
+        conn,
+        zone
+    );
+}
+
+pub async fn set_tracklist(zone: &str, tracklist: String) {
+    let mut conn = acquire!().unwrap();
+    query!(
+        r#"
+            UPDATE session
+            SET tracklist=?2
+            WHERE zone = ?1
+            "#,
+        conn,
+        zone,
+        tracklist
+    );
+}
+
+pub async fn set_position(zone: &str, position: i64) {
+    let mut conn = acquire!().unwrap();
+    query!(
+        r#"
+            UPDATE session
+            SET position=?2
+            WHERE zone = ?1
+            "#,
+        conn,
+        zone,
+        position
+    );
+}
+
+pub async fn set_volume(zone: &str, volume: f64) {
+    let mut conn = acquire!().unwrap();
+    query!(
+        r#"
+            UPDATE session
+            SET volume=?2
+            WHERE zone = ?1
+            "#,
+        conn,
+        zone,
+        volume
+    );
+}
+
+pub async fn set_session_output(zone: &str, output: String) {
+    let mut conn = acquire!().unwrap();
+    query!(
+        r#"
+            UPDATE session
+            SET output=?2
+            WHERE zone = ?1
+            "#,
+        conn,
+        zone,
+        output
+    );
+}
+
+pub async fn set_bit_perfect(zone: &str, bit_perfect: bool) {
+    let mut conn = acquire!().unwrap();
+    query!(
+        r#"
+            UPDATE session
+            SET bit_perfect=?2
+            WHERE zone = ?1
+            "#,
+        conn,
+        zone,
+        bit_perfect
+    );
+}
+
+pub async fn get_session(zone: &str) -> SavedSession {
+    let mut conn = acquire!().unwrap();
+
+    get_one!(
+        r#"
+            SELECT tracklist, position, volume, output, bit_perfect FROM session
+            WHERE zone = ?1;
+            "#,
+        SavedSession,
+        conn,
+        zone
+    )
+    .unwrap()
+}
+
+pub async fn add_play(play: SavedPlay) {
+    let mut conn = acquire!().unwrap();
+
+    let SavedPlay {
+        track_id,
+        track_title,
+        album_id,
+        album_title,
+        artist_id,
+        artist_name,
+        started_at,
+        listened_ms,
+        skipped,
+    } = play;
+
+    query!(
+        r#"
+            INSERT INTO plays (
+                track_id, track_title, album_id, album_title, artist_id, artist_name,
+                started_at, listened_ms, skipped
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            "#,
+        conn,
+        track_id,
+        track_title,
+        album_id,
+        album_title,
+        artist_id,
+        artist_name,
+        started_at,
+        listened_ms,
+        skipped
+    );
+}
+
+pub async fn get_recent_plays(limit: i64) -> Vec<SavedPlay> {
+    let mut conn = acquire!().unwrap();
+
+    get_all!(
+        r#"
+            SELECT track_id, track_title, album_id, album_title, artist_id, artist_name,
+                started_at, listened_ms, skipped
+            FROM plays
+            ORDER BY started_at DESC
+            LIMIT ?1;
+            "#,
+        SavedPlay,
+        conn,
+        limit
+    )
+    .unwrap()
+}
+
+pub async fn get_play_totals(since: i64) -> SavedPlayTotals {
+    let mut conn = acquire!().unwrap();
+
+    get_one!(
+        r#"
+            SELECT COUNT(*) AS "plays!: i64", COALESCE(SUM(listened_ms), 0) AS "listened_ms!: i64"
+            FROM plays
+            WHERE started_at >= ?1;
+            "#,
+        SavedPlayTotals,
+        conn,
+        since
+    )
+    .unwrap()
+}
+
+pub async fn get_top_artists(since: i64, limit: i64) -> Vec<SavedPlayCount> {
+    let mut conn = acquire!().unwrap();
+
+    get_all!(
+        r#"
+            SELECT CAST(artist_id AS TEXT) AS "id!: String", MAX(artist_name) AS "name!: String",
+                COUNT(*) AS "plays!: i64", SUM(listened_ms) AS "listened_ms!: i64"
+            FROM plays
+            WHERE started_at >= ?1 AND artist_id IS NOT NULL
+            GROUP BY artist_id
+            ORDER BY 4 DESC
+            LIMIT ?2;
+            "#,
+        SavedPlayCount,
+        conn,
+        since,
+        limit
+    )
+    .unwrap()
+}
+
+pub async fn get_top_albums(since: i64, limit: i64) -> Vec<SavedPlayCount> {
+    let mut conn = acquire!().unwrap();
+
+    get_all!(
+        r#"
+            SELECT album_id AS "id!: String", MAX(album_title) AS "name!: String",
+                COUNT(*) AS "plays!: i64", SUM(listened_ms) AS "listened_ms!: i64"
+            FROM plays
+            WHERE started_at >= ?1 AND album_id IS NOT NULL
+            GROUP BY album_id
+            ORDER BY 4 DESC
+            LIMIT ?2;
+            "#,
+        SavedPlayCount,
+        conn,
+        since,
+        limit
+    )
+    .unwrap()
+}
+
+pub async fn get_top_tracks(since: i64, limit: i64) -> Vec<SavedPlayCount> {
+    let mut conn = acquire!().unwrap();
+
+    get_all!(
+        r#"
+            SELECT CAST(track_id AS TEXT) AS "id!: String", MAX(track_title) AS "name!: String",
+                COUNT(*) AS "plays!: i64", SUM(listened_ms) AS "listened_ms!: i64"
+            FROM plays
+            WHERE started_at >= ?1
+            GROUP BY track_id
+            ORDER BY 4 DESC
+            LIMIT ?2;
+            "#,
+        SavedPlayCount,
+        conn,
+        since,
+        limit
+    )
+    .unwrap()
+}
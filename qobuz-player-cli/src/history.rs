@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use qobuz_player_controls::history::{Count, HistoryStore, ListeningStats, Play};
+use std::time::Duration;
+
+use crate::database::{self, SavedPlay, SavedPlayCount};
+
+/// Keeps the listening history in the database.
+pub struct DatabaseHistory;
+
+impl HistoryStore for DatabaseHistory {
+    fn record(&self, play: Play) -> BoxFuture<'_, ()> {
+        Box::pin(database::add_play(SavedPlay {
+            track_id: play.track_id.into(),
+            track_title: play.track_title,
+            album_id: play.album_id,
+            album_title: play.album_title,
+            artist_id: play.artist_id.map(i64::from),
+            artist_name: play.artist_name,
+            started_at: play.started_at.timestamp(),
+            listened_ms: play.listened.as_millis() as i64,
+            skipped: play.skipped,
+        }))
+    }
+
+    fn recently_played(&self, limit: u32) -> BoxFuture<'_, Vec<Play>> {
+        Box::pin(async move {
+            database::get_recent_plays(limit.into())
+                .await
+                .into_iter()
+                .map(|saved| Play {
+                    track_id: saved.track_id as u32,
+                    track_title: saved.track_title,
+                    album_id: saved.album_id,
+                    album_title: saved.album_title,
+                    artist_id: saved.artist_id.map(|id| id as u32),
+                    artist_name: saved.artist_name,
+                    started_at: DateTime::from_timestamp(saved.started_at, 0).unwrap_or_default(),
+                    listened: Duration::from_millis(saved.listened_ms as u64),
+                    skipped: saved.skipped,
+                })
+                .collect()
+        })
+    }
+
+    fn stats(&self, since: Option<DateTime<Utc>>, limit: u32) -> BoxFuture<'_, ListeningStats> {
+        let since = since.map_or(0, |since| since.timestamp());
+        let limit = limit.into();
+
+        Box::pin(async move {
+            let totals = database::get_play_totals(since).await;
+
+            ListeningStats {
+                plays: totals.plays as u32,
+                listened: Duration::from_millis(totals.listened_ms as u64),
+                top_artists: counts(database::get_top_artists(since, limit).await),
+                top_albums: counts(database::get_top_albums(since, limit).await),
+                top_tracks: counts(database::get_top_tracks(since, limit).await),
+            }
+        })
+    }
+}
+
+fn counts(saved: Vec<SavedPlayCount>) -> Vec<Count> {
+    saved
+        .into_iter()
+        .map(|saved| Count {
+            id: saved.id,
+            name: saved.name,
+            plays: saved.plays as u32,
+            listened: Duration::from_millis(saved.listened_ms as u64),
+        })
+        .collect()
+}
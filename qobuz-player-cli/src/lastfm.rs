@@ -0,0 +1,181 @@
+use futures::future::BoxFuture;
+use reqwest::Client;
+use serde_json::Value;
+use snafu::prelude::*;
+use std::collections::BTreeMap;
+
+use crate::scrobble::{
+    Error, Listen, RejectedSnafu, Scrobbler, UnauthorizedSnafu, UnavailableSnafu, UnreachableSnafu,
+};
+
+const SERVICE: &str = "Last.fm";
+
+const API_ROOT: &str = "https://ws.audioscrobbler.com/2.0/";
+
+/// Scrobbles to Last.fm with a session key from [`LastFm::session_key`].
+pub struct LastFm {
+    client: Client,
+    api_key: String,
+    api_secret: String,
+    session_key: String,
+}
+
+impl LastFm {
+    pub fn new(api_key: String, api_secret: String, session_key: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            api_secret,
+            session_key,
+        }
+    }
+
+    /// Logs in to get a session key, which doesn't expire and is kept instead of the password.
+    pub async fn session_key(
+        api_key: &str,
+        api_secret: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<String, Error> {
+        let mut params = params("auth.getMobileSession");
+        params.insert("username".to_string(), username.to_string());
+        params.insert("password".to_string(), password.to_string());
+
+        let response = call(&Client::new(), api_key, api_secret, params).await?;
+
+        response["session"]["key"]
+            .as_str()
+            .map(str::to_string)
+            .context(RejectedSnafu {
+                service: SERVICE,
+                message: "no session key in the response",
+            })
+    }
+
+    async fn call(&self, mut params: BTreeMap<String, String>) -> Result<(), Error> {
+        params.insert("sk".to_string(), self.session_key.clone());
+
+        call(&self.client, &self.api_key, &self.api_secret, params)
+            .await
+            .map(|_| ())
+    }
+}
+
+impl Scrobbler for LastFm {
+    fn service(&self) -> &'static str {
+        SERVICE
+    }
+
+    fn batch_size(&self) -> usize {
+        50
+    }
+
+    fn now_playing<'a>(&'a self, listen: &'a Listen) -> BoxFuture<'a, Result<(), Error>> {
+        let mut params = params("track.updateNowPlaying");
+        params.insert("artist".to_string(), listen.artist_name.clone());
+        params.insert("track".to_string(), listen.track_title.clone());
+        params.insert("duration".to_string(), listen.duration_seconds.to_string());
+
+        if let Some(album_title) = &listen.album_title {
+            params.insert("album".to_string(), album_title.clone());
+        }
+
+        Box::pin(self.call(params))
+    }
+
+    fn submit<'a>(&'a self, listens: &'a [Listen]) -> BoxFuture<'a, Result<(), Error>> {
+        let mut params = params("track.scrobble");
+
+        // Batched parameters are numbered, like artist[0], artist[1] and so on.
+        for (index, listen) in listens.iter().enumerate() {
+            params.insert(format!("artist[{index}]"), listen.artist_name.clone());
+            params.insert(format!("track[{index}]"), listen.track_title.clone());
+            params.insert(
+                format!("timestamp[{index}]"),
+                listen.listened_at.to_string(),
+            );
+            params.insert(
+                format!("duration[{index}]"),
+                listen.duration_seconds.to_string(),
+            );
+
+            if let Some(album_title) = &listen.album_title {
+                params.insert(format!("album[{index}]"), album_title.clone());
+            }
+        }
+
+        Box::pin(self.call(params))
+    }
+}
+
+fn params(method: &str) -> BTreeMap<String, String> {
+    BTreeMap::from([("method".to_string(), method.to_string())])
+}
+
+/// Posts a signed call and returns the response, turning Last.fm error codes into errors.
+async fn call(
+    client: &Client,
+    api_key: &str,
+    api_secret: &str,
+    mut params: BTreeMap<String, String>,
+) -> Result<Value, Error> {
+    params.insert("api_key".to_string(), api_key.to_string());
+
+    let signature = params
+        .iter()
+        .fold(String::new(), |signature, (name, value)| {
+            signature + name + value
+        })
+        + api_secret;
+    params.insert(
+        "api_sig".to_string(),
+        format!("{:x}", md5::compute(signature)),
+    );
+    params.insert("format".to_string(), "json".to_string());
+
+    let response = client
+        .post(API_ROOT)
+        .form(&params)
+        .send()
+        .await
+        .context(UnreachableSnafu { service: SERVICE })?;
+
+    let status = response.status();
+    let body = response.json::<Value>().await.unwrap_or_default();
+
+    let Some(code) = body["error"].as_u64() else {
+        if status.is_server_error() {
+            return UnavailableSnafu {
+                service: SERVICE,
+                message: status.to_string(),
+            }
+            .fail();
+        }
+
+        return Ok(body);
+    };
+
+    let message = body["message"]
+        .as_str()
+        .map_or_else(|| format!("error {code}"), str::to_string);
+
+    match code {
+        // Authentication failed, invalid session key, invalid API key, unauthorized token or suspended API key.
+        4 | 9 | 10 | 14 | 26 => UnauthorizedSnafu {
+            service: SERVICE,
+            message,
+        }
+        .fail(),
+        // Operation failed, service offline, temporarily unavailable or rate limited.
+        8 | 11 | 16 | 29 => UnavailableSnafu {
+            service: SERVICE,
+            message,
+        }
+        .fail(),
+        _ => RejectedSnafu {
+            service: SERVICE,
+            message,
+        }
+        .fail(),
+    }
+}
@@ -5,3 +5,7 @@ extern crate tracing;
 #[macro_use]
 pub mod cli;
 mod database;
+mod history;
+mod lastfm;
+mod listenbrainz;
+mod scrobble;
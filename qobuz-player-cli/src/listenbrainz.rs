@@ -0,0 +1,138 @@
+use futures::future::BoxFuture;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use snafu::prelude::*;
+
+use crate::scrobble::{
+    Error, Listen, RejectedSnafu, Scrobbler, UnauthorizedSnafu, UnavailableSnafu, UnreachableSnafu,
+};
+
+const SERVICE: &str = "ListenBrainz";
+
+const DEFAULT_API_ROOT: &str = "https://api.listenbrainz.org";
+
+/// Submits listens to ListenBrainz, or to another server with the same API.
+pub struct ListenBrainz {
+    client: Client,
+    token: String,
+    api_root: String,
+}
+
+impl ListenBrainz {
+    pub fn new(token: String, api_root: Option<String>) -> Self {
+        let api_root = api_root
+            .as_deref()
+            .unwrap_or(DEFAULT_API_ROOT)
+            .trim_end_matches('/')
+            .to_string();
+
+        Self {
+            client: Client::new(),
+            token,
+            api_root,
+        }
+    }
+
+    async fn submit_listens(&self, listen_type: &str, payload: Vec<Value>) -> Result<(), Error> {
+        let response = self
+            .client
+            .post(format!("{}/1/submit-listens", self.api_root))
+            .header("Authorization", format!("Token {}", self.token))
+            .json(&json!({ "listen_type": listen_type, "payload": payload }))
+            .send()
+            .await
+            .context(UnreachableSnafu { service: SERVICE })?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let message = response
+            .json::<Value>()
+            .await
+            .ok()
+            .and_then(|body| body["error"].as_str().map(str::to_string))
+            .unwrap_or_else(|| status.to_string());
+
+        match status {
+            StatusCode::UNAUTHORIZED => UnauthorizedSnafu {
+                service: SERVICE,
+                message,
+            }
+            .fail(),
+            StatusCode::TOO_MANY_REQUESTS => UnavailableSnafu {
+                service: SERVICE,
+                message,
+            }
+            .fail(),
+            status if status.is_server_error() => UnavailableSnafu {
+                service: SERVICE,
+                message,
+            }
+            .fail(),
+            _ => RejectedSnafu {
+                service: SERVICE,
+                message,
+            }
+            .fail(),
+        }
+    }
+}
+
+impl Scrobbler for ListenBrainz {
+    fn service(&self) -> &'static str {
+        SERVICE
+    }
+
+    fn batch_size(&self) -> usize {
+        100
+    }
+
+    fn now_playing<'a>(&'a self, listen: &'a Listen) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(self.submit_listens(
+            "playing_now",
+            vec![json!({ "track_metadata": track_metadata(listen) })],
+        ))
+    }
+
+    fn submit<'a>(&'a self, listens: &'a [Listen]) -> BoxFuture<'a, Result<(), Error>> {
+        let listen_type = if listens.len() == 1 {
+            "single"
+        } else {
+            "import"
+        };
+
+        let payload = listens
+            .iter()
+            .map(|listen| {
+                json!({
+                    "listened_at": listen.listened_at,
+                    "track_metadata": track_metadata(listen),
+                })
+            })
+            .collect();
+
+        Box::pin(self.submit_listens(listen_type, payload))
+    }
+}
+
+fn track_metadata(listen: &Listen) -> Value {
+    let mut metadata = json!({
+        "artist_name": listen.artist_name,
+        "track_name": listen.track_title,
+        "additional_info": {
+            "duration_ms": u64::from(listen.duration_seconds) * 1000,
+            "media_player": "qobuz-player",
+            "submission_client": "qobuz-player",
+            "submission_client_version": env!("CARGO_PKG_VERSION"),
+            "music_service": "qobuz.com",
+        },
+    });
+
+    if let Some(album_title) = &listen.album_title {
+        metadata["release_name"] = json!(album_title);
+    }
+
+    metadata
+}
@@ -0,0 +1,297 @@
+use chrono::Utc;
+use futures::future::BoxFuture;
+use qobuz_player_controls::{models::Track, notification::Notification, ClockTime, Player};
+use snafu::prelude::*;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::{
+    broadcast::{error::RecvError, Receiver},
+    Mutex,
+};
+
+use crate::{
+    database::{self, SavedScrobble},
+    lastfm::LastFm,
+    listenbrainz::ListenBrainz,
+};
+
+/// Tracks shorter than this are never scrobbled.
+const MIN_DURATION: Duration = Duration::from_secs(30);
+
+/// A listen is scrobbled after half the track or this long, whichever comes first.
+const MAX_THRESHOLD: Duration = Duration::from_secs(240);
+
+/// Position jumps longer than this are seeks, not listening.
+const MAX_POSITION_STEP: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum Error {
+    #[snafu(display("{service} could not be reached: {source}"))]
+    Unreachable {
+        service: &'static str,
+        source: reqwest::Error,
+    },
+    #[snafu(display("{service} is unavailable: {message}"))]
+    Unavailable {
+        service: &'static str,
+        message: String,
+    },
+    #[snafu(display("{service} did not accept the credentials: {message}"))]
+    Unauthorized {
+        service: &'static str,
+        message: String,
+    },
+    #[snafu(display("{service} rejected the submission: {message}"))]
+    Rejected {
+        service: &'static str,
+        message: String,
+    },
+}
+
+/// A track listened to, as sent to scrobbling services.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listen {
+    pub track_title: String,
+    pub artist_name: String,
+    pub album_title: Option<String>,
+    pub duration_seconds: u32,
+    /// When the track started playing, in unix seconds.
+    pub listened_at: i64,
+}
+
+impl From<SavedScrobble> for Listen {
+    fn from(saved: SavedScrobble) -> Self {
+        Self {
+            track_title: saved.track_title,
+            artist_name: saved.artist_name,
+            album_title: saved.album_title,
+            duration_seconds: saved.duration_seconds as u32,
+            listened_at: saved.listened_at,
+        }
+    }
+}
+
+/// A service listens are submitted to.
+pub trait Scrobbler: Send + Sync {
+    /// Names the service in messages and in the offline buffer.
+    fn service(&self) -> &'static str;
+
+    /// The most listens sent in one submission.
+    fn batch_size(&self) -> usize;
+
+    fn now_playing<'a>(&'a self, listen: &'a Listen) -> BoxFuture<'a, Result<(), Error>>;
+
+    fn submit<'a>(&'a self, listens: &'a [Listen]) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// The configured scrobblers, each sending its buffered listens one batch at a time.
+pub struct Scrobblers(Vec<(Box<dyn Scrobbler>, Mutex<()>)>);
+
+impl Scrobblers {
+    /// The scrobblers with credentials in the database.
+    pub async fn configured() -> Self {
+        let saved = database::get_scrobbling().await;
+        let mut scrobblers: Vec<Box<dyn Scrobbler>> = vec![];
+
+        if let Some(token) = saved.listenbrainz_token {
+            scrobblers.push(Box::new(ListenBrainz::new(
+                token,
+                saved.listenbrainz_api_root,
+            )));
+        }
+
+        if let (Some(api_key), Some(api_secret), Some(session_key)) = (
+            saved.lastfm_api_key,
+            saved.lastfm_api_secret,
+            saved.lastfm_session_key,
+        ) {
+            scrobblers.push(Box::new(LastFm::new(api_key, api_secret, session_key)));
+        }
+
+        Self(
+            scrobblers
+                .into_iter()
+                .map(|scrobbler| (scrobbler, Mutex::new(())))
+                .collect(),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    async fn now_playing(&self, listen: &Listen) {
+        for (scrobbler, _) in &self.0 {
+            if let Err(error) = scrobbler.now_playing(listen).await {
+                debug!("now playing not sent: {error}");
+            }
+        }
+    }
+
+    /// Buffers a listen for every service, then sends what they have buffered.
+    async fn scrobble(&self, listen: Listen) {
+        for (scrobbler, _) in &self.0 {
+            database::add_scrobble(
+                scrobbler.service(),
+                SavedScrobble {
+                    id: 0,
+                    track_title: listen.track_title.clone(),
+                    artist_name: listen.artist_name.clone(),
+                    album_title: listen.album_title.clone(),
+                    duration_seconds: listen.duration_seconds.into(),
+                    listened_at: listen.listened_at,
+                },
+            )
+            .await;
+        }
+
+        self.flush().await;
+    }
+
+    /// Sends buffered listens until every service is done or can't be reached.
+    /// Listens a service rejects are dropped, as sending them again would fail the same way.
+    pub async fn flush(&self) {
+        for (scrobbler, flushing) in &self.0 {
+            let _flushing = flushing.lock().await;
+            let service = scrobbler.service();
+
+            loop {
+                let saved = database::get_scrobbles(service, scrobbler.batch_size() as i64).await;
+                let Some(last_id) = saved.last().map(|saved| saved.id) else {
+                    break;
+                };
+
+                let listens: Vec<Listen> = saved.into_iter().map(Listen::from).collect();
+
+                match scrobbler.submit(&listens).await {
+                    Ok(()) => {
+                        debug!("{} listens sent to {service}", listens.len());
+                    }
+                    Err(error @ Error::Rejected { .. }) => {
+                        error!("dropping {} listens: {error}", listens.len());
+                    }
+                    Err(error) => {
+                        debug!("keeping listens for later: {error}");
+                        break;
+                    }
+                }
+
+                database::remove_scrobbles(service, last_id).await;
+            }
+        }
+    }
+}
+
+/// The track playing and how long it has been listened to.
+struct Playing {
+    track_id: u32,
+    listen: Option<Listen>,
+    listened: Duration,
+    position: ClockTime,
+    scrobbled: bool,
+}
+
+impl Playing {
+    fn threshold(&self) -> Option<Duration> {
+        let duration = Duration::from_secs(self.listen.as_ref()?.duration_seconds.into());
+
+        (duration >= MIN_DURATION).then(|| (duration / 2).min(MAX_THRESHOLD))
+    }
+
+    /// Starts a new listen of the same track.
+    fn restart(&mut self, scrobblers: &Arc<Scrobblers>) {
+        self.listened = Duration::ZERO;
+        self.scrobbled = false;
+
+        if let Some(listen) = self.listen.as_mut() {
+            listen.listened_at = Utc::now().timestamp();
+            send_now_playing(scrobblers, listen.clone());
+        }
+    }
+}
+
+/// Sends now playing when a track starts and scrobbles it once it has been listened to long enough.
+pub async fn scrobble(
+    player: &Player,
+    scrobblers: Arc<Scrobblers>,
+    mut receiver: Receiver<Notification>,
+) {
+    let mut playing: Option<Playing> = None;
+
+    loop {
+        match receiver.recv().await {
+            Ok(Notification::TrackChanged { track }) => {
+                let track_id = track.as_ref().map(|track| track.id);
+
+                if track_id == playing.as_ref().map(|playing| playing.track_id) {
+                    continue;
+                }
+
+                playing = track.map(|track| start(player, &scrobblers, track));
+            }
+            Ok(Notification::Position { clock }) => {
+                let Some(playing) = playing.as_mut() else {
+                    continue;
+                };
+
+                // Played again from the start, like on repeat or when queued twice in a row,
+                // which doesn't change the current track.
+                let (position, previous) =
+                    (Duration::from(clock), Duration::from(playing.position));
+                if position < MAX_POSITION_STEP && previous > position + MAX_POSITION_STEP {
+                    playing.restart(&scrobblers);
+                }
+
+                let step = Duration::from(clock.saturating_sub(playing.position));
+                playing.position = clock;
+
+                if playing.scrobbled || step > MAX_POSITION_STEP {
+                    continue;
+                }
+
+                playing.listened += step;
+
+                if let Some(threshold) = playing.threshold() {
+                    if playing.listened >= threshold {
+                        playing.scrobbled = true;
+
+                        if let Some(listen) = playing.listen.clone() {
+                            let scrobblers = scrobblers.clone();
+                            tokio::spawn(async move { scrobblers.scrobble(listen).await });
+                        }
+                    }
+                }
+            }
+            Ok(Notification::Quit) | Err(RecvError::Closed) => break,
+            Ok(_) | Err(RecvError::Lagged(_)) => {}
+        }
+    }
+}
+
+fn start(player: &Player, scrobblers: &Arc<Scrobblers>, track: Track) -> Playing {
+    let track_id = track.id;
+    let listen = track.artist.map(|artist| Listen {
+        track_title: track.title,
+        artist_name: artist.name,
+        album_title: track.album.map(|album| album.title),
+        duration_seconds: track.duration_seconds,
+        listened_at: Utc::now().timestamp(),
+    });
+
+    if let Some(listen) = listen.clone() {
+        send_now_playing(scrobblers, listen);
+    }
+
+    Playing {
+        track_id,
+        listen,
+        listened: Duration::ZERO,
+        position: player.position().unwrap_or_default(),
+        scrobbled: false,
+    }
+}
+
+fn send_now_playing(scrobblers: &Arc<Scrobblers>, listen: Listen) {
+    let scrobblers = scrobblers.clone();
+    tokio::spawn(async move { scrobblers.now_playing(&listen).await });
+}
@@ -33,6 +33,16 @@ pub struct Client {
     user_id: i64,
 }
 
+/// A track stream being downloaded.
+pub struct Download(Response);
+
+impl Download {
+    /// The next part of the stream, or `None` once it is complete.
+    pub async fn chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        Ok(self.0.chunk().await?.map(|chunk| chunk.to_vec()))
+    }
+}
+
 pub async fn new(username: &str, password: &str) -> Result<Client> {
     let mut headers = HeaderMap::new();
     headers.insert(
@@ -144,6 +154,7 @@ macro_rules! get {
                     message: error.to_string(),
                 }),
             },
+            Err(Error::Unreachable) => Err(Error::Unreachable),
             Err(error) => Err(Error::Api {
                 message: error.to_string(),
             }),
@@ -160,6 +171,7 @@ macro_rules! post {
                     message: error.to_string(),
                 }),
             },
+            Err(Error::Unreachable) => Err(Error::Unreachable),
             Err(error) => Err(Error::Api {
                 message: error.to_string(),
             }),
@@ -230,17 +242,10 @@ impl Client {
             ("playlist_id", id_string.as_str()),
             ("offset", "0"),
         ];
-        let playlist: Result<Playlist> = get!(self, &endpoint, Some(&params));
-
-        if let Ok(mut playlist) = playlist {
-            self.playlist_items(&mut playlist, &endpoint).await?;
+        let mut playlist: Playlist = get!(self, &endpoint, Some(&params))?;
+        self.playlist_items(&mut playlist, &endpoint).await?;
 
-            Ok(playlist)
-        } else {
-            Err(Error::Api {
-                message: "error fetching playlist".to_string(),
-            })
-        }
+        Ok(playlist)
     }
 
     async fn playlist_items(&self, playlist: &mut Playlist, endpoint: &str) -> Result<()> {
@@ -384,6 +389,14 @@ impl Client {
         .map(|u| u.url)
     }
 
+    /// Starts downloading the stream of a track, see [`Download::chunk`].
+    pub async fn download(&self, track_id: u32) -> Result<Download> {
+        let url = self.track_url(track_id).await?;
+        let response = self.client.get(url).send().await?.error_for_status()?;
+
+        Ok(Download(response))
+    }
+
     pub async fn favorites(&self, limit: i32) -> Result<Favorites> {
         let endpoint = format!("{}{}", self.base_url, Endpoint::Favorites);
 
@@ -548,6 +561,7 @@ impl Client {
                     message: error.to_string(),
                 }),
             },
+            Err(Error::Unreachable) => Err(Error::Unreachable),
             Err(error) => Err(Error::Api {
                 message: error.to_string(),
             }),
@@ -652,6 +666,7 @@ async fn track_url(
                 message: error.to_string(),
             }),
         },
+        Err(Error::Unreachable) => Err(Error::Unreachable),
         Err(error) => Err(Error::Api {
             message: error.to_string(),
         }),
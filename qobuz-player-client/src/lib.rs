@@ -25,12 +25,18 @@ pub enum Error {
     Create,
     #[snafu(display("{message}"))]
     Api { message: String },
+    #[snafu(display("Qobuz could not be reached."))]
+    Unreachable,
     #[snafu(display("Failed to deserialize json: {message}"))]
     DeserializeJSON { message: String },
 }
 
 impl From<reqwest::Error> for Error {
     fn from(error: reqwest::Error) -> Self {
+        if error.is_connect() || error.is_timeout() {
+            return Error::Unreachable;
+        }
+
         let status = error.status();
 
         match status {
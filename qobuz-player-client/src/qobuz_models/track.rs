@@ -28,6 +28,7 @@ pub struct Track {
     pub parental_warning: bool,
     pub performer: Option<Performer>,
     pub performers: Option<String>,
+    pub playlist_track_id: Option<i64>,
     pub position: Option<usize>,
     pub previewable: bool,
     pub purchasable: bool,
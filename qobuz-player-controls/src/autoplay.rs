@@ -0,0 +1,44 @@
+use std::collections::VecDeque;
+
+/// How many albums and tracks autoplay remembers picking.
+const HISTORY_LENGTH: usize = 200;
+
+/// How many tracks autoplay adds at a time when the queue isn't an album.
+pub(crate) const AUTOPLAY_TRACKS: usize = 5;
+
+/// What autoplay added recently, so it doesn't come back to the same music.
+#[derive(Debug, Default)]
+pub(crate) struct AutoplayHistory {
+    albums: VecDeque<String>,
+    tracks: VecDeque<u32>,
+}
+
+impl AutoplayHistory {
+    pub(crate) fn add_album(&mut self, id: &str) {
+        if !self.has_album(id) {
+            push_bounded(&mut self.albums, id.to_string());
+        }
+    }
+
+    pub(crate) fn add_track(&mut self, id: u32) {
+        if !self.has_track(id) {
+            push_bounded(&mut self.tracks, id);
+        }
+    }
+
+    pub(crate) fn has_album(&self, id: &str) -> bool {
+        self.albums.iter().any(|album| album == id)
+    }
+
+    pub(crate) fn has_track(&self, id: u32) -> bool {
+        self.tracks.contains(&id)
+    }
+}
+
+fn push_bounded<T>(items: &mut VecDeque<T>, item: T) {
+    if items.len() == HISTORY_LENGTH {
+        items.pop_front();
+    }
+
+    items.push_back(item);
+}
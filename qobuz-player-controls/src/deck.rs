@@ -0,0 +1,189 @@
+use gstreamer::{prelude::*, Element, Structure};
+use std::{
+    f64::consts::FRAC_PI_2,
+    str::FromStr,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+use tokio::sync::broadcast::Sender;
+use tracing::debug;
+
+use crate::{equalizer::Equalizer, Result};
+
+static USER_AGENTS: &[&str] = &[
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 13_4) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36"
+];
+
+/// Time between volume changes while fading.
+const FADE_STEP: Duration = Duration::from_millis(20);
+
+/// A playbin with its own audio filter. Players have two of them so the end of
+/// one track can keep playing on one deck while the next one starts on the other.
+pub(crate) struct Deck {
+    pub(crate) playbin: Element,
+    audio_filter: Element,
+    equalizer: Element,
+    replay_gain: Element,
+    fade: Element,
+    /// Bumped by every fade, so a running fade stops when another one starts.
+    fade_generation: AtomicU64,
+    fade_in_pending: AtomicBool,
+}
+
+impl Deck {
+    /// Builds a playbin that reports `about-to-finish` with its index.
+    pub(crate) fn new(index: usize, about_to_finish: Sender<usize>) -> Result<Self> {
+        let playbin = gstreamer::ElementFactory::make("playbin3").build()?;
+
+        playbin.set_property_from_str("flags", "audio+buffering");
+
+        if gstreamer::version().1 >= 22 {
+            playbin.connect("element-setup", false, |value| {
+                let element = &value[1].get::<gstreamer::Element>().unwrap();
+
+                if element.name().contains("urisourcebin") {
+                    element.set_property("parse-streams", true);
+                }
+
+                None
+            });
+        }
+
+        playbin.connect("source-setup", false, |value| {
+            let element = &value[1].get::<gstreamer::Element>().unwrap();
+
+            if element.name().contains("souphttpsrc") {
+                debug!("new source, changing settings");
+                let ua = if rand::random() {
+                    USER_AGENTS[0]
+                } else {
+                    USER_AGENTS[1]
+                };
+                element.set_property("user-agent", ua);
+                element.set_property("compress", true);
+                element.set_property("retries", 10);
+                element.set_property("timeout", 30_u32);
+                element.set_property(
+                    "extra-headers",
+                    Structure::from_str(
+                        "a-structure, DNT=1, Pragma=no-cache, Cache-Control=no-cache",
+                    )
+                    .expect("failed to make structure from string"),
+                )
+            }
+
+            None
+        });
+
+        // The equalizer only takes float samples, the converter keeps any decoder output working.
+        let audio_filter = gstreamer::parse::bin_from_description(
+            "audioconvert ! equalizer-10bands name=equalizer ! volume name=replaygain ! volume name=fade",
+            true,
+        )?;
+        let equalizer = audio_filter
+            .by_name("equalizer")
+            .expect("audio filter has an equalizer");
+        let replay_gain = audio_filter
+            .by_name("replaygain")
+            .expect("audio filter has a replaygain volume");
+        let fade = audio_filter
+            .by_name("fade")
+            .expect("audio filter has a fade volume");
+        let audio_filter = audio_filter.upcast::<Element>();
+        playbin.set_property("audio-filter", &audio_filter);
+
+        playbin.add_property_deep_notify_watch(Some("caps"), true);
+
+        // Lets the player setup the next track to play. Enables gapless playback.
+        playbin.connect("about-to-finish", false, move |_| {
+            debug!("about to finish");
+            about_to_finish
+                .send(index)
+                .expect("failed to send about to finish message");
+
+            None
+        });
+
+        Ok(Self {
+            playbin,
+            audio_filter,
+            equalizer,
+            replay_gain,
+            fade,
+            fade_generation: AtomicU64::new(0),
+            fade_in_pending: AtomicBool::new(false),
+        })
+    }
+
+    /// Plays to a new sink. The playbin has to be stopped or ready.
+    pub(crate) fn set_output(&self, sink: &Element, bit_perfect: bool) {
+        self.playbin.set_property("audio-sink", sink);
+
+        if bit_perfect {
+            // Without converters the sink has to accept the decoded format as is.
+            self.playbin
+                .set_property_from_str("flags", "audio+buffering+native-audio");
+            self.playbin.set_property("audio-filter", None::<&Element>);
+        } else {
+            self.playbin
+                .set_property_from_str("flags", "audio+buffering");
+            self.playbin
+                .set_property("audio-filter", &self.audio_filter);
+        }
+    }
+
+    pub(crate) fn set_equalizer(&self, equalizer: &Equalizer) {
+        for (band, gain) in equalizer.bands.into_iter().enumerate() {
+            self.equalizer.set_property(&format!("band{band}"), gain);
+        }
+    }
+
+    pub(crate) fn set_replay_gain(&self, volume: f64) {
+        self.replay_gain.set_property("volume", volume);
+    }
+
+    /// Sets the fade volume right away, stopping any running fade.
+    pub(crate) fn set_fade_volume(&self, volume: f64) {
+        self.fade_generation.fetch_add(1, Ordering::Relaxed);
+        self.fade_in_pending.store(false, Ordering::Relaxed);
+        self.fade.set_property("volume", volume);
+    }
+
+    /// Starts silent and fades in once [`Deck::take_fade_in`] says the playbin is playing.
+    pub(crate) fn fade_in_when_playing(&self) {
+        self.set_fade_volume(0.0);
+        self.fade_in_pending.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn take_fade_in(&self) -> bool {
+        self.fade_in_pending.swap(false, Ordering::Relaxed)
+    }
+
+    /// Moves the fade volume to `to` over `duration`, along an equal power curve
+    /// so two decks fading in opposite directions keep the loudness steady.
+    pub(crate) async fn fade(&self, to: f64, duration: Duration) {
+        let generation = self.fade_generation.fetch_add(1, Ordering::Relaxed) + 1;
+        let from = self.fade.property::<f64>("volume");
+        let steps = (duration.as_millis() / FADE_STEP.as_millis()).max(1) as u32;
+
+        let mut interval = tokio::time::interval(FADE_STEP);
+
+        for step in 1..=steps {
+            interval.tick().await;
+
+            if self.fade_generation.load(Ordering::Relaxed) != generation {
+                return;
+            }
+
+            let progress = step as f64 / steps as f64 * FRAC_PI_2;
+            let curve = if to > from {
+                progress.sin()
+            } else {
+                1.0 - progress.cos()
+            };
+
+            self.fade.set_property("volume", from + (to - from) * curve);
+        }
+    }
+}
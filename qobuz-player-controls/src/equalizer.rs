@@ -0,0 +1,107 @@
+use std::{fmt::Display, str::FromStr};
+
+/// Center frequencies in Hz of the bands of GStreamer's `equalizer-10bands`.
+pub const FREQUENCIES: [u32; 10] = [29, 59, 119, 237, 474, 947, 1889, 3770, 7523, 15011];
+
+/// Lowest gain in dB a band can be set to.
+pub const MIN_GAIN: f64 = -24.0;
+
+/// Highest gain in dB a band can be set to.
+pub const MAX_GAIN: f64 = 12.0;
+
+/// Gains in dB for each of the [`FREQUENCIES`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Equalizer {
+    pub bands: [f64; 10],
+}
+
+/// Named starting points for the equalizer.
+pub static PRESETS: &[(&str, Equalizer)] = &[
+    ("Flat", Equalizer { bands: [0.0; 10] }),
+    (
+        "Bass boost",
+        Equalizer {
+            bands: [6.0, 5.0, 4.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        },
+    ),
+    (
+        "Treble boost",
+        Equalizer {
+            bands: [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 4.0, 5.0, 6.0],
+        },
+    ),
+    (
+        "Loudness",
+        Equalizer {
+            bands: [5.0, 4.0, 2.0, 0.0, -1.0, -1.0, 0.0, 2.0, 4.0, 5.0],
+        },
+    ),
+    (
+        "Vocal",
+        Equalizer {
+            bands: [-2.0, -1.0, 0.0, 2.0, 4.0, 4.0, 3.0, 1.0, 0.0, -1.0],
+        },
+    ),
+    (
+        "Classical",
+        Equalizer {
+            bands: [4.0, 3.0, 2.0, 0.0, 0.0, 0.0, 0.0, -1.0, -2.0, -3.0],
+        },
+    ),
+    (
+        "Rock",
+        Equalizer {
+            bands: [4.0, 3.0, 1.0, -1.0, -2.0, -1.0, 1.0, 3.0, 4.0, 4.0],
+        },
+    ),
+];
+
+impl Equalizer {
+    /// Band gains limited to what the equalizer supports.
+    pub fn new(bands: [f64; 10]) -> Self {
+        Self {
+            bands: bands.map(|gain| gain.clamp(MIN_GAIN, MAX_GAIN)),
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .map(|(_, equalizer)| *equalizer)
+    }
+
+    /// The name of the preset these settings match, if any.
+    pub fn preset_name(&self) -> Option<&'static str> {
+        PRESETS
+            .iter()
+            .find(|(_, equalizer)| equalizer == self)
+            .map(|(name, _)| *name)
+    }
+}
+
+/// Comma separated band gains, lowest frequency first.
+impl Display for Equalizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bands = self.bands.map(|gain| gain.to_string());
+        f.write_str(&bands.join(","))
+    }
+}
+
+impl FromStr for Equalizer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let gains = s
+            .split(',')
+            .map(|gain| gain.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| error.to_string())?;
+
+        let bands = gains
+            .try_into()
+            .map_err(|_| format!("expected {} band gains", FREQUENCIES.len()))?;
+
+        Ok(Self::new(bands))
+    }
+}
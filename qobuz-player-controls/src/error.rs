@@ -13,6 +13,22 @@ pub enum Error {
     TrackURL,
     #[snafu(display("failed to seek"))]
     Seek,
+    #[snafu(display("playlist has no track at that position"))]
+    TrackNotInPlaylist,
+    #[snafu(display("invalid queue position {position}"))]
+    QueuePosition {
+        position: u32,
+    },
+    #[snafu(display("output device {name} was not found"))]
+    OutputDevice {
+        name: String,
+    },
+    #[snafu(display("bit-perfect playback needs an ALSA hw device, {output} is not one"))]
+    BitPerfectOutput {
+        output: String,
+    },
+    #[snafu(display("the output device can not play this stream without conversion"))]
+    BitPerfectFormat,
     #[snafu(display("sorry, could not resume previous session"))]
     Resume,
     #[snafu(display("{message}"))]
@@ -23,6 +39,18 @@ pub enum Error {
     Client {
         message: String,
     },
+    #[snafu(display("Qobuz can not be reached, only music available offline can be played"))]
+    Offline,
+    #[snafu(display("not found in the local library"))]
+    NotInLibrary,
+    #[snafu(display("no offline cache is set up"))]
+    NoOfflineCache,
+    #[snafu(display("the offline cache is too small for everything marked for offline use"))]
+    OfflineCacheFull,
+    #[snafu(display("offline cache: {message}"))]
+    OfflineCache {
+        message: String,
+    },
     Notification,
     App,
 }
@@ -53,7 +81,18 @@ impl From<StateChangeError> for Error {
 
 impl From<qobuz_player_client::Error> for Error {
     fn from(value: qobuz_player_client::Error) -> Self {
-        Error::Client {
+        match value {
+            qobuz_player_client::Error::Unreachable => Error::Offline,
+            _ => Error::Client {
+                message: value.to_string(),
+            },
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Error::OfflineCache {
             message: value.to_string(),
         }
     }
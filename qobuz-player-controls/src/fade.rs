@@ -0,0 +1,27 @@
+use std::time::Duration;
+
+/// Longest crossfade in seconds that can be set.
+pub const MAX_CROSSFADE: f64 = 12.0;
+
+/// How long soft fades on play, pause and skip take.
+pub(crate) const SOFT_FADE: Duration = Duration::from_millis(300);
+
+/// How tracks fade into each other and in and out of silence.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Fades {
+    /// Seconds the end of a track overlaps the start of the next, zero to play gaplessly.
+    /// Tracks from the same album always follow each other gaplessly.
+    pub crossfade: f64,
+    /// Fade in and out briefly when playing, pausing and skipping.
+    pub soft: bool,
+}
+
+impl Fades {
+    /// Settings limited to what the player supports.
+    pub fn new(crossfade: f64, soft: bool) -> Self {
+        Self {
+            crossfade: crossfade.clamp(0.0, MAX_CROSSFADE),
+            soft,
+        }
+    }
+}
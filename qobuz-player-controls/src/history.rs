@@ -0,0 +1,152 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::future::BoxFuture;
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+use crate::tracklist::Track;
+
+/// A play counts as skipped when more than this was left of the track.
+/// Longer than the longest crossfade, so tracks faded out at the end are not skips.
+pub(crate) const SKIPPED_REMAINING: Duration = Duration::from_secs(15);
+
+/// One listen of a track.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Play {
+    pub track_id: u32,
+    pub track_title: String,
+    pub album_id: Option<String>,
+    pub album_title: Option<String>,
+    pub artist_id: Option<u32>,
+    pub artist_name: Option<String>,
+    pub started_at: DateTime<Utc>,
+    /// Time spent playing, not counting pauses.
+    pub listened: Duration,
+    /// Whether playback moved on before the end of the track.
+    pub skipped: bool,
+}
+
+/// An artist, album or track with how much it was listened to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Count {
+    pub id: String,
+    pub name: String,
+    pub plays: u32,
+    pub listened: Duration,
+}
+
+/// Listening totals and the most listened music over a period.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ListeningStats {
+    pub plays: u32,
+    pub listened: Duration,
+    pub top_artists: Vec<Count>,
+    pub top_albums: Vec<Count>,
+    pub top_tracks: Vec<Count>,
+}
+
+/// How far back listening statistics go.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Week,
+    #[default]
+    Month,
+    Year,
+    AllTime,
+}
+
+impl Period {
+    pub const ALL: [Period; 4] = [Self::Week, Self::Month, Self::Year, Self::AllTime];
+
+    /// When the period started, `None` for all time.
+    pub fn since(self) -> Option<DateTime<Utc>> {
+        let days = match self {
+            Self::Week => 7,
+            Self::Month => 30,
+            Self::Year => 365,
+            Self::AllTime => return None,
+        };
+
+        Some(Utc::now() - TimeDelta::days(days))
+    }
+}
+
+impl Display for Period {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Week => "week",
+            Self::Month => "month",
+            Self::Year => "year",
+            Self::AllTime => "all",
+        })
+    }
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "week" => Ok(Self::Week),
+            "month" => Ok(Self::Month),
+            "year" => Ok(Self::Year),
+            "all" => Ok(Self::AllTime),
+            _ => Err(format!(
+                "unknown period {s}, expected week, month, year or all"
+            )),
+        }
+    }
+}
+
+/// Where plays are kept. The player records to it and reads history back through it.
+pub trait HistoryStore: Send + Sync {
+    fn record(&self, play: Play) -> BoxFuture<'_, ()>;
+
+    /// The latest plays, newest first.
+    fn recently_played(&self, limit: u32) -> BoxFuture<'_, Vec<Play>>;
+
+    /// Totals since a point in time, with at most `limit` entries in each top list.
+    fn stats(&self, since: Option<DateTime<Utc>>, limit: u32) -> BoxFuture<'_, ListeningStats>;
+}
+
+/// A track being listened to, finished into a [`Play`] when the next one starts.
+/// Keeps the queued track, so plays are recorded without reaching Qobuz.
+#[derive(Debug)]
+pub(crate) struct CurrentPlay {
+    pub(crate) track: Track,
+    pub(crate) started_at: DateTime<Utc>,
+    pub(crate) listened: Duration,
+    pub(crate) position: Duration,
+    pub(crate) duration: Option<Duration>,
+}
+
+impl CurrentPlay {
+    pub(crate) fn new(track: Track) -> Self {
+        Self {
+            track,
+            started_at: Utc::now(),
+            listened: Duration::ZERO,
+            position: Duration::ZERO,
+            duration: None,
+        }
+    }
+
+    pub(crate) fn skipped(&self) -> bool {
+        self.duration
+            .is_some_and(|duration| duration.saturating_sub(self.position) > SKIPPED_REMAINING)
+    }
+
+    pub(crate) fn finish(self) -> Play {
+        let skipped = self.skipped();
+
+        Play {
+            track_id: self.track.id,
+            track_title: self.track.title,
+            album_id: self.track.album_id,
+            album_title: self.track.album_title,
+            artist_id: self.track.artist_id,
+            artist_name: self.track.artist_name,
+            started_at: self.started_at,
+            listened: self.listened,
+            skipped,
+        }
+    }
+}
@@ -1,22 +1,39 @@
 use crate::models::{Artist, Favorites, Playlist, SearchResults, Track, TrackStatus};
+use autoplay::{AutoplayHistory, AUTOPLAY_TRACKS};
 use cached::{proc_macro::cached, Cached};
+use deck::Deck;
+use equalizer::Equalizer;
 use error::Error;
+use fade::{Fades, SOFT_FADE};
 use futures::prelude::*;
-use gstreamer::{
-    prelude::*, Element, Message, MessageView, SeekFlags, StateChangeSuccess, Structure,
-};
+use gstreamer::{prelude::*, Message, MessageView, PadDirection, SeekFlags, StateChangeSuccess};
+use history::{CurrentPlay, HistoryStore, ListeningStats, Period, Play};
+use local::LocalLibrary;
 use models::{Album, ArtistPage};
 use notification::Notification;
+use offline::{OfflineCache, OfflineCollection};
+use output::Output;
 use qobuz_player_client::client::Client;
+use radio::{Candidate, RadioHistory, Weight, RADIO_SIMILAR_ARTISTS, RADIO_TRACKS};
+use rand::seq::SliceRandom;
+use recovery::Recovery;
+use replaygain::{Gain, ReplayGain, ReplayGainMode};
+use session::Session;
+use sleep_timer::{SleepTimer, SLEEP_FADE};
 use std::{
+    collections::HashMap,
+    path::Path,
     str::FromStr,
     sync::{
-        atomic::{AtomicBool, Ordering},
-        LazyLock, OnceLock,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
     },
     time::Duration,
 };
+use stream_format::StreamFormat;
+use stream_url::{Prefetch, StreamUrl};
 use tokio::{
+    io::AsyncWriteExt,
     select,
     sync::{
         broadcast::{self, Receiver, Sender},
@@ -24,717 +41,2868 @@ use tokio::{
     },
 };
 use tracing::{debug, instrument};
-use tracklist::{TrackListType, Tracklist};
+use tracklist::{RepeatMode, TrackListType, Tracklist, TracklistCopy};
 
 pub use gstreamer::{ClockTime, State};
 pub use qobuz_player_client::client::{AlbumFeaturedType, PlaylistFeaturedType};
+mod autoplay;
+mod deck;
+pub mod equalizer;
 pub mod error;
+pub mod fade;
+pub mod history;
+pub mod local;
 pub mod models;
 pub mod notification;
+pub mod offline;
+pub mod output;
+mod radio;
+mod recovery;
+pub mod replaygain;
+pub mod session;
+pub mod sleep_timer;
+pub mod stream_format;
+mod stream_url;
 pub mod tracklist;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
-static PLAYBIN: LazyLock<Element> = LazyLock::new(|| {
-    gstreamer::init().expect("error initializing gstreamer");
+struct Broadcast {
+    tx: Sender<Notification>,
+    rx: Receiver<Notification>,
+}
+
+/// Carries the index of the deck whose track is about to finish.
+struct TrackAboutToFinish {
+    tx: Sender<usize>,
+    rx: Receiver<usize>,
+}
+
+/// Qobuz credentials and the client logged in with them, shared by every zone.
+pub struct Account {
+    client: RwLock<Option<Client>>,
+    credentials: RwLock<Credentials>,
+}
+
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+impl Account {
+    pub fn new(username: String, password: String) -> Arc<Self> {
+        Arc::new(Self {
+            client: RwLock::new(None),
+            credentials: RwLock::new(Credentials { username, password }),
+        })
+    }
+
+    /// The logged in client, logging in on first use.
+    /// Logging in is tried again on the next use if it failed, like when offline.
+    async fn client(&self) -> Result<Client> {
+        if let Some(client) = self.client.read().await.as_ref() {
+            return Ok(client.clone());
+        }
+
+        let mut client = self.client.write().await;
+
+        // Another zone may have logged in while waiting for the lock.
+        if let Some(client) = client.as_ref() {
+            return Ok(client.clone());
+        }
+
+        tracing::info!("Logging in");
+
+        let credentials = self.credentials.read().await;
+        let logged_in =
+            qobuz_player_client::client::new(&credentials.username, &credentials.password).await?;
+
+        tracing::info!("Done");
+
+        *client = Some(logged_in.clone());
+        Ok(logged_in)
+    }
+
+    /// Logs in with other credentials, replacing the client of every zone.
+    /// The previous login is kept if this one fails.
+    pub async fn login(&self, username: String, password: String) -> Result<()> {
+        tracing::info!("Logging in as {username}");
+
+        let logged_in = qobuz_player_client::client::new(&username, &password).await?;
+
+        *self.client.write().await = Some(logged_in);
+        *self.credentials.write().await = Credentials { username, password };
+        Ok(())
+    }
+}
+
+/// A playback zone with its own pipeline, output device, queue and notification channel.
+/// Clones are cheap and control the same zone.
+#[derive(Clone)]
+pub struct Player {
+    zone: Arc<str>,
+    decks: Arc<[Deck; 2]>,
+    active_deck: Arc<AtomicUsize>,
+    output: Arc<RwLock<Output>>,
+    bit_perfect: Arc<AtomicBool>,
+    equalizer_profiles: Arc<RwLock<HashMap<Output, Equalizer>>>,
+    replay_gain: Arc<RwLock<ReplayGain>>,
+    fades: Arc<RwLock<Fades>>,
+    crossfading: Arc<AtomicBool>,
+    sleep_timer: Arc<RwLock<Option<SleepTimer>>>,
+    sleep_fading: Arc<AtomicBool>,
+    tracklist: Arc<RwLock<Tracklist>>,
+    /// The tracklist as it was last broadcast, to tell what changed since.
+    broadcast_tracklist: Arc<Mutex<TracklistCopy>>,
+    refilled_after: Arc<Mutex<Option<u32>>>,
+    autoplay_history: Arc<Mutex<AutoplayHistory>>,
+    radio_history: Arc<Mutex<RadioHistory>>,
+    history: Arc<RwLock<Option<Arc<dyn HistoryStore>>>>,
+    current_play: Arc<Mutex<Option<CurrentPlay>>>,
+    offline: Arc<RwLock<Option<Arc<OfflineCache>>>>,
+    local: Arc<RwLock<Option<Arc<LocalLibrary>>>>,
+    broadcast: Arc<Broadcast>,
+    about_to_finish: Arc<TrackAboutToFinish>,
+    should_quit: Arc<AtomicBool>,
+    is_live: Arc<AtomicBool>,
+    target_status: Arc<RwLock<gstreamer::State>>,
+    queued_next_track: Arc<Mutex<Option<u32>>>,
+    /// The track handed to playbin to follow the current one was removed from the queue,
+    /// so playback stops when it starts instead of playing it.
+    stale_next_track: Arc<AtomicBool>,
+    /// Replaygain volume of the queued next track, switched to as soon as it starts.
+    next_replay_gain: Arc<Mutex<Option<f64>>>,
+    prefetch: Arc<Mutex<Prefetch>>,
+    recovery: Arc<Mutex<Recovery>>,
+    recovering: Arc<AtomicBool>,
+    resume_position: Arc<Mutex<Option<ClockTime>>>,
+    stream_format: Arc<RwLock<Option<StreamFormat>>>,
+    next_stream_format: Arc<Mutex<StreamFormat>>,
+    account: Arc<Account>,
+}
+
+impl std::fmt::Debug for Player {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Player")
+            .field("zone", &self.zone)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Player {
+    /// Builds the pipeline for a zone playing to the default output,
+    /// see [`Player::set_output`]. Logging in happens on first use of the client.
+    pub fn new(zone: &str, account: Arc<Account>) -> Result<Self> {
+        gstreamer::init()?;
+
+        let (tx, rx) = broadcast::channel(1);
+        let about_to_finish = TrackAboutToFinish { tx, rx };
+
+        let decks = [
+            Deck::new(0, about_to_finish.tx.clone())?,
+            Deck::new(1, about_to_finish.tx.clone())?,
+        ];
+
+        let (tx, rx) = broadcast::channel(20);
+
+        Ok(Self {
+            zone: zone.into(),
+            decks: Arc::new(decks),
+            active_deck: Arc::new(AtomicUsize::new(0)),
+            output: Arc::new(RwLock::new(Output::default())),
+            bit_perfect: Arc::new(AtomicBool::new(false)),
+            equalizer_profiles: Arc::new(RwLock::new(HashMap::new())),
+            replay_gain: Arc::new(RwLock::new(ReplayGain::default())),
+            fades: Arc::new(RwLock::new(Fades::default())),
+            crossfading: Arc::new(AtomicBool::new(false)),
+            sleep_timer: Arc::new(RwLock::new(None)),
+            sleep_fading: Arc::new(AtomicBool::new(false)),
+            tracklist: Arc::new(RwLock::new(Tracklist::new())),
+            broadcast_tracklist: Arc::new(Mutex::new(TracklistCopy::default())),
+            refilled_after: Arc::new(Mutex::new(None)),
+            autoplay_history: Arc::new(Mutex::new(AutoplayHistory::default())),
+            radio_history: Arc::new(Mutex::new(RadioHistory::default())),
+            history: Arc::new(RwLock::new(None)),
+            current_play: Arc::new(Mutex::new(None)),
+            offline: Arc::new(RwLock::new(None)),
+            local: Arc::new(RwLock::new(None)),
+            broadcast: Arc::new(Broadcast { tx, rx }),
+            about_to_finish: Arc::new(about_to_finish),
+            should_quit: Arc::new(AtomicBool::new(false)),
+            is_live: Arc::new(AtomicBool::new(false)),
+            target_status: Arc::new(RwLock::new(gstreamer::State::Null)),
+            queued_next_track: Arc::new(Mutex::new(None)),
+            stale_next_track: Arc::new(AtomicBool::new(false)),
+            next_replay_gain: Arc::new(Mutex::new(None)),
+            prefetch: Arc::new(Mutex::new(Prefetch::default())),
+            recovery: Arc::new(Mutex::new(Recovery::default())),
+            recovering: Arc::new(AtomicBool::new(false)),
+            resume_position: Arc::new(Mutex::new(None)),
+            stream_format: Arc::new(RwLock::new(None)),
+            next_stream_format: Arc::new(Mutex::new(StreamFormat::default())),
+            account,
+        })
+    }
+
+    /// The name of the zone this player plays in.
+    pub fn zone(&self) -> &str {
+        &self.zone
+    }
+
+    async fn client(&self) -> Result<Client> {
+        self.account.client().await
+    }
+
+    /// The deck playing the current track.
+    fn deck(&self) -> &Deck {
+        &self.decks[self.active_deck.load(Ordering::Relaxed)]
+    }
+
+    /// The deck that is idle, or still fading out the previous track.
+    fn other_deck(&self) -> &Deck {
+        &self.decks[1 - self.active_deck.load(Ordering::Relaxed)]
+    }
+
+    fn playbin(&self) -> &gstreamer::Element {
+        &self.deck().playbin
+    }
+
+    #[instrument]
+    /// Logs in to another Qobuz account, for every zone sharing this login.
+    pub async fn login(&self, username: String, password: String) -> Result<()> {
+        self.account.login(username, password).await?;
+
+        FAVORITES.lock().await.cache_clear();
+        Ok(())
+    }
+
+    #[instrument]
+    /// Ready the player.
+    async fn ready(&self) -> Result<()> {
+        *self.queued_next_track.lock().await = None;
+        self.stale_next_track.store(false, Ordering::Relaxed);
+        *self.next_replay_gain.lock().await = None;
+        self.crossfading.store(false, Ordering::Relaxed);
+
+        if self.is_playing() {
+            self.soft_fade_out().await;
+        }
+
+        self.set_player_state(gstreamer::State::Ready).await
+    }
 
-    let playbin = gstreamer::ElementFactory::make("playbin3")
-        .build()
-        .expect("error building playbin element");
+    #[instrument]
+    /// Stop the player.
+    pub async fn stop(&self) -> Result<()> {
+        self.other_deck()
+            .playbin
+            .set_state(gstreamer::State::Null)?;
+        self.set_player_state(gstreamer::State::Null).await
+    }
 
-    playbin.set_property_from_str("flags", "audio+buffering");
+    async fn set_target_state(&self, state: gstreamer::State) {
+        let mut target_status = self.target_status.write().await;
+        *target_status = state;
+    }
 
-    if gstreamer::version().1 >= 22 {
-        playbin.connect("element-setup", false, |value| {
-            let element = &value[1].get::<gstreamer::Element>().unwrap();
+    #[instrument]
+    /// Sets the player to a specific state.
+    async fn set_player_state(&self, state: gstreamer::State) -> Result<()> {
+        let ret = self.playbin().set_state(state)?;
 
-            if element.name().contains("urisourcebin") {
-                element.set_property("parse-streams", true);
+        match ret {
+            StateChangeSuccess::Success => {
+                tracing::debug!("*** successful state change ***");
+            }
+            StateChangeSuccess::Async => {
+                tracing::debug!("*** async state change ***");
+            }
+            StateChangeSuccess::NoPreroll => {
+                tracing::debug!("*** stream is live ***");
+                self.is_live.store(true, Ordering::Relaxed);
             }
+        }
 
-            None
-        });
+        Ok(())
+    }
+
+    /// Broadcasts what changed in the tracklist since the last broadcast,
+    /// and the new current track if it changed.
+    async fn broadcast_track_list(&self, list: &Tracklist) -> Result<()> {
+        let mut broadcast_tracklist = self.broadcast_tracklist.lock().await;
+
+        if *broadcast_tracklist.tracklist() == *list {
+            return Ok(());
+        }
+
+        let track_changed =
+            broadcast_tracklist.tracklist().currently_playing() != list.currently_playing();
+        let diff = broadcast_tracklist.update(list);
+
+        // Sent while holding the lock, so diffs go out in the order of their versions.
+        self.broadcast
+            .tx
+            .send(Notification::QueueChanged { diff })?;
+        drop(broadcast_tracklist);
+
+        if track_changed {
+            self.broadcast_current_track(list);
+        }
+
+        Ok(())
     }
 
-    playbin.connect("source-setup", false, |value| {
-        let element = &value[1].get::<gstreamer::Element>().unwrap();
+    /// Broadcasts the current track with what the queue knows about it.
+    fn broadcast_current_track(&self, list: &Tracklist) {
+        let track = list.current_track().cloned().map(Track::from);
 
-        if element.name().contains("souphttpsrc") {
-            debug!("new source, changing settings");
-            let ua = if rand::random() {
-                USER_AGENTS[0]
+        // Nobody may be listening.
+        _ = self.broadcast.tx.send(Notification::TrackChanged { track });
+    }
+
+    #[instrument]
+    /// Toggle play and pause.
+    pub async fn play_pause(&self) -> Result<()> {
+        if self.is_playing() {
+            self.pause().await?;
+        } else if self.is_paused() || self.is_ready() {
+            self.play().await?;
+        }
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Play the player.
+    pub async fn play(&self) -> Result<()> {
+        self.set_target_state(gstreamer::State::Playing).await;
+
+        if !self.is_playing() {
+            if self.soft_fades().await {
+                self.deck().fade_in_when_playing();
             } else {
-                USER_AGENTS[1]
-            };
-            element.set_property("user-agent", ua);
-            element.set_property("compress", true);
-            element.set_property("retries", 10);
-            element.set_property("timeout", 30_u32);
-            element.set_property(
-                "extra-headers",
-                Structure::from_str("a-structure, DNT=1, Pragma=no-cache, Cache-Control=no-cache")
-                    .expect("failed to make structure from string"),
-            )
+                self.deck().set_fade_volume(1.0);
+            }
+        }
+
+        self.set_player_state(gstreamer::State::Playing).await?;
+        Ok(())
+    }
+
+    #[instrument]
+    /// Pause the player.
+    pub async fn pause(&self) -> Result<()> {
+        self.set_target_state(gstreamer::State::Paused).await;
+
+        if self.is_playing() {
+            self.soft_fade_out().await;
+        }
+
+        self.set_player_state(gstreamer::State::Paused).await?;
+        Ok(())
+    }
+
+    /// Soft fades need the audio filter, which bit-perfect playback leaves out.
+    async fn soft_fades(&self) -> bool {
+        self.fades.read().await.soft && !self.bit_perfect()
+    }
+
+    async fn soft_fade_out(&self) {
+        if self.soft_fades().await {
+            self.deck().fade(0.0, SOFT_FADE).await;
+        }
+    }
+
+    #[instrument]
+    /// Is the player paused?
+    pub fn is_paused(&self) -> bool {
+        self.playbin().current_state() != gstreamer::State::Playing
+    }
+
+    #[instrument]
+    /// Is the player playing?
+    pub fn is_playing(&self) -> bool {
+        self.playbin().current_state() == gstreamer::State::Playing
+    }
+
+    #[instrument]
+    /// Is the player ready?
+    pub fn is_ready(&self) -> bool {
+        self.playbin().current_state() == gstreamer::State::Ready
+    }
+
+    #[instrument]
+    /// Current player state
+    pub fn current_state(&self) -> State {
+        self.playbin().current_state()
+    }
+
+    #[instrument]
+    /// Current track position.
+    pub fn position(&self) -> Option<ClockTime> {
+        self.playbin().query_position::<ClockTime>()
+    }
+
+    #[instrument]
+    /// Current track duration.
+    pub fn duration(&self) -> Option<ClockTime> {
+        self.playbin().query_duration::<ClockTime>()
+    }
+
+    #[instrument]
+    /// Current volume
+    pub fn volume(&self) -> f64 {
+        self.playbin().property::<f64>("volume")
+    }
+
+    #[instrument]
+    /// Set volume
+    pub fn set_volume(&self, value: f64) {
+        // Bit-perfect playback leaves the samples alone, so the volume stays at full.
+        let value = if self.bit_perfect() { 1.0 } else { value };
+
+        for deck in self.decks.iter() {
+            deck.playbin.set_property("volume", value);
         }
 
-        None
-    });
+        // Nobody may be listening yet.
+        _ = self
+            .broadcast
+            .tx
+            .send(Notification::Volume { volume: value });
+    }
+
+    #[instrument]
+    /// Set how track loudness is normalized and apply it to the playing track.
+    pub async fn set_replay_gain(&self, replay_gain: ReplayGain) -> Result<()> {
+        *self.replay_gain.write().await = replay_gain;
+        // Looked up again with the new settings when the next track starts.
+        *self.next_replay_gain.lock().await = None;
 
-    playbin.add_property_deep_notify_watch(Some("caps"), true);
+        let current_track = self.tracklist.read().await.currently_playing();
+        if let Some(track_id) = current_track {
+            self.apply_replay_gain(track_id).await?;
+        }
 
-    // Connects to the `about-to-finish` signal so the player
-    // can setup the next track to play. Enables gapless playback.
-    playbin.connect("about-to-finish", false, move |_| {
-        debug!("about to finish");
-        TRACK_ABOUT_TO_FINISH
+        self.broadcast
             .tx
-            .send(true)
-            .expect("failed to send about to finish message");
+            .send(Notification::ReplayGain { replay_gain })?;
 
-        None
-    });
+        Ok(())
+    }
 
-    playbin
-});
+    pub async fn replay_gain(&self) -> ReplayGain {
+        *self.replay_gain.read().await
+    }
 
-struct Broadcast {
-    tx: Sender<Notification>,
-    rx: Receiver<Notification>,
-}
+    /// The volume the replaygain settings call for when playing a track.
+    async fn replay_gain_volume(&self, track_id: u32) -> Result<f64> {
+        let replay_gain = *self.replay_gain.read().await;
 
-static BROADCAST_CHANNELS: LazyLock<Broadcast> = LazyLock::new(|| {
-    let (tx, rx) = broadcast::channel(20);
-    Broadcast { tx, rx }
-});
+        // Local files are played as they are.
+        if replay_gain.mode == ReplayGainMode::Off || local::is_local(track_id) {
+            return Ok(1.0);
+        }
 
-struct TrackAboutToFinish {
-    tx: Sender<bool>,
-    rx: Receiver<bool>,
-}
+        let client = self.client().await?;
+        let (track, album_id) = track_gain(&client, track_id).await?;
 
-static TRACK_ABOUT_TO_FINISH: LazyLock<TrackAboutToFinish> = LazyLock::new(|| {
-    let (tx, rx) = broadcast::channel(1);
-    TrackAboutToFinish { tx, rx }
-});
-
-static SHOULD_QUIT: AtomicBool = AtomicBool::new(false);
-static IS_LIVE: AtomicBool = AtomicBool::new(false);
-static TARGET_STATUS: LazyLock<RwLock<gstreamer::State>> =
-    LazyLock::new(|| RwLock::new(gstreamer::State::Null));
-static TRACKLIST: LazyLock<RwLock<Tracklist>> = LazyLock::new(|| RwLock::new(Tracklist::new()));
-static USER_AGENTS: &[&str] = &[
-    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36",
-    "Mozilla/5.0 (Macintosh; Intel Mac OS X 13_4) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36"
-];
-
-static CLIENT: OnceLock<Client> = OnceLock::new();
-static CLIENT_INITIATED: LazyLock<Mutex<bool>> = LazyLock::new(|| Mutex::new(false));
-static USERNAME: OnceLock<String> = OnceLock::new();
-static PASSWORD: OnceLock<String> = OnceLock::new();
+        let album = match (replay_gain.mode, album_id) {
+            (ReplayGainMode::Album, Some(album_id)) => album_gain(&client, &album_id).await?,
+            _ => None,
+        };
 
-#[instrument]
-async fn init_client() -> Client {
-    tracing::info!("Logging in");
-    let username = USERNAME.get().unwrap();
-    let password = PASSWORD.get().unwrap();
+        Ok(replay_gain.volume(track, album))
+    }
 
-    let client = qobuz_player_client::client::new(username, password)
-        .await
-        .expect("error making client");
+    async fn apply_replay_gain(&self, track_id: u32) -> Result<()> {
+        let volume = self.replay_gain_volume(track_id).await?;
+        debug!("replaygain volume {volume}");
+        self.deck().set_replay_gain(volume);
 
-    tracing::info!("Done");
-    client
-}
+        Ok(())
+    }
+
+    #[instrument]
+    /// Set the equalizer for the current output and apply it right away.
+    pub async fn set_equalizer(&self, equalizer: Equalizer) -> Result<()> {
+        let output = self.output().await;
+
+        self.equalizer_profiles
+            .write()
+            .await
+            .insert(output, equalizer);
+        self.apply_equalizer(&equalizer);
+
+        self.broadcast
+            .tx
+            .send(Notification::Equalizer { equalizer })?;
 
-async fn get_client() -> &'static Client {
-    if let Some(client) = CLIENT.get() {
-        return client;
+        Ok(())
     }
 
-    let mut inititiated = CLIENT_INITIATED.lock().await;
+    /// The equalizer for the current output, flat unless one was set for it.
+    pub async fn equalizer(&self) -> Equalizer {
+        let output = self.output().await;
+
+        self.equalizer_profiles
+            .read()
+            .await
+            .get(&output)
+            .copied()
+            .unwrap_or_default()
+    }
 
-    if !*inititiated {
-        let client = init_client().await;
+    /// Replaces the equalizer settings of every output, like ones saved earlier.
+    pub async fn set_equalizer_profiles(&self, profiles: HashMap<Output, Equalizer>) {
+        *self.equalizer_profiles.write().await = profiles;
 
-        CLIENT.set(client).unwrap();
-        *inititiated = true;
-        drop(inititiated);
+        let equalizer = self.equalizer().await;
+        self.apply_equalizer(&equalizer);
     }
 
-    CLIENT.get().unwrap()
-}
+    fn apply_equalizer(&self, equalizer: &Equalizer) {
+        for deck in self.decks.iter() {
+            deck.set_equalizer(equalizer);
+        }
+    }
 
-#[instrument]
-/// Ready the player.
-async fn ready() -> Result<()> {
-    set_player_state(gstreamer::State::Ready).await
-}
+    #[instrument]
+    /// Set how tracks fade into each other and when playing, pausing and skipping.
+    pub async fn set_fades(&self, fades: Fades) -> Result<()> {
+        *self.fades.write().await = fades;
 
-#[instrument]
-/// Stop the player.
-pub async fn stop() -> Result<()> {
-    set_player_state(gstreamer::State::Null).await
-}
+        if !fades.soft && !self.crossfading.load(Ordering::Relaxed) {
+            self.deck().set_fade_volume(1.0);
+        }
 
-async fn set_target_state(state: gstreamer::State) {
-    let mut target_status = TARGET_STATUS.write().await;
-    *target_status = state;
-}
+        self.broadcast.tx.send(Notification::Fades { fades })?;
 
-#[instrument]
-/// Sets the player to a specific state.
-async fn set_player_state(state: gstreamer::State) -> Result<()> {
-    let ret = PLAYBIN.set_state(state)?;
+        Ok(())
+    }
+
+    pub async fn fades(&self) -> Fades {
+        *self.fades.read().await
+    }
+
+    #[instrument]
+    /// Pause playback later, see [`SleepTimer`]. `None` cancels the timer.
+    pub async fn set_sleep_timer(&self, timer: Option<SleepTimer>) -> Result<()> {
+        *self.sleep_timer.write().await = timer;
+
+        // Changing the timer during the fade out brings the music back.
+        if self.sleep_fading.swap(false, Ordering::Relaxed) {
+            self.deck().set_fade_volume(1.0);
+        }
+
+        self.broadcast.tx.send(Notification::SleepTimer { timer })?;
+
+        Ok(())
+    }
+
+    pub async fn sleep_timer(&self) -> Option<SleepTimer> {
+        *self.sleep_timer.read().await
+    }
+
+    #[instrument]
+    /// Pause once the current track ends, or cancel that if it was already set.
+    pub async fn toggle_stop_after_current(&self) -> Result<()> {
+        let timer = match self.sleep_timer().await {
+            Some(SleepTimer::EndOfTrack) => None,
+            _ => Some(SleepTimer::EndOfTrack),
+        };
+
+        self.set_sleep_timer(timer).await
+    }
 
-    match ret {
-        StateChangeSuccess::Success => {
-            tracing::debug!("*** successful state change ***");
+    /// Whether the sleep timer pauses playback when the current track ends.
+    async fn stops_after_current(&self, tracklist: &Tracklist) -> bool {
+        match self.sleep_timer().await {
+            Some(SleepTimer::EndOfTrack) => true,
+            Some(SleepTimer::EndOfAlbum) => self.album_continues(tracklist).await != Some(true),
+            Some(SleepTimer::At { .. }) | None => false,
         }
-        StateChangeSuccess::Async => {
-            tracing::debug!("*** async state change ***");
+    }
+
+    /// Fades out once a timed stop is close and pauses when it is reached.
+    async fn sleep_when_due(&self) {
+        let Some(timer @ SleepTimer::At { .. }) = self.sleep_timer().await else {
+            return;
+        };
+        let remaining = timer.remaining().unwrap_or_default();
+
+        if !self.is_playing() {
+            // Nothing to stop, the timer just runs out.
+            if remaining.is_zero() {
+                if let Err(error) = self.set_sleep_timer(None).await {
+                    debug!(?error);
+                }
+            }
+
+            return;
         }
-        StateChangeSuccess::NoPreroll => {
-            tracing::debug!("*** stream is live ***");
-            IS_LIVE.store(true, Ordering::Relaxed);
+
+        if remaining > SLEEP_FADE || self.sleep_fading.swap(true, Ordering::Relaxed) {
+            return;
         }
+
+        let player = self.clone();
+        tokio::spawn(async move {
+            player.deck().fade(0.0, remaining).await;
+
+            // Cancelled or changed while fading.
+            if player.sleep_timer().await != Some(timer) {
+                return;
+            }
+
+            player.sleep_fading.store(false, Ordering::Relaxed);
+
+            if let Err(error) = player.pause().await {
+                debug!(?error);
+            }
+            if let Err(error) = player.set_sleep_timer(None).await {
+                debug!(?error);
+            }
+        });
     }
 
-    Ok(())
-}
+    /// Keep a history of what is played in `store`, see [`Player::recently_played`].
+    pub async fn set_history_store(&self, store: Arc<dyn HistoryStore>) {
+        *self.history.write().await = Some(store);
+    }
 
-async fn broadcast_track_list(list: &Tracklist) -> Result<()> {
-    BROADCAST_CHANNELS
-        .tx
-        .send(Notification::CurrentTrackList { list: list.clone() })?;
-    Ok(())
-}
+    #[instrument]
+    /// The latest plays, newest first. Empty without a history store.
+    pub async fn recently_played(&self, limit: u32) -> Vec<Play> {
+        let store = self.history.read().await.clone();
 
-#[instrument]
-/// Toggle play and pause.
-pub async fn play_pause() -> Result<()> {
-    if is_playing() {
-        pause().await?;
-    } else if is_paused() || is_ready() {
-        play().await?;
+        match store {
+            Some(store) => store.recently_played(limit).await,
+            None => vec![],
+        }
     }
 
-    Ok(())
-}
+    #[instrument]
+    /// Listening time and the most played artists, albums and tracks over a period.
+    pub async fn listening_stats(&self, period: Period, limit: u32) -> ListeningStats {
+        let store = self.history.read().await.clone();
 
-#[instrument]
-/// Play the player.
-pub async fn play() -> Result<()> {
-    set_target_state(gstreamer::State::Playing).await;
-    set_player_state(gstreamer::State::Playing).await?;
-    Ok(())
-}
+        match store {
+            Some(store) => store.stats(period.since(), limit).await,
+            None => ListeningStats::default(),
+        }
+    }
 
-#[instrument]
-/// Pause the player.
-pub async fn pause() -> Result<()> {
-    set_target_state(gstreamer::State::Paused).await;
-    set_player_state(gstreamer::State::Paused).await?;
-    Ok(())
-}
+    /// Play local files from `library` and include them in search and browsing,
+    /// see [`LocalLibrary::scan`].
+    pub async fn set_local_library(&self, library: Arc<LocalLibrary>) {
+        *self.local.write().await = Some(library);
+    }
 
-#[instrument]
-/// Is the player paused?
-pub fn is_paused() -> bool {
-    PLAYBIN.current_state() != gstreamer::State::Playing
-}
+    async fn local_library(&self) -> Result<Arc<LocalLibrary>> {
+        self.local.read().await.clone().ok_or(Error::NotInLibrary)
+    }
 
-#[instrument]
-/// Is the player playing?
-pub fn is_playing() -> bool {
-    PLAYBIN.current_state() == gstreamer::State::Playing
-}
+    /// Every album in the local library. Empty without one.
+    pub async fn local_albums(&self) -> Vec<Album> {
+        match self.local_library().await {
+            Ok(library) => library.albums(),
+            Err(_) => vec![],
+        }
+    }
 
-#[instrument]
-/// Is the player ready?
-pub fn is_ready() -> bool {
-    PLAYBIN.current_state() == gstreamer::State::Ready
-}
+    /// Keep albums and playlists marked for offline use in `cache`, and play tracks from it
+    /// when they have been downloaded, see [`Player::set_album_offline`].
+    pub async fn set_offline_cache(&self, cache: Arc<OfflineCache>) {
+        *self.offline.write().await = Some(cache);
+    }
 
-#[instrument]
-/// Current player state
-pub fn current_state() -> State {
-    PLAYBIN.current_state()
-}
+    async fn offline_cache(&self) -> Result<Arc<OfflineCache>> {
+        self.offline
+            .read()
+            .await
+            .clone()
+            .ok_or(Error::NoOfflineCache)
+    }
 
-#[instrument]
-/// Current track position.
-pub fn position() -> Option<ClockTime> {
-    PLAYBIN.query_position::<ClockTime>()
-}
+    #[instrument]
+    /// Marks an album to be downloaded for offline use, or stops keeping it.
+    /// Downloading happens in the background, see [`Player::sync_offline`].
+    pub async fn set_album_offline(&self, album_id: &str, offline: bool) -> Result<()> {
+        let cache = self.offline_cache().await?;
+
+        if !offline {
+            let list_type = TrackListType::Album(tracklist::AlbumTracklist {
+                id: album_id.to_string(),
+                ..Default::default()
+            });
+            return Ok(cache.unmark(&list_type).await?);
+        }
+
+        let album: Album = self.client().await?.album(album_id).await?.into();
+
+        cache
+            .mark(OfflineCollection {
+                list_type: TrackListType::Album(tracklist::AlbumTracklist {
+                    title: album.title,
+                    id: album.id,
+                }),
+                cover_art: Some(album.cover_art),
+                tracks: album.tracks.into_iter().map(queued_track).collect(),
+            })
+            .await?;
+
+        self.spawn_sync_offline();
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Marks a playlist to be downloaded for offline use, or stops keeping it.
+    /// Downloading happens in the background, see [`Player::sync_offline`].
+    pub async fn set_playlist_offline(&self, playlist_id: i64, offline: bool) -> Result<()> {
+        let cache = self.offline_cache().await?;
+
+        if !offline {
+            let list_type = TrackListType::Playlist(tracklist::PlaylistTracklist {
+                id: playlist_id,
+                ..Default::default()
+            });
+            return Ok(cache.unmark(&list_type).await?);
+        }
+
+        let playlist = self.playlist(playlist_id).await?;
+
+        cache
+            .mark(OfflineCollection {
+                list_type: TrackListType::Playlist(tracklist::PlaylistTracklist {
+                    title: playlist.title,
+                    id: playlist_id,
+                }),
+                cover_art: playlist.cover_art,
+                tracks: playlist.tracks.into_iter().map(queued_track).collect(),
+            })
+            .await?;
+
+        self.spawn_sync_offline();
+
+        Ok(())
+    }
+
+    /// Whether an album or playlist is kept for offline use. False without an offline cache.
+    pub async fn is_offline(&self, list_type: &TrackListType) -> bool {
+        match self.offline_cache().await {
+            Ok(cache) => cache.is_marked(list_type).await,
+            Err(_) => false,
+        }
+    }
+
+    /// The albums and playlists kept for offline use. Empty without an offline cache.
+    pub async fn offline_collections(&self) -> Vec<OfflineCollection> {
+        match self.offline_cache().await {
+            Ok(cache) => cache.collections().await,
+            Err(_) => vec![],
+        }
+    }
+
+    #[instrument]
+    /// Plays an album or playlist kept for offline use starting from index, without reaching
+    /// Qobuz. Only the id of `list_type` is compared. Tracks that haven't been downloaded yet
+    /// are skipped.
+    pub async fn play_offline(&self, list_type: &TrackListType, index: u32) -> Result<()> {
+        let cache = self.offline_cache().await?;
+        let Some(collection) = cache.collection(list_type).await else {
+            return Ok(());
+        };
+
+        self.ready().await?;
+
+        let mut tracklist = self.tracklist.write().await;
+        tracklist.queue = collection.tracks;
+
+        if let Some(track) = tracklist.skip_to(index) {
+            let track_url = self.stream_url(track.id).await?;
+            self.playbin().set_property("uri", track_url);
+            self.play().await?;
+
+            tracklist.list_type = collection.list_type;
+
+            if tracklist.is_shuffled() {
+                tracklist.shuffle();
+            }
+
+            self.broadcast_track_list(&tracklist).await?;
+        };
+
+        Ok(())
+    }
+
+    /// Downloads what is marked for offline use in the background, see [`Player::sync_offline`].
+    fn spawn_sync_offline(&self) {
+        let player = self.clone();
+
+        tokio::spawn(async move {
+            if let Err(error) = player.sync_offline().await {
+                tracing::warn!("failed to download tracks for offline use: {error}");
+            }
+        });
+    }
+
+    #[instrument]
+    /// Downloads the tracks of albums and playlists marked for offline use that are not in the
+    /// cache yet. Returns right away if a download is already running.
+    pub async fn sync_offline(&self) -> Result<()> {
+        let cache = self.offline_cache().await?;
+        let Some(_downloading) = cache.start_downloading() else {
+            return Ok(());
+        };
+
+        let mut failed = vec![];
+
+        // Albums and playlists marked while downloading are picked up too.
+        loop {
+            let missing = cache.missing().await;
+            let Some(track_id) = missing.into_iter().find(|id| !failed.contains(id)) else {
+                return Ok(());
+            };
+
+            match self.download(&cache, track_id).await {
+                Ok(()) => {}
+                Err(Error::Offline) => return Err(Error::Offline),
+                Err(Error::OfflineCacheFull) => return Err(Error::OfflineCacheFull),
+                Err(error) => {
+                    tracing::warn!("failed to download track {track_id}: {error}");
+                    failed.push(track_id);
+                }
+            }
+        }
+    }
+
+    async fn download(&self, cache: &OfflineCache, track_id: u32) -> Result<()> {
+        tracing::info!("Downloading track {track_id} for offline use");
+
+        let partial_path = cache.partial_path(track_id);
+        let result = self.download_to(&partial_path, track_id).await;
+
+        if result.is_err() {
+            // A part of the track is of no use, it is downloaded again from the start.
+            if let Err(error) = offline::remove_file(&partial_path).await {
+                tracing::warn!("failed to remove {}: {error}", partial_path.display());
+            }
+            return result;
+        }
+
+        cache.add(track_id).await
+    }
+
+    async fn download_to(&self, path: &Path, track_id: u32) -> Result<()> {
+        let mut download = self.client().await?.download(track_id).await?;
+        let mut file = tokio::fs::File::create(path).await?;
+
+        while let Some(chunk) = download.chunk().await? {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+
+        Ok(())
+    }
+
+    /// Finishes the play of the previous track and starts counting for a new one.
+    async fn start_play(&self, track: tracklist::Track) {
+        let previous = self
+            .current_play
+            .lock()
+            .await
+            .replace(CurrentPlay::new(track));
+
+        if let Some(previous) = previous {
+            let player = self.clone();
+            tokio::spawn(async move { player.record_play(previous).await });
+        }
+    }
+
+    /// Adds time spent playing to the current play.
+    async fn count_play(&self, listened: Duration) {
+        let mut current_play = self.current_play.lock().await;
+
+        if let Some(play) = current_play.as_mut() {
+            play.listened += listened;
+
+            // Stays at the furthest point, the next track may already be reporting its position.
+            if let Some(position) = self.position() {
+                play.position = play.position.max(position.into());
+            }
+            if play.duration.is_none() {
+                play.duration = self.duration().map(Duration::from);
+            }
+        }
+    }
+
+    /// Stores a finished play with the album and artist of its queued track.
+    async fn record_play(&self, play: CurrentPlay) {
+        let Some(store) = self.history.read().await.clone() else {
+            return;
+        };
+
+        // Loaded but never played, like a resumed session that was not started.
+        if play.listened.is_zero() {
+            return;
+        }
+
+        store.record(play.finish()).await;
+    }
+
+    #[instrument]
+    /// Switch the output, picking up the current track where it was.
+    pub async fn set_output(&self, output: Output) -> Result<()> {
+        self.configure_output(&output, self.bit_perfect()).await?;
+        *self.output.write().await = output.clone();
+
+        let equalizer = self.equalizer().await;
+        self.apply_equalizer(&equalizer);
+
+        self.broadcast.tx.send(Notification::Output { output })?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Play streams at their native rate and depth straight to an ALSA hw device,
+    /// without resampling, format conversion, equalizer, replaygain or volume.
+    pub async fn set_bit_perfect(&self, bit_perfect: bool) -> Result<()> {
+        let output = self.output().await;
+        self.configure_output(&output, bit_perfect).await?;
+        self.bit_perfect.store(bit_perfect, Ordering::Relaxed);
+
+        if bit_perfect {
+            self.set_volume(1.0);
+        }
+
+        self.broadcast
+            .tx
+            .send(Notification::BitPerfect { bit_perfect })?;
+
+        Ok(())
+    }
+
+    pub fn bit_perfect(&self) -> bool {
+        self.bit_perfect.load(Ordering::Relaxed)
+    }
+
+    /// Rebuilds the audio end of the pipeline, picking up the current track where it was.
+    async fn configure_output(&self, output: &Output, bit_perfect: bool) -> Result<()> {
+        // Each deck needs a sink of its own.
+        let sinks = [output.sink(bit_perfect)?, output.sink(bit_perfect)?];
+
+        let state = self.current_state();
+        let position = self.position();
+
+        // The sink can only be swapped while the pipeline isn't running.
+        if state == State::Playing || state == State::Paused {
+            self.ready().await?;
+        }
+
+        // A crossfade still fading out the previous track is cut short.
+        self.other_deck().playbin.set_state(State::Null)?;
+
+        for (deck, sink) in self.decks.iter().zip(sinks) {
+            deck.set_output(&sink, bit_perfect);
+        }
+
+        if state == State::Playing || state == State::Paused {
+            let current_track = self.tracklist.read().await.currently_playing();
+
+            if let Some(track_id) = current_track {
+                // The uri may already point at the next track if it was queued.
+                let track_url = self.stream_url(track_id).await?;
+                self.playbin().set_property("uri", track_url);
+
+                // Seeking only works once the track has prerolled, see AsyncDone.
+                *self.resume_position.lock().await = position;
+
+                if state == State::Playing {
+                    self.play().await?;
+                } else {
+                    self.pause().await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn output(&self) -> Output {
+        self.output.read().await.clone()
+    }
+
+    /// The format of the stream that is playing.
+    pub async fn stream_format(&self) -> Option<StreamFormat> {
+        self.stream_format.read().await.clone()
+    }
+
+    async fn set_stream_format(&self, format: StreamFormat) -> Result<()> {
+        let mut stream_format = self.stream_format.write().await;
+
+        if stream_format.as_ref() != Some(&format) {
+            *stream_format = Some(format.clone());
+            self.broadcast
+                .tx
+                .send(Notification::StreamFormat { format })?;
+        }
+
+        Ok(())
+    }
+
+    /// Follows the caps of the decoder pads, which the caps deep notify watch reports.
+    async fn update_stream_format(
+        &self,
+        pad: &gstreamer::Pad,
+        caps: &gstreamer::Caps,
+    ) -> Result<()> {
+        let is_decoder = pad
+            .parent_element()
+            .and_then(|element| element.factory())
+            .is_some_and(|factory| factory.klass().contains("Decoder"));
+
+        if !is_decoder {
+            return Ok(());
+        }
+
+        let mut next_stream_format = self.next_stream_format.lock().await;
+
+        match pad.direction() {
+            PadDirection::Sink => next_stream_format.set_encoded_caps(caps),
+            PadDirection::Src => {
+                next_stream_format.set_decoded_caps(caps);
+
+                // A gapless next track is decoded ahead of time, it is only playing from StreamStart.
+                if self.queued_next_track.lock().await.is_none() {
+                    self.set_stream_format(next_stream_format.clone()).await?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Seek to a specified time in the current track.
+    pub async fn seek(&self, time: ClockTime, flags: Option<SeekFlags>) -> Result<()> {
+        let flags = flags.unwrap_or(SeekFlags::FLUSH | SeekFlags::TRICKMODE_KEY_UNITS);
+
+        self.playbin().seek_simple(flags, time)?;
+        self.broadcast
+            .tx
+            .send(Notification::Seeked { position: time })?;
+        Ok(())
+    }
+
+    #[instrument]
+    /// Jump forward in the currently playing track +10 seconds.
+    pub async fn jump_forward(&self) -> Result<()> {
+        if let (Some(current_position), Some(duration)) = (
+            self.playbin().query_position::<ClockTime>(),
+            self.playbin().query_duration::<ClockTime>(),
+        ) {
+            let ten_seconds = ClockTime::from_seconds(10);
+            let next_position = current_position + ten_seconds;
+
+            if next_position < duration {
+                self.seek(next_position, None).await?;
+            } else {
+                self.seek(duration, None).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Jump forward in the currently playing track -10 seconds.
+    pub async fn jump_backward(&self) -> Result<()> {
+        if let Some(current_position) = self.playbin().query_position::<ClockTime>() {
+            if current_position.seconds() < 10 {
+                self.seek(ClockTime::default(), None).await?;
+            } else {
+                let ten_seconds = ClockTime::from_seconds(10);
+                let seek_position = current_position - ten_seconds;
+
+                self.seek(seek_position, None).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Skip to a specific track in the tracklist.
+    pub async fn skip_to_position(&self, new_position: u32, force: bool) -> Result<()> {
+        let mut tracklist = self.tracklist.write().await;
+        let current_position = tracklist.current_position();
+
+        if !force && new_position < current_position && current_position == 1 {
+            self.seek(ClockTime::default(), None).await?;
+            return Ok(());
+        }
+
+        let total_tracks = tracklist.total();
+
+        // Typical previous skip functionality where if,
+        // the track is greater than 1 second into playing,
+        // then it goes to the beginning. If triggered again
+        // within a second after playing, it will skip to the previous track.
+        // Ignore if going from the last track to the first (EOS).
+        if !force
+            && new_position < current_position
+            && total_tracks != current_position
+            && new_position != 0
+        {
+            if let Some(current_player_position) = self.position() {
+                if current_player_position.seconds() > 1 {
+                    self.seek(ClockTime::default(), None).await?;
+                    return Ok(());
+                }
+            }
+        }
+
+        self.ready().await?;
+
+        if let Some(next_track) = tracklist.skip_to(new_position) {
+            let next_track_url = self.stream_url(next_track.id).await?;
+            self.playbin().set_property("uri", next_track_url);
+            self.play().await?;
+        } else if let Some(first_track) = tracklist.rewind() {
+            let first_track_url = self.stream_url(first_track.id).await?;
+
+            self.playbin().set_property("uri", first_track_url);
+        };
+
+        self.broadcast_track_list(&tracklist).await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    pub async fn next(&self) -> Result<()> {
+        let next_position = {
+            let lock = self.tracklist.read().await;
+            lock.skip_position().unwrap_or(lock.total())
+        };
+
+        self.skip_to_position(next_position, true).await
+    }
+
+    #[instrument]
+    pub async fn previous(&self) -> Result<()> {
+        let previous_position = {
+            let lock = self.tracklist.read().await;
+            lock.previous_position()
+        };
+
+        self.skip_to_position(previous_position.unwrap_or_default(), false)
+            .await
+    }
+
+    #[instrument]
+    /// Plays a single track.
+    pub async fn play_track(&self, track_id: u32) -> Result<()> {
+        self.ready().await?;
+
+        let track_url = self.stream_url(track_id).await?;
+        self.playbin().set_property("uri", track_url);
+        self.play().await?;
+
+        let mut tracklist = self.tracklist.write().await;
+
+        let track = queued_track(self.track(track_id).await?);
+
+        tracklist.queue = vec![track];
+        tracklist.list_type = TrackListType::Track;
+
+        self.broadcast_track_list(&tracklist).await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Plays a full album.
+    pub async fn play_album(&self, album_id: &str, index: u32) -> Result<()> {
+        self.ready().await?;
+
+        let mut tracklist = self.tracklist.write().await;
+
+        let album = self.album(album_id).await?;
+
+        let tracks = album.tracks.into_iter().map(queued_track).collect();
+        let list_type = TrackListType::Album(tracklist::AlbumTracklist {
+            title: album.title,
+            id: album.id,
+        });
+
+        self.play_queue(&mut tracklist, tracks, index, list_type)
+            .await
+    }
+
+    #[instrument]
+    /// Plays top tracks from artist starting from index.
+    pub async fn play_top_tracks(&self, artist_id: u32, index: u32) -> Result<()> {
+        self.ready().await?;
+
+        let mut tracklist = self.tracklist.write().await;
+
+        let artist = self.artist_page(artist_id).await?;
+
+        let tracks = artist.top_tracks.into_iter().map(queued_track).collect();
+        let list_type = TrackListType::ArtistTopTracks(tracklist::ArtistTracklist {
+            name: artist.name,
+            id: artist.id,
+        });
+
+        self.play_queue(&mut tracklist, tracks, index, list_type)
+            .await
+    }
+
+    #[instrument]
+    /// Plays an endless mix of an artist and similar artists.
+    /// More tracks are added whenever the queue is about to run out.
+    pub async fn play_artist_radio(&self, artist_id: u32) -> Result<()> {
+        self.ready().await?;
+
+        let client = self.client().await?;
+        let artist = client.artist(artist_id).await?;
+
+        *self.radio_history.lock().await = RadioHistory::default();
+        let tracks = self.radio_tracks(artist_id).await?;
+
+        let mut tracklist = self.tracklist.write().await;
+        let list_type = TrackListType::ArtistRadio(tracklist::ArtistTracklist {
+            name: artist.name.display,
+            id: artist.id,
+        });
+
+        self.play_queue(&mut tracklist, tracks, 0, list_type).await
+    }
+
+    /// The next tracks of a radio, picked from the artist and a few of the similar artists.
+    async fn radio_tracks(&self, artist_id: u32) -> Result<Vec<tracklist::Track>> {
+        let client = self.client().await?;
+
+        let mut candidates =
+            radio_candidates(&client, artist_id, Weight::Top, Weight::Album).await?;
+
+        let similar_artists: Vec<u32> = client
+            .similar_artists(artist_id, None)
+            .await
+            .map(|artists| artists.items.into_iter().map(|artist| artist.id).collect())
+            .unwrap_or_default();
+
+        let similar_artists: Vec<u32> = similar_artists
+            .choose_multiple(&mut rand::thread_rng(), RADIO_SIMILAR_ARTISTS)
+            .copied()
+            .collect();
+
+        for similar_artist in similar_artists {
+            match radio_candidates(
+                &client,
+                similar_artist,
+                Weight::SimilarTop,
+                Weight::SimilarAlbum,
+            )
+            .await
+            {
+                Ok(similar_candidates) => candidates.extend(similar_candidates),
+                Err(error) => debug!(?error),
+            }
+        }
+
+        Ok(self
+            .radio_history
+            .lock()
+            .await
+            .pick(candidates, RADIO_TRACKS))
+    }
+
+    #[instrument]
+    /// Plays all tracks in a playlist.
+    pub async fn play_playlist(&self, playlist_id: i64, index: u32) -> Result<()> {
+        self.ready().await?;
+
+        let client = self.client().await?;
+        let mut tracklist = self.tracklist.write().await;
+
+        let playlist = client.playlist(playlist_id).await?;
+
+        let tracks = playlist.tracks.unwrap_or_default();
+
+        let tracks = tracks
+            .items
+            .into_iter()
+            .map(|t| queued_track(t.into()))
+            .collect();
+        let list_type = TrackListType::Playlist(tracklist::PlaylistTracklist {
+            title: playlist.name,
+            id: playlist.id,
+        });
+
+        self.play_queue(&mut tracklist, tracks, index, list_type)
+            .await
+    }
+
+    #[instrument]
+    /// Plays a collection of tracks starting from index.
+    /// `list_type` tells the frontends where the tracks came from.
+    pub async fn play_tracks(
+        &self,
+        track_ids: &[u32],
+        index: u32,
+        list_type: TrackListType,
+    ) -> Result<()> {
+        self.ready().await?;
+
+        let mut tracklist = self.tracklist.write().await;
+
+        let tracks: Vec<_> = stream::iter(track_ids.iter().copied())
+            .map(|id| self.track(id))
+            .buffered(10)
+            .try_collect()
+            .await?;
+
+        let tracks = tracks.into_iter().map(queued_track).collect();
+
+        self.play_queue(&mut tracklist, tracks, index, list_type)
+            .await
+    }
+
+    /// Replaces the queue and plays it from the first playable track at or after `index`,
+    /// or from the first playable one when there is none after it.
+    /// Tracks without a stream are marked unplayable and passed over.
+    async fn play_queue(
+        &self,
+        tracklist: &mut Tracklist,
+        tracks: Vec<tracklist::Track>,
+        index: u32,
+        list_type: TrackListType,
+    ) -> Result<()> {
+        tracklist.queue = tracks;
+        tracklist.list_type = list_type;
+
+        let mut track_id = match tracklist.skip_to(index) {
+            Some(track) => Some(track.id),
+            None => tracklist.rewind().map(|track| track.id),
+        };
+
+        if tracklist.is_shuffled() {
+            tracklist.shuffle();
+        }
+
+        while let Some(id) = track_id {
+            match self.stream_url(id).await {
+                Ok(track_url) => {
+                    self.playbin().set_property("uri", track_url);
+                    self.play().await?;
+                    break;
+                }
+                Err(error) => {
+                    tracing::warn!("Track {id} can not be played, marking it unplayable: {error}");
+                    // Nobody may be listening.
+                    _ = self.broadcast.tx.send(Notification::PlaybackError {
+                        track_id: id,
+                        error,
+                    });
+
+                    let next_position = tracklist.next_position();
+                    tracklist.mark_unplayable();
+                    track_id = next_position
+                        .and_then(|position| tracklist.skip_to(position))
+                        .map(|track| track.id);
+                }
+            }
+        }
+
+        self.broadcast_track_list(tracklist).await
+    }
+
+    #[instrument]
+    /// Adds a track to the queue, either at the end or directly after the current track.
+    pub async fn queue_track(&self, track_id: u32, play_next: bool) -> Result<()> {
+        let track = self.track(track_id).await?;
+
+        let tracks = vec![queued_track(track)];
+
+        self.add_to_queue(tracks, play_next).await
+    }
+
+    #[instrument]
+    /// Adds the tracks of an album to the queue.
+    pub async fn queue_album(&self, album_id: &str, play_next: bool) -> Result<()> {
+        let album = self.album(album_id).await?;
+
+        let tracks = album.tracks.into_iter().map(queued_track).collect();
+
+        self.add_to_queue(tracks, play_next).await
+    }
+
+    #[instrument]
+    /// Adds the tracks of a playlist to the queue.
+    pub async fn queue_playlist(&self, playlist_id: i64, play_next: bool) -> Result<()> {
+        let client = self.client().await?;
+        let playlist = client.playlist(playlist_id).await?;
+
+        let tracks = playlist
+            .tracks
+            .unwrap_or_default()
+            .items
+            .into_iter()
+            .map(|t| queued_track(t.into()))
+            .collect();
+
+        self.add_to_queue(tracks, play_next).await
+    }
+
+    async fn add_to_queue(&self, tracks: Vec<tracklist::Track>, play_next: bool) -> Result<()> {
+        let mut tracklist = self.tracklist.write().await;
+        let was_empty = tracklist.queue.is_empty();
+
+        if play_next {
+            tracklist.insert_next(tracks);
+        } else {
+            tracklist.append(tracks);
+        }
+
+        // Nothing was loaded, so start playing what was just added.
+        if was_empty {
+            if let Some(track) = tracklist.skip_to(0) {
+                let track_url = self.stream_url(track.id).await?;
+                self.ready().await?;
+                self.playbin().set_property("uri", track_url);
+                self.play().await?;
+            }
+        } else {
+            self.requeue_next_track(&tracklist).await?;
+        }
+
+        self.broadcast_track_list(&tracklist).await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Removes the track at a position in the queue. The current track can not be removed.
+    pub async fn remove_from_queue(&self, position: u32) -> Result<()> {
+        let mut tracklist = self.tracklist.write().await;
+
+        if tracklist.remove(position).is_none() {
+            return Err(Error::QueuePosition { position });
+        }
+
+        self.requeue_next_track(&tracklist).await?;
+        self.broadcast_track_list(&tracklist).await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Moves a track in the queue to a new position.
+    pub async fn move_in_queue(&self, from: u32, to: u32) -> Result<()> {
+        let mut tracklist = self.tracklist.write().await;
+
+        if tracklist.move_track(from, to).is_none() {
+            return Err(Error::QueuePosition {
+                position: from.max(to),
+            });
+        }
+
+        self.requeue_next_track(&tracklist).await?;
+        self.broadcast_track_list(&tracklist).await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Removes every track after the current one from the queue.
+    pub async fn clear_upcoming(&self) -> Result<()> {
+        let mut tracklist = self.tracklist.write().await;
+
+        tracklist.clear_upcoming();
+
+        self.requeue_next_track(&tracklist).await?;
+        self.broadcast_track_list(&tracklist).await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Turn shuffle on or off. The current track keeps playing either way.
+    pub async fn set_shuffle(&self, shuffle: bool) -> Result<()> {
+        let mut tracklist = self.tracklist.write().await;
+
+        if tracklist.is_shuffled() == shuffle {
+            return Ok(());
+        }
+
+        if shuffle {
+            tracklist.shuffle();
+        } else {
+            tracklist.unshuffle();
+        }
+
+        self.requeue_next_track(&tracklist).await?;
+        self.broadcast_track_list(&tracklist).await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Toggle shuffle.
+    pub async fn toggle_shuffle(&self) -> Result<()> {
+        let shuffle = !self.tracklist.read().await.is_shuffled();
+        self.set_shuffle(shuffle).await
+    }
+
+    #[instrument]
+    /// Is shuffle on?
+    pub async fn shuffle(&self) -> bool {
+        self.tracklist.read().await.is_shuffled()
+    }
+
+    #[instrument]
+    /// Set how the queue repeats when a track or the whole queue ends.
+    pub async fn set_repeat_mode(&self, repeat_mode: RepeatMode) -> Result<()> {
+        let mut tracklist = self.tracklist.write().await;
+        tracklist.repeat_mode = repeat_mode;
+
+        self.requeue_next_track(&tracklist).await?;
+        self.broadcast_track_list(&tracklist).await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Cycle through the repeat modes: off, repeat all, repeat one.
+    pub async fn cycle_repeat_mode(&self) -> Result<()> {
+        let repeat_mode = match self.tracklist.read().await.repeat_mode {
+            RepeatMode::None => RepeatMode::All,
+            RepeatMode::All => RepeatMode::Track,
+            RepeatMode::Track => RepeatMode::None,
+        };
+
+        self.set_repeat_mode(repeat_mode).await
+    }
+
+    #[instrument]
+    /// Current repeat mode
+    pub async fn repeat_mode(&self) -> RepeatMode {
+        self.tracklist.read().await.repeat_mode
+    }
+
+    #[instrument]
+    /// Turn autoplay on or off. Tracks it already added stay in the queue.
+    pub async fn set_autoplay(&self, autoplay: bool) -> Result<()> {
+        let mut tracklist = self.tracklist.write().await;
+        tracklist.autoplay = autoplay;
+        *self.refilled_after.lock().await = None;
+
+        self.broadcast_track_list(&tracklist).await
+    }
+
+    #[instrument]
+    /// Toggle autoplay.
+    pub async fn toggle_autoplay(&self) -> Result<()> {
+        let autoplay = !self.tracklist.read().await.autoplay;
+        self.set_autoplay(autoplay).await
+    }
+
+    #[instrument]
+    /// Is autoplay on?
+    pub async fn autoplay(&self) -> bool {
+        self.tracklist.read().await.autoplay
+    }
+
+    #[instrument]
+    /// Removes the upcoming tracks that autoplay added.
+    pub async fn clear_autoplay(&self) -> Result<()> {
+        let mut tracklist = self.tracklist.write().await;
+
+        tracklist.clear_autoplay();
+
+        self.requeue_next_track(&tracklist).await?;
+        self.broadcast_track_list(&tracklist).await?;
+
+        Ok(())
+    }
+
+    /// Looks for more music in the background once the last track of the queue is playing.
+    /// This runs once per track, so it doesn't keep asking when nothing is found.
+    async fn refill_when_due(&self) {
+        let last_track = {
+            let tracklist = self.tracklist.read().await;
+
+            match tracklist.needs_more_tracks() {
+                true => tracklist.currently_playing(),
+                false => None,
+            }
+        };
+
+        let Some(last_track) = last_track else {
+            return;
+        };
+
+        {
+            let mut refilled_after = self.refilled_after.lock().await;
+
+            if *refilled_after == Some(last_track) {
+                return;
+            }
+
+            *refilled_after = Some(last_track);
+        }
+
+        let player = self.clone();
+        tokio::spawn(async move {
+            if let Err(error) = player.refill_after(last_track).await {
+                debug!(?error);
+            }
+        });
+    }
+
+    #[instrument]
+    /// Appends more music after a track. A radio continues with its artist. Autoplay
+    /// adds a suggested album when playing albums, otherwise top tracks of similar artists.
+    async fn refill_after(&self, track_id: u32) -> Result<()> {
+        let (list_type, queued) = {
+            let tracklist = self.tracklist.read().await;
+            let queued: Vec<u32> = tracklist.queue.iter().map(|track| track.id).collect();
+
+            (tracklist.list_type.clone(), queued)
+        };
+
+        let tracks = match list_type {
+            TrackListType::ArtistRadio(artist) => self.radio_tracks(artist.id).await?,
+            TrackListType::Album(_) => self.autoplay_album(track_id).await?,
+            _ => self.autoplay_tracks(track_id, &queued).await?,
+        };
+
+        if tracks.is_empty() {
+            tracing::info!("Found nothing to add to the queue");
+            return Ok(());
+        }
+
+        let mut tracklist = self.tracklist.write().await;
+
+        // The queue may have changed or autoplay been turned off while looking.
+        if !tracklist.needs_more_tracks() || tracklist.currently_playing() != Some(track_id) {
+            return Ok(());
+        }
+
+        tracing::info!("Adding {} tracks to the queue", tracks.len());
+        tracklist.append(tracks);
+
+        self.broadcast_track_list(&tracklist).await
+    }
+
+    /// The tracks of the first suggested album that autoplay hasn't picked before.
+    async fn autoplay_album(&self, track_id: u32) -> Result<Vec<tracklist::Track>> {
+        let client = self.client().await?;
+
+        let Some(album_id) = track_album(&client, track_id).await? else {
+            return Ok(vec![]);
+        };
+
+        let suggestions = client.suggested_albums(&album_id).await?;
+
+        let suggested_album = {
+            let mut history = self.autoplay_history.lock().await;
+            history.add_album(&album_id);
+
+            let suggested_album = suggestions
+                .albums
+                .items
+                .into_iter()
+                .find(|album| album.rights.streamable && !history.has_album(&album.id));
+
+            if let Some(album) = &suggested_album {
+                history.add_album(&album.id);
+            }
+
+            suggested_album
+        };
+
+        let Some(suggested_album) = suggested_album else {
+            return Ok(vec![]);
+        };
+
+        let album: Album = client.album(&suggested_album.id).await?.into();
+
+        Ok(album
+            .tracks
+            .into_iter()
+            .filter(|t| t.available)
+            .map(|t| tracklist::Track {
+                added_by_autoplay: true,
+                ..t.into()
+            })
+            .collect())
+    }
+
+    /// A top track from each of a few artists similar to the one playing,
+    /// skipping tracks already in the queue or picked before.
+    async fn autoplay_tracks(
+        &self,
+        track_id: u32,
+        queued: &[u32],
+    ) -> Result<Vec<tracklist::Track>> {
+        let client = self.client().await?;
+        let track = client.track(track_id).await?;
+
+        let artist_id = track
+            .performer
+            .and_then(|performer| u32::try_from(performer.id).ok())
+            .or(track.album.map(|album| album.artist.id));
+
+        let Some(artist_id) = artist_id else {
+            return Ok(vec![]);
+        };
+
+        let similar_artists = client.similar_artists(artist_id, None).await?;
+        let mut history = self.autoplay_history.lock().await;
+        let mut tracks = vec![];
+
+        for artist in similar_artists.items {
+            if tracks.len() == AUTOPLAY_TRACKS {
+                break;
+            }
+
+            let Ok(artist) = client.artist(artist.id).await else {
+                continue;
+            };
+
+            let top_track = ArtistPage::from(artist)
+                .top_tracks
+                .into_iter()
+                .find(|t| t.available && !queued.contains(&t.id) && !history.has_track(t.id));
+
+            if let Some(top_track) = top_track {
+                history.add_track(top_track.id);
+
+                tracks.push(tracklist::Track {
+                    added_by_autoplay: true,
+                    ..top_track.into()
+                });
+            }
+        }
+
+        Ok(tracks)
+    }
+
+    #[instrument]
+    /// In response to the about-to-finish signal,
+    /// prepare the next track by downloading the stream url.
+    async fn prep_next_track(&self) -> Result<()> {
+        tracing::info!("Prepping for next track");
+
+        let tracklist = self.tracklist.read().await;
+
+        if self.stops_after_current(&tracklist).await {
+            tracing::info!("Sleep timer stops playback after this track");
+            return Ok(());
+        }
+
+        if self.crossfades_into_next(&tracklist).await {
+            tracing::info!("Crossfading into the next track instead");
+            return Ok(());
+        }
+
+        self.queue_next_track(&tracklist).await
+    }
+
+    /// Whether the next track should fade in over the end of the current one
+    /// instead of following it gaplessly, which is never the case within an album.
+    async fn crossfades_into_next(&self, tracklist: &Tracklist) -> bool {
+        if self.fades.read().await.crossfade <= 0.0
+            || self.bit_perfect()
+            || !self.output().await.can_overlap()
+            || self.stops_after_current(tracklist).await
+        {
+            return false;
+        }
+
+        self.album_continues(tracklist).await == Some(false)
+    }
+
+    /// Whether the next track is on the same album as the current one,
+    /// unknown when there is no next track or the albums can't be looked up.
+    async fn album_continues(&self, tracklist: &Tracklist) -> Option<bool> {
+        let current_track = tracklist.currently_playing()?;
+        let next_track = tracklist
+            .next_position()
+            .and_then(|position| tracklist.queue.get(position as usize))?;
+
+        if current_track == next_track.id {
+            return Some(true);
+        }
+
+        let client = self.client().await.ok()?;
+        let current_album = track_album(&client, current_track).await.ok()?;
+        let next_album = track_album(&client, next_track.id).await.ok()?;
+
+        Some(current_album.is_some() && current_album == next_album)
+    }
+
+    /// Starts the next track on the idle deck and fades it in over the time left
+    /// of the current track, which fades out on the other deck.
+    async fn crossfade(&self, overlap: Duration) -> Result<()> {
+        let mut tracklist = self.tracklist.write().await;
+
+        if self.queued_next_track.lock().await.is_some()
+            || !self.crossfades_into_next(&tracklist).await
+        {
+            return Ok(());
+        }
+
+        let Some(position) = tracklist.next_position() else {
+            return Ok(());
+        };
+        let Some(track_id) = tracklist.queue.get(position as usize).map(|track| track.id) else {
+            return Ok(());
+        };
+
+        tracing::info!("Crossfading over {overlap:?}");
+
+        let track_url = self.stream_url(track_id).await?;
+        let replay_gain = self.replay_gain_volume(track_id).await.unwrap_or(1.0);
+        tracklist.skip_to(position);
+
+        let outgoing = self.active_deck.load(Ordering::Relaxed);
+        let incoming = 1 - outgoing;
+
+        let deck = &self.decks[incoming];
+        deck.playbin.set_state(State::Null)?;
+        deck.playbin.set_property("uri", track_url);
+        deck.set_replay_gain(replay_gain);
+        deck.set_fade_volume(0.0);
+
+        self.active_deck.store(incoming, Ordering::Relaxed);
+        self.set_player_state(State::Playing).await?;
+        self.broadcast_track_list(&tracklist).await?;
+
+        let player = self.clone();
+        tokio::spawn(async move {
+            let outgoing_deck = &player.decks[outgoing];
+
+            tokio::join!(
+                player.decks[incoming].fade(1.0, overlap),
+                outgoing_deck.fade(0.0, overlap)
+            );
+
+            // The deck may have been picked up again by another crossfade in the meantime.
+            if player.active_deck.load(Ordering::Relaxed) == incoming {
+                if let Err(error) = outgoing_deck.playbin.set_state(State::Null) {
+                    debug!(?error);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Sets the uri of the track that should follow the current one
+    /// so playbin can switch to it gaplessly.
+    async fn queue_next_track(&self, tracklist: &Tracklist) -> Result<()> {
+        let total_tracks = tracklist.total();
+        let current_position = tracklist.current_position();
+
+        tracing::info!(
+            "Total tracks: {}, current position: {}, repeat mode: {:?}",
+            total_tracks,
+            current_position,
+            tracklist.repeat_mode
+        );
+
+        let next_track = tracklist
+            .next_position()
+            .and_then(|position| tracklist.queue.get(position as usize));
+
+        if next_track.is_none() {
+            tracing::info!("No more tracks left");
+        }
+
+        if let Some(next_track) = next_track {
+            let url = self.stream_url(next_track.id).await?;
+
+            // Look up the gain ahead of time, so it can be switched to right as the track starts.
+            let replay_gain = match self.replay_gain_volume(next_track.id).await {
+                Ok(replay_gain) => Some(replay_gain),
+                Err(error) => {
+                    debug!("failed to look up replaygain of the next track: {error}");
+                    None
+                }
+            };
+
+            self.playbin().set_property("uri", url);
+            *self.queued_next_track.lock().await = Some(next_track.id);
+            *self.next_replay_gain.lock().await = replay_gain;
+        };
+
+        Ok(())
+    }
+
+    /// Stops once the queue has played, ready to play it again from the start.
+    async fn stop_at_end_of_queue(&self, tracklist: &mut Tracklist) -> Result<()> {
+        if let Some(first_track) = tracklist.rewind() {
+            let track_url = self.stream_url(first_track.id).await?;
+            self.playbin().set_property("uri", track_url);
+        };
+
+        self.set_target_state(gstreamer::State::Ready).await;
+        self.ready().await?;
+        self.broadcast_track_list(tracklist).await
+    }
+
+    /// The stream url for a track: the downloaded file if it is kept offline,
+    /// otherwise the prefetched url if it is still fresh.
+    async fn stream_url(&self, track_id: u32) -> Result<String> {
+        if local::is_local(track_id) {
+            let library = self.local_library().await?;
+            let path = library.path(track_id).ok_or(Error::NotInLibrary)?;
+
+            return Ok(gstreamer::glib::filename_to_uri(path, None)?.to_string());
+        }
+
+        if let Ok(cache) = self.offline_cache().await {
+            if let Some(path) = cache.path(track_id).await {
+                return Ok(gstreamer::glib::filename_to_uri(path, None)?.to_string());
+            }
+        }
+
+        if let Some(url) = self.prefetch.lock().await.take(track_id) {
+            return Ok(url);
+        }
+
+        Ok(self.client().await?.track_url(track_id).await?)
+    }
+
+    /// Fetches the url of the next track well before it is needed, and again before it expires,
+    /// so the track can start even if the network is briefly gone by then.
+    async fn prefetch_when_due(&self) {
+        if self.queued_next_track.lock().await.is_some() {
+            return;
+        }
+
+        let next_track = {
+            let tracklist = self.tracklist.read().await;
+            tracklist
+                .next_position()
+                .and_then(|position| tracklist.queue.get(position as usize))
+                .map(|track| track.id)
+        };
+
+        let Some(track_id) = next_track else {
+            return;
+        };
+
+        // Local tracks and tracks kept offline are played from their file.
+        if local::is_local(track_id) {
+            return;
+        }
+        if let Ok(cache) = self.offline_cache().await {
+            if cache.contains(track_id).await {
+                return;
+            }
+        }
+
+        {
+            let mut prefetch = self.prefetch.lock().await;
+            if !prefetch.is_due(track_id) {
+                return;
+            }
+
+            prefetch.attempt();
+        }
+
+        let player = self.clone();
+        tokio::spawn(async move {
+            let url = match player.client().await {
+                Ok(client) => client.track_url(track_id).await.map_err(Error::from),
+                Err(error) => Err(error),
+            };
+
+            match url {
+                Ok(url) => player
+                    .prefetch
+                    .lock()
+                    .await
+                    .set(StreamUrl::new(track_id, url)),
+                Err(error) => debug!("failed to prefetch the next track url: {error}"),
+            }
+        });
+    }
+
+    /// Reloads the current track with a new url after playback failed,
+    /// like when the network dropped, to pick up where it stopped.
+    async fn recover(&self, error: Error) -> Result<()> {
+        if self.recovering.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let position = match self.resume_position.lock().await.take() {
+            Some(position) => position,
+            None => self.position().unwrap_or_default(),
+        };
+
+        self.ready().await?;
+
+        let Some(track_id) = self.tracklist.read().await.currently_playing() else {
+            self.recovering.store(false, Ordering::Relaxed);
+            self.broadcast.tx.send(Notification::Error { error })?;
+            return Ok(());
+        };
+
+        let player = self.clone();
+        tokio::spawn(async move {
+            if let Err(error) = player.reload(track_id, position, error).await {
+                debug!(?error);
+            }
+
+            player.recovering.store(false, Ordering::Relaxed);
+        });
+
+        Ok(())
+    }
+
+    /// Marks the current track unplayable and moves on to the next playable one.
+    async fn skip_unplayable(&self) -> Result<()> {
+        let next_position = {
+            let mut tracklist = self.tracklist.write().await;
+            let next_position = tracklist.next_position().unwrap_or(tracklist.total());
+            tracklist.mark_unplayable();
+            next_position
+        };
+
+        self.skip_to_position(next_position, true).await
+    }
+
+    /// Tries to play a failed track again from a position, waiting longer before each attempt.
+    /// Gives up and moves on to the next track after a few attempts.
+    async fn reload(&self, track_id: u32, position: ClockTime, error: Error) -> Result<()> {
+        loop {
+            let Some(backoff) = self.recovery.lock().await.retry(track_id) else {
+                tracing::warn!("Track {track_id} keeps failing, marking it unplayable");
+                self.broadcast
+                    .tx
+                    .send(Notification::PlaybackError { track_id, error })?;
+                return self.skip_unplayable().await;
+            };
+
+            tracing::info!("Reloading track {track_id} at {position} in {backoff:?}");
+            tokio::time::sleep(backoff).await;
+
+            // Playback may have been paused or moved on in the meantime.
+            if *self.target_status.read().await != State::Playing
+                || self.tracklist.read().await.currently_playing() != Some(track_id)
+            {
+                return Ok(());
+            }
+
+            match self.stream_url(track_id).await {
+                Ok(url) => {
+                    *self.resume_position.lock().await = Some(position);
+                    self.playbin().set_property("uri", url);
+                    return self.play().await;
+                }
+                Err(error) => debug!("failed to get a new url for track {track_id}: {error}"),
+            }
+        }
+    }
+
+    /// After the queue changes, update the upcoming uri
+    /// if the next track was already handed to playbin.
+    /// If it was removed and nothing follows the current track anymore,
+    /// playback stops instead, until a track is added again.
+    async fn requeue_next_track(&self, tracklist: &Tracklist) -> Result<()> {
+        let queued = *self.queued_next_track.lock().await;
+
+        if queued.is_none() && !self.stale_next_track.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let next_track = tracklist
+            .next_position()
+            .and_then(|position| tracklist.queue.get(position as usize));
+
+        match next_track {
+            Some(next_track) if Some(next_track.id) != queued => {
+                self.stale_next_track.store(false, Ordering::Relaxed);
+                self.queue_next_track(tracklist).await?
+            }
+            Some(_) => {}
+            None => {
+                tracing::info!(
+                    "Next track was removed after it was queued, stopping after this one"
+                );
+                *self.queued_next_track.lock().await = None;
+                self.stale_next_track.store(true, Ordering::Relaxed);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[instrument]
+    /// Get a notification channel receiver
+    pub fn notify_receiver(&self) -> Receiver<Notification> {
+        self.broadcast.rx.resubscribe()
+    }
+
+    #[instrument]
+    /// Returns the current track list loaded in the player.
+    pub async fn current_tracklist(&self) -> Tracklist {
+        self.tracklist.read().await.clone()
+    }
+
+    #[instrument]
+    /// The queue as last broadcast, to keep up to date with [`Notification::QueueChanged`].
+    pub async fn tracklist_copy(&self) -> TracklistCopy {
+        self.broadcast_tracklist.lock().await.clone()
+    }
+
+    #[instrument]
+    /// Returns the current track loaded in the player.
+    pub async fn current_track(&self) -> Result<Option<Track>> {
+        Ok(self
+            .tracklist
+            .read()
+            .await
+            .current_track()
+            .cloned()
+            .map(Track::from))
+    }
+
+    #[instrument]
+    /// Searches Qobuz and the local library, local results first.
+    /// Only local results are returned while Qobuz can't be reached.
+    pub async fn search(&self, query: &str) -> Result<SearchResults> {
+        let local = match self.local_library().await {
+            Ok(library) => library.search(query),
+            Err(_) => SearchResults::default(),
+        };
+
+        let client = match self.client().await {
+            Ok(client) => client,
+            Err(Error::Offline) => return Ok(local),
+            Err(error) => return Err(error),
+        };
+        let user_id = client.get_user_id();
+
+        let results = match client.search_all(query, 20).await.map_err(Error::from) {
+            Ok(results) => results,
+            // Without Qobuz only the local library is searched.
+            Err(Error::Offline) => return Ok(local),
+            Err(error) => return Err(error),
+        };
+        let results = models::parse_search_results(results, user_id);
+
+        Ok(SearchResults {
+            query: results.query,
+            albums: [local.albums, results.albums].concat(),
+            artists: [local.artists, results.artists].concat(),
+            playlists: results.playlists,
+            tracks: [local.tracks, results.tracks].concat(),
+        })
+    }
+
+    #[instrument]
+    /// Get artist page
+    pub async fn artist_page(&self, artist_id: u32) -> Result<ArtistPage> {
+        if local::is_local(artist_id) {
+            let library = self.local_library().await?;
+            return library.artist(artist_id).ok_or(Error::NotInLibrary);
+        }
+
+        let client = self.client().await?;
+        let artist = client.artist(artist_id).await?;
+        Ok(artist.into())
+    }
+
+    #[instrument]
+    /// Get similar artists
+    pub async fn similar_artists(&self, artist_id: u32) -> Result<Vec<Artist>> {
+        if local::is_local(artist_id) {
+            return Ok(vec![]);
+        }
+
+        let client = self.client().await?;
+        let similar_artists = client.similar_artists(artist_id, None).await?;
+
+        Ok(similar_artists
+            .items
+            .into_iter()
+            .map(|s_a| s_a.into())
+            .collect())
+    }
+
+    #[instrument]
+    /// Get album
+    pub async fn album(&self, id: &str) -> Result<Album> {
+        if local::is_local_album(id) {
+            let library = self.local_library().await?;
+            return library.album(id).ok_or(Error::NotInLibrary);
+        }
+
+        let client = self.client().await?;
+        let album = client.album(id).await?;
+        Ok(album.into())
+    }
+
+    #[instrument]
+    /// Get track
+    pub async fn track(&self, id: u32) -> Result<Track> {
+        if local::is_local(id) {
+            let library = self.local_library().await?;
+            return library.track(id).ok_or(Error::NotInLibrary);
+        }
+
+        let client = self.client().await?;
+        Ok(client.track(id).await?.into())
+    }
+
+    #[instrument]
+    /// Get suggested albums
+    pub async fn suggested_albums(&self, album_id: &str) -> Result<Vec<Album>> {
+        if local::is_local_album(album_id) {
+            return Ok(vec![]);
+        }
+
+        let client = self.client().await?;
+        let suggested_albums = client.suggested_albums(album_id).await?;
+
+        Ok(suggested_albums
+            .albums
+            .items
+            .into_iter()
+            .map(|x| x.into())
+            .collect())
+    }
+
+    #[instrument]
+    /// Get featured albums
+    pub async fn featured_albums(&self, featured_type: AlbumFeaturedType) -> Result<Vec<Album>> {
+        featured_albums(&self.client().await?, featured_type).await
+    }
+
+    #[instrument]
+    /// Get featured playlists
+    pub async fn featured_playlists(
+        &self,
+        featured_type: PlaylistFeaturedType,
+    ) -> Result<Vec<Playlist>> {
+        featured_playlists(&self.client().await?, featured_type).await
+    }
+
+    #[instrument]
+    /// Get playlist
+    pub async fn playlist(&self, id: i64) -> Result<Playlist> {
+        let client = self.client().await?;
+        let user_id = client.get_user_id();
+        let playlist = client.playlist(id).await?;
+
+        Ok(models::parse_playlist(playlist, user_id))
+    }
+
+    #[instrument]
+    /// Create a new playlist owned by the current user.
+    pub async fn create_playlist(
+        &self,
+        name: String,
+        is_public: bool,
+        description: Option<String>,
+    ) -> Result<Playlist> {
+        let client = self.client().await?;
+        let user_id = client.get_user_id();
+        let playlist = client
+            .create_playlist(name, is_public, description, None)
+            .await?;
+
+        FAVORITES.lock().await.cache_clear();
+        self.broadcast_playlist_changed(playlist.id)?;
+
+        Ok(models::parse_playlist(playlist, user_id))
+    }
+
+    #[instrument]
+    /// Delete a playlist owned by the current user.
+    pub async fn delete_playlist(&self, playlist_id: i64) -> Result<()> {
+        let client = self.client().await?;
+        client.delete_playlist(playlist_id.to_string()).await?;
+
+        FAVORITES.lock().await.cache_clear();
+        self.broadcast_playlist_changed(playlist_id)?;
+        Ok(())
+    }
+
+    #[instrument]
+    /// Add tracks to the end of a playlist.
+    pub async fn playlist_add_tracks(&self, playlist_id: i64, track_ids: &[u32]) -> Result<()> {
+        let client = self.client().await?;
+        let track_ids = track_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>();
+
+        client
+            .playlist_add_track(
+                &playlist_id.to_string(),
+                track_ids.iter().map(|id| id.as_str()).collect(),
+            )
+            .await?;
+
+        FAVORITES.lock().await.cache_clear();
+        self.broadcast_playlist_changed(playlist_id)?;
+        Ok(())
+    }
+
+    #[instrument]
+    /// Remove the track at a position in a playlist.
+    /// By position, since a playlist may contain the same track more than once.
+    pub async fn playlist_remove_track(&self, playlist_id: i64, position: usize) -> Result<()> {
+        let client = self.client().await?;
+        let playlist_track_id = playlist_track_id(&client, playlist_id, position).await?;
+
+        client
+            .playlist_delete_track(playlist_id.to_string(), vec![playlist_track_id.to_string()])
+            .await?;
+
+        FAVORITES.lock().await.cache_clear();
+        self.broadcast_playlist_changed(playlist_id)?;
+        Ok(())
+    }
 
-#[instrument]
-/// Current track duration.
-pub fn duration() -> Option<ClockTime> {
-    PLAYBIN.query_duration::<ClockTime>()
-}
+    #[instrument]
+    /// Move the track at position `from` in a playlist so it ends up at position `to`.
+    pub async fn playlist_move_track(
+        &self,
+        playlist_id: i64,
+        from: usize,
+        to: usize,
+    ) -> Result<()> {
+        let client = self.client().await?;
+        let playlist_track_id = playlist_track_id(&client, playlist_id, from).await?;
+
+        // Qobuz inserts the track before the one currently at the given position,
+        // which still counts the moved track when moving it down.
+        let insert_before = if to > from { to + 1 } else { to };
+
+        client
+            .update_playlist_track_position(
+                insert_before,
+                &playlist_id.to_string(),
+                &playlist_track_id.to_string(),
+            )
+            .await?;
 
-#[instrument]
-/// Current volume
-pub fn volume() -> f64 {
-    PLAYBIN.property::<f64>("volume")
-}
+        FAVORITES.lock().await.cache_clear();
+        self.broadcast_playlist_changed(playlist_id)?;
+        Ok(())
+    }
 
-#[instrument]
-/// Set volume
-pub fn set_volume(value: f64) {
-    PLAYBIN.set_property("volume", value);
+    #[instrument]
+    /// Fetch the albums for a specific artist.
+    pub async fn artist_albums(&self, artist_id: u32) -> Result<Vec<Album>> {
+        if local::is_local(artist_id) {
+            return Ok(self.local_library().await?.artist_albums(artist_id));
+        }
 
-    tokio::task::spawn(async move {
-        BROADCAST_CHANNELS
-            .tx
-            .send(Notification::Volume { volume: value })
-            .unwrap();
-    });
-}
+        artist_albums(&self.client().await?, artist_id).await
+    }
 
-#[instrument]
-/// Seek to a specified time in the current track.
-pub async fn seek(time: ClockTime, flags: Option<SeekFlags>) -> Result<()> {
-    let flags = flags.unwrap_or(SeekFlags::FLUSH | SeekFlags::TRICKMODE_KEY_UNITS);
+    fn broadcast_playlist_changed(&self, id: i64) -> Result<()> {
+        self.broadcast
+            .tx
+            .send(Notification::PlaylistChanged { id })?;
+        Ok(())
+    }
 
-    PLAYBIN.seek_simple(flags, time)?;
-    Ok(())
-}
+    #[instrument]
+    /// Add album to favorites
+    pub async fn add_favorite_album(&self, id: &str) -> Result<()> {
+        let client = self.client().await?;
+        client.add_favorite_album(id).await?;
 
-#[instrument]
-/// Jump forward in the currently playing track +10 seconds.
-pub async fn jump_forward() -> Result<()> {
-    if let (Some(current_position), Some(duration)) = (
-        PLAYBIN.query_position::<ClockTime>(),
-        PLAYBIN.query_duration::<ClockTime>(),
-    ) {
-        let ten_seconds = ClockTime::from_seconds(10);
-        let next_position = current_position + ten_seconds;
-
-        if next_position < duration {
-            seek(next_position, None).await?;
-        } else {
-            seek(duration, None).await?;
-        }
+        FAVORITES.lock().await.cache_clear();
+        Ok(())
     }
 
-    Ok(())
-}
+    #[instrument]
+    /// Remove album from favorites
+    pub async fn remove_favorite_album(&self, id: &str) -> Result<()> {
+        let client = self.client().await?;
+        client.remove_favorite_album(id).await?;
 
-#[instrument]
-/// Jump forward in the currently playing track -10 seconds.
-pub async fn jump_backward() -> Result<()> {
-    if let Some(current_position) = PLAYBIN.query_position::<ClockTime>() {
-        if current_position.seconds() < 10 {
-            seek(ClockTime::default(), None).await?;
-        } else {
-            let ten_seconds = ClockTime::from_seconds(10);
-            let seek_position = current_position - ten_seconds;
+        FAVORITES.lock().await.cache_clear();
+        Ok(())
+    }
 
-            seek(seek_position, None).await?;
-        }
+    #[instrument]
+    /// Add artist to favorites
+    pub async fn add_favorite_artist(&self, id: &str) -> Result<()> {
+        let client = self.client().await?;
+        client.add_favorite_artist(id).await?;
+
+        FAVORITES.lock().await.cache_clear();
+        Ok(())
     }
 
-    Ok(())
-}
+    #[instrument]
+    /// Remove artist from favorites
+    pub async fn remove_favorite_artist(&self, id: &str) -> Result<()> {
+        let client = self.client().await?;
+        client.remove_favorite_artist(id).await?;
 
-#[instrument]
-/// Skip to a specific track in the tracklist.
-pub async fn skip_to_position(new_position: u32, force: bool) -> Result<()> {
-    let mut tracklist = TRACKLIST.write().await;
-    let current_position = tracklist.current_position();
-
-    if !force && new_position < current_position && current_position == 1 {
-        seek(ClockTime::default(), None).await?;
-        return Ok(());
-    }
-
-    let total_tracks = tracklist.total();
-
-    // Typical previous skip functionality where if,
-    // the track is greater than 1 second into playing,
-    // then it goes to the beginning. If triggered again
-    // within a second after playing, it will skip to the previous track.
-    // Ignore if going from the last track to the first (EOS).
-    if !force
-        && new_position < current_position
-        && total_tracks != current_position
-        && new_position != 0
-    {
-        if let Some(current_player_position) = position() {
-            if current_player_position.seconds() > 1 {
-                seek(ClockTime::default(), None).await?;
-                return Ok(());
-            }
-        }
+        FAVORITES.lock().await.cache_clear();
+        Ok(())
     }
 
-    ready().await?;
+    #[instrument]
+    /// Add playlist to favorites
+    pub async fn add_favorite_playlist(&self, id: &str) -> Result<()> {
+        let client = self.client().await?;
+        client.add_favorite_playlist(id).await?;
 
-    let client = get_client().await;
+        FAVORITES.lock().await.cache_clear();
+        Ok(())
+    }
 
-    if let Some(next_track) = skip_to_track(&mut tracklist, new_position) {
-        let next_track_url = client.track_url(next_track.id).await?;
-        PLAYBIN.set_property("uri", next_track_url);
-        play().await?;
-    } else if let Some(first_track) = tracklist.queue.first_mut() {
-        first_track.status = TrackStatus::Playing;
-        let first_track_url = client.track_url(first_track.id).await?;
+    #[instrument]
+    /// Remove playlist from favorites
+    pub async fn remove_favorite_playlist(&self, id: &str) -> Result<()> {
+        let client = self.client().await?;
+        client.remove_favorite_playlist(id).await?;
 
-        PLAYBIN.set_property("uri", first_track_url);
-    };
+        FAVORITES.lock().await.cache_clear();
+        Ok(())
+    }
 
-    broadcast_track_list(&tracklist).await?;
+    #[instrument]
+    /// Get favorites
+    pub async fn favorites(&self) -> Result<Favorites> {
+        favorites(&self.client().await?).await
+    }
 
-    Ok(())
-}
+    #[instrument]
+    /// Inserts the most recent position into the state at a set interval.
+    async fn clock_loop(&self) {
+        debug!("starting clock loop");
 
-#[instrument]
-pub async fn next() -> Result<()> {
-    let current_position = {
-        let lock = TRACKLIST.read().await;
-        lock.current_position()
-    };
+        let mut interval = tokio::time::interval(Duration::from_millis(250));
+        let mut last_position = ClockTime::default();
 
-    skip_to_position(current_position + 1, true).await
-}
+        loop {
+            interval.tick().await;
+            self.sleep_when_due().await;
 
-#[instrument]
-pub async fn previous() -> Result<()> {
-    let current_position = {
-        let lock = TRACKLIST.read().await;
-        lock.current_position()
-    };
+            if self.current_state() == State::Playing {
+                self.refill_when_due().await;
+                self.prefetch_when_due().await;
+                self.recovery.lock().await.settle();
+                self.count_play(interval.period()).await;
 
-    skip_to_position(current_position - 1, false).await
-}
+                if let Some(position) = self.position() {
+                    self.crossfade_when_due(position).await;
 
-fn skip_to_track(tracklist: &mut Tracklist, new_position: u32) -> Option<&tracklist::Track> {
-    let mut new_track: Option<&tracklist::Track> = None;
-    for queue_item in tracklist.queue.iter_mut().enumerate() {
-        let queue_item_position = queue_item.0 as u32;
-        match queue_item_position.cmp(&new_position) {
-            std::cmp::Ordering::Less => {
-                queue_item.1.status = TrackStatus::Played;
-            }
-            std::cmp::Ordering::Equal => {
-                queue_item.1.status = TrackStatus::Playing;
-                new_track = Some(queue_item.1)
-            }
-            std::cmp::Ordering::Greater => {
-                queue_item.1.status = TrackStatus::Unplayed;
+                    if position.seconds() != last_position.seconds() {
+                        last_position = position;
+
+                        self.broadcast
+                            .tx
+                            .send(Notification::Position { clock: position })
+                            .expect("failed to send notification");
+                    }
+                }
             }
         }
     }
 
-    new_track
-}
+    /// Starts a crossfade once the time left of the track is down to the crossfade length.
+    async fn crossfade_when_due(&self, position: ClockTime) {
+        let crossfade = self.fades.read().await.crossfade;
 
-fn skip_to_next_track(tracklist: &mut Tracklist) {
-    let current_position = tracklist.current_position();
-    let new_position = current_position + 1;
-
-    for queue_item in tracklist.queue.iter_mut().enumerate() {
-        let queue_item_position = queue_item.0 as u32;
-        match queue_item_position.cmp(&new_position) {
-            std::cmp::Ordering::Less => {
-                queue_item.1.status = TrackStatus::Played;
-            }
-            std::cmp::Ordering::Equal => {
-                queue_item.1.status = TrackStatus::Playing;
-            }
-            std::cmp::Ordering::Greater => {
-                queue_item.1.status = TrackStatus::Unplayed;
-            }
+        if crossfade <= 0.0 || self.crossfading.load(Ordering::Relaxed) {
+            return;
         }
-    }
-}
 
-#[instrument]
-/// Plays a single track.
-pub async fn play_track(track_id: u32) -> Result<()> {
-    ready().await?;
-
-    let client = get_client().await;
-    let track_url = client.track_url(track_id).await?;
-    PLAYBIN.set_property("uri", track_url);
-    play().await?;
-
-    let mut tracklist = TRACKLIST.write().await;
-
-    let full_track_info: Track = client.track(track_id).await?.into();
-    let track = tracklist::Track {
-        id: full_track_info.id,
-        title: full_track_info.title,
-        status: TrackStatus::Unplayed,
-    };
-
-    tracklist.queue = vec![track];
-    tracklist.list_type = TrackListType::Track;
+        let Some(duration) = self.duration() else {
+            return;
+        };
 
-    broadcast_track_list(&tracklist).await?;
+        // Short tracks fade for at most half their length.
+        let overlap = ((crossfade * 1000.0) as u64).min(duration.mseconds() / 2);
+        let remaining = duration.mseconds().saturating_sub(position.mseconds());
 
-    Ok(())
-}
+        if remaining <= overlap {
+            self.crossfading.store(true, Ordering::Relaxed);
 
-#[instrument]
-/// Plays a full album.
-pub async fn play_album(album_id: &str, index: u32) -> Result<()> {
-    ready().await?;
+            if let Err(error) = self.crossfade(Duration::from_millis(remaining)).await {
+                debug!(?error);
+            }
+        }
+    }
 
-    let client = get_client().await;
-    let mut tracklist = TRACKLIST.write().await;
+    pub async fn quit(&self) -> Result<()> {
+        debug!("stopping player");
 
-    let album = client.album(album_id).await?;
+        self.should_quit.store(true, Ordering::Relaxed);
 
-    let tracks = album.tracks.unwrap_or_default();
+        if let Some(play) = self.current_play.lock().await.take() {
+            self.record_play(play).await;
+        }
 
-    let unstreambale_tracks_to_index = tracks
-        .items
-        .iter()
-        .take(index as usize)
-        .filter(|t| !t.streamable)
-        .count() as u32;
+        if self.is_playing() {
+            debug!("pausing player");
+            self.pause().await?;
+        }
 
-    tracklist.queue = tracks
-        .items
-        .into_iter()
-        .filter(|t| t.streamable)
-        .map(|t| tracklist::Track {
-            id: t.id,
-            title: t.title,
-            status: TrackStatus::Unplayed,
-        })
-        .collect();
+        if self.is_paused() {
+            debug!("readying player");
+            self.ready().await?;
+        }
 
-    if let Some(track) = skip_to_track(&mut tracklist, index - unstreambale_tracks_to_index) {
-        let track_url = client.track_url(track.id).await?;
-        PLAYBIN.set_property("uri", track_url);
-        play().await?;
+        if self.is_ready() {
+            debug!("stopping player");
+            self.stop().await?;
+        }
 
-        tracklist.list_type = TrackListType::Album(tracklist::AlbumTracklist {
-            title: album.title,
-            id: album.id,
-        });
+        self.broadcast
+            .tx
+            .send(Notification::Quit)
+            .expect("error sending broadcast");
 
-        broadcast_track_list(&tracklist).await?;
-    };
+        Ok(())
+    }
 
-    Ok(())
-}
+    #[instrument]
+    /// Loads a saved session, paused at the saved position.
+    async fn resume(&self, session: Session) -> Result<()> {
+        self.set_volume(session.volume);
 
-#[instrument]
-/// Plays top tracks from artist starting from index.
-pub async fn play_top_tracks(artist_id: u32, index: u32) -> Result<()> {
-    ready().await?;
+        let mut tracklist = self.tracklist.write().await;
+        *tracklist = session.tracklist;
 
-    let client = get_client().await;
-    let mut tracklist = TRACKLIST.write().await;
+        if let Some(track) = tracklist.current_track() {
+            let track_url = self.stream_url(track.id).await.map_err(|_| Error::Resume)?;
 
-    let tracks = client.artist(artist_id).await?.top_tracks;
+            // Seeking only works once the track has prerolled, see AsyncDone.
+            *self.resume_position.lock().await = Some(session.position);
 
-    let unstreambale_tracks_to_index = tracks
-        .iter()
-        .take(index as usize)
-        .filter(|t| !t.rights.streamable)
-        .count() as u32;
+            self.playbin().set_property("uri", track_url);
+            self.pause().await?;
+        }
 
-    tracklist.queue = tracks
-        .into_iter()
-        .map(|t| tracklist::Track {
-            id: t.id,
-            title: t.title,
-            status: TrackStatus::Unplayed,
-        })
-        .collect();
+        self.broadcast_track_list(&tracklist).await?;
 
-    if let Some(track) = skip_to_track(&mut tracklist, index - unstreambale_tracks_to_index) {
-        let track_url = client.track_url(track.id).await?;
-        PLAYBIN.set_property("uri", track_url);
-        play().await?;
+        Ok(())
+    }
 
-        tracklist.list_type = TrackListType::Track;
+    #[instrument(skip(session))]
+    /// Handles messages from GStreamer, receives player actions from external controls
+    /// receives the about-to-finish event and takes necessary action.
+    pub async fn player_loop(&self, session: Option<Session>) -> Result<()> {
+        let bus_messages = |index: usize| {
+            self.decks[index]
+                .playbin
+                .bus()
+                .unwrap()
+                .stream()
+                .map(move |msg| (index, msg))
+        };
+        let mut messages = stream::select(bus_messages(0), bus_messages(1));
+        let mut about_to_finish = self.about_to_finish.rx.resubscribe();
 
-        broadcast_track_list(&tracklist).await?;
-    };
+        // The loop can run again after a previous one was stopped with `quit`.
+        self.should_quit.store(false, Ordering::Relaxed);
 
-    Ok(())
-}
+        if let Some(session) = session {
+            if let Err(error) = self.resume(session).await {
+                debug!(?error);
+                self.broadcast.tx.send(Notification::Error {
+                    error: Error::Resume,
+                })?;
+            }
+        }
 
-#[instrument]
-/// Plays all tracks in a playlist.
-pub async fn play_playlist(playlist_id: i64, index: u32) -> Result<()> {
-    ready().await?;
+        let player = self.clone();
+        let clock_loop = tokio::spawn(async move { player.clock_loop().await });
 
-    let client = get_client().await;
-    let mut tracklist = TRACKLIST.write().await;
+        loop {
+            if self.should_quit.load(Ordering::Relaxed) {
+                clock_loop.abort();
+                break;
+            }
 
-    let playlist = client.playlist(playlist_id).await?;
+            select! {
+                 Ok(deck) = about_to_finish.recv()=> {
+                    if deck == self.active_deck.load(Ordering::Relaxed) {
+                        // Without a next uri playback ends with the track, and EOS restarts it.
+                        if let Err(error) = self.prep_next_track().await {
+                            tracing::warn!("failed to queue the next track: {error}");
+                        }
+                    }
+                }
+                Some((deck, msg)) = messages.next() => {
+                        // A deck fading out a track is left alone until it is stopped.
+                        if deck != self.active_deck.load(Ordering::Relaxed) {
+                            continue;
+                        }
+
+                        match self.handle_message(&msg).await {
+                            Ok(_) => {},
+                            Err(error) => debug!(?error),
+                        };
+                }
+            }
+        }
+        Ok(())
+    }
 
-    let tracks = playlist.tracks.unwrap_or_default();
+    async fn handle_message(&self, msg: &Message) -> Result<()> {
+        match msg.view() {
+            MessageView::Eos(_) => {
+                tracing::debug!("END OF STREAM");
+                let mut tracklist = self.tracklist.write().await;
+
+                // Leave the next track loaded, so playing again picks up from there.
+                if self.stops_after_current(&tracklist).await {
+                    self.set_sleep_timer(None).await?;
+
+                    if let Some(position) = tracklist.next_position() {
+                        if let Some(track) = tracklist.skip_to(position) {
+                            let track_url = self.stream_url(track.id).await?;
+                            self.playbin().set_property("uri", track_url);
+                        }
+
+                        self.set_target_state(gstreamer::State::Ready).await;
+                        self.ready().await?;
+                        self.broadcast_track_list(&tracklist).await?;
+                        return Ok(());
+                    }
+                }
 
-    let unstreambale_tracks_to_index = tracks
-        .items
-        .iter()
-        .take(index as usize)
-        .filter(|t| !t.streamable)
-        .count() as u32;
+                // The next track is normally queued gaplessly on about-to-finish, or crossfaded.
+                // If that didn't happen, restart playback from the next track.
+                if let Some(position) = tracklist.next_position() {
+                    if let Some(track_id) = tracklist.skip_to(position).map(|track| track.id) {
+                        self.ready().await?;
+
+                        match self.stream_url(track_id).await {
+                            Ok(track_url) => {
+                                self.playbin().set_property("uri", track_url);
+                                self.play().await?;
+                            }
+                            Err(error) => {
+                                self.broadcast_track_list(&tracklist).await?;
+                                drop(tracklist);
+                                return self.recover(error).await;
+                            }
+                        }
+                    }
+
+                    self.broadcast_track_list(&tracklist).await?;
+                    return Ok(());
+                }
 
-    tracklist.queue = tracks
-        .items
-        .into_iter()
-        .filter(|t| t.streamable)
-        .map(|t| tracklist::Track {
-            id: t.id,
-            title: t.title,
-            status: TrackStatus::Unplayed,
-        })
-        .collect();
+                self.stop_at_end_of_queue(&mut tracklist).await?;
+            }
+            MessageView::StreamStart(_) => {
+                tracing::debug!("STREAM START");
+                self.crossfading.store(false, Ordering::Relaxed);
+
+                if self.is_playing() && self.stale_next_track.swap(false, Ordering::Relaxed) {
+                    tracing::info!("Stopping instead of playing a track removed from the queue");
+                    let mut tracklist = self.tracklist.write().await;
+                    return self.stop_at_end_of_queue(&mut tracklist).await;
+                }
 
-    if let Some(track) = skip_to_track(&mut tracklist, index - unstreambale_tracks_to_index) {
-        let track_url = client.track_url(track.id).await?;
-        PLAYBIN.set_property("uri", track_url);
-        play().await?;
+                // Looked up when the track was queued, so the switch doesn't wait for the network.
+                let mut replay_gain_applied = false;
 
-        tracklist.list_type = TrackListType::Playlist(tracklist::PlaylistTracklist {
-            title: playlist.name,
-            id: playlist.id,
-        });
+                if self.is_playing() {
+                    tracing::debug!("Starting next song");
 
-        broadcast_track_list(&tracklist).await?;
-    };
+                    if let Some(volume) = self.next_replay_gain.lock().await.take() {
+                        self.deck().set_replay_gain(volume);
+                        replay_gain_applied = true;
+                    }
 
-    Ok(())
-}
+                    let mut tracklist = self.tracklist.write().await;
+                    *self.queued_next_track.lock().await = None;
 
-#[instrument]
-/// In response to the about-to-finish signal,
-/// prepare the next track by downloading the stream url.
-async fn prep_next_track() -> Result<()> {
-    tracing::info!("Prepping for next track");
+                    // The sleep timer was set after this track had already been queued.
+                    let stop = self.stops_after_current(&tracklist).await;
 
-    let client = get_client().await;
-    let mut tracklist = TRACKLIST.write().await;
+                    skip_to_next_track(&mut tracklist);
+                    self.broadcast_track_list(&tracklist).await?;
 
-    let total_tracks = tracklist.total();
-    let current_position = tracklist.current_position();
+                    if stop {
+                        self.set_sleep_timer(None).await?;
+                        self.pause().await?;
+                    }
 
-    tracing::info!(
-        "Total tracks: {}, current position: {}",
-        total_tracks,
-        current_position
-    );
+                    let next_stream_format = self.next_stream_format.lock().await.clone();
+                    self.set_stream_format(next_stream_format).await?;
+                }
 
-    if total_tracks == current_position {
-        tracing::info!("No more tracks left");
-    }
+                let current_track = self.tracklist.read().await.current_track().cloned();
+                if let Some(track) = current_track {
+                    let track_id = track.id;
+                    self.start_play(track).await;
+
+                    if !replay_gain_applied {
+                        let player = self.clone();
+                        tokio::spawn(async move {
+                            if let Err(error) = player.apply_replay_gain(track_id).await {
+                                debug!("failed to apply replaygain: {error}");
+                            }
+                        });
+                    }
+                }
+            }
+            MessageView::AsyncDone(msg) => {
+                tracing::debug!("ASYNC DONE");
 
-    let next_track = tracklist
-        .queue
-        .iter_mut()
-        .enumerate()
-        .find(|t| t.0 as u32 == current_position + 1)
-        .map(|t| t.1);
+                if let Some(resume_position) = self.resume_position.lock().await.take() {
+                    self.seek(resume_position, None).await?;
+                }
 
-    if let Some(next_track) = next_track {
-        if let Ok(url) = client.track_url(next_track.id).await {
-            PLAYBIN.set_property("uri", url);
-        };
-    };
+                let position = if let Some(p) = msg.running_time() {
+                    p
+                } else {
+                    self.position().unwrap_or_default()
+                };
 
-    Ok(())
-}
+                self.broadcast
+                    .tx
+                    .send(Notification::Position { clock: position })?;
+            }
+            MessageView::Buffering(buffering) => {
+                if self.is_live.load(Ordering::Relaxed) {
+                    debug!("stream is live, ignore buffering");
+                    return Ok(());
+                }
+                let percent = buffering.percent();
+                self.broadcast
+                    .tx
+                    .send(Notification::Buffering { percent })?;
+
+                if percent < 100 && !self.is_paused() {
+                    tracing::info!("Buffering");
+                    self.pause().await?;
+                } else if percent >= 100 && self.is_paused() {
+                    tracing::info!("Done buffering");
+                    self.play().await?;
+                }
+            }
+            MessageView::StateChanged(state_changed) => {
+                let current_player_state =
+                    state_changed.current().to_value().get::<State>().unwrap();
 
-#[instrument]
-/// Get a notification channel receiver
-pub fn notify_receiver() -> Receiver<Notification> {
-    BROADCAST_CHANNELS.rx.resubscribe()
-}
+                let target_status = self.target_status.read().await;
 
-#[instrument]
-/// Returns the current track list loaded in the player.
-pub async fn current_tracklist() -> Tracklist {
-    TRACKLIST.read().await.clone()
-}
+                if current_player_state == State::Playing && self.deck().take_fade_in() {
+                    let player = self.clone();
+                    let deck = self.active_deck.load(Ordering::Relaxed);
+                    tokio::spawn(async move { player.decks[deck].fade(1.0, SOFT_FADE).await });
+                }
 
-#[instrument]
-/// Returns the current track loaded in the player.
-pub async fn current_track() -> Result<Option<Track>> {
-    let track_id = TRACKLIST.read().await.current_track().map(|t| t.id);
+                if *target_status == current_player_state {
+                    debug!("player state changed {:?}", current_player_state);
 
-    match track_id {
-        Some(id) => {
-            let client = get_client().await;
-            Ok(Some(client.track(id).await?.into()))
-        }
-        None => Ok(None),
-    }
-}
+                    self.broadcast.tx.send(Notification::Status {
+                        status: current_player_state,
+                    })?;
+                }
+            }
+            MessageView::PropertyNotify(notify) => {
+                let (object, property, value) = notify.get();
 
-#[instrument]
-pub async fn search(query: &str) -> Result<SearchResults> {
-    let client = get_client().await;
-    let user_id = client.get_user_id();
+                if property == "caps" {
+                    let pad = object.downcast_ref::<gstreamer::Pad>();
+                    let caps = value.and_then(|value| value.get::<gstreamer::Caps>().ok());
 
-    let results = client.search_all(query, 20).await?;
-    Ok(models::parse_search_results(results, user_id))
-}
+                    if let (Some(pad), Some(caps)) = (pad, caps) {
+                        self.update_stream_format(pad, &caps).await?;
+                    }
+                }
+            }
+            MessageView::ClockLost(_) => {
+                tracing::warn!("clock lost, restarting playback");
+                self.pause().await?;
+                self.play().await?;
+            }
+            MessageView::Error(err) if self.bit_perfect() && is_not_negotiated(err) => {
+                // Retrying would fail the same way, the output has to change instead.
+                self.broadcast.tx.send(Notification::Error {
+                    error: Error::BitPerfectFormat,
+                })?;
 
-#[instrument]
-/// Get artist page
-pub async fn artist_page(artist_id: u32) -> Result<ArtistPage> {
-    let client = get_client().await;
-    let artist = client.artist(artist_id).await?;
-    Ok(artist.into())
-}
+                self.set_target_state(gstreamer::State::Ready).await;
+                self.ready().await?;
 
-#[instrument]
-/// Get similar artists
-pub async fn similar_artists(artist_id: u32) -> Result<Vec<Artist>> {
-    let client = get_client().await;
-    let similar_artists = client.similar_artists(artist_id, None).await?;
+                tracing::error!("bit-perfect output rejected the stream: {:?}", err.debug());
+            }
+            MessageView::Error(err) => {
+                tracing::error!(
+                    "Error from {:?}: {} ({:?})",
+                    err.src().map(|s| s.path_string()),
+                    err.error(),
+                    err.debug()
+                );
+
+                self.recover(err.into()).await?;
+            }
+            _ => (),
+        }
 
-    Ok(similar_artists
-        .items
-        .into_iter()
-        .map(|s_a| s_a.into())
-        .collect())
+        Ok(())
+    }
 }
 
-#[instrument]
-/// Get album
-pub async fn album(id: &str) -> Result<Album> {
-    let client = get_client().await;
-    let album = client.album(id).await?;
-    Ok(album.into())
+/// Whether the pipeline failed because no format could be agreed on.
+fn is_not_negotiated(err: &gstreamer::message::Error) -> bool {
+    err.error().matches(gstreamer::StreamError::NotNegotiated)
+        || err
+            .debug()
+            .is_some_and(|debug| debug.contains("not-negotiated"))
 }
 
-#[instrument]
-/// Get track
-pub async fn track(id: u32) -> Result<Track> {
-    let client = get_client().await;
-    Ok(client.track(id).await?.into())
+fn skip_to_next_track(tracklist: &mut Tracklist) {
+    if let Some(new_position) = tracklist.next_position() {
+        tracklist.skip_to(new_position);
+    }
 }
 
-#[instrument]
-/// Get suggested albums
-pub async fn suggested_albums(album_id: &str) -> Result<Vec<Album>> {
-    let client = get_client().await;
-    let suggested_albums = client.suggested_albums(album_id).await?;
+/// A track to add to the queue. Tracks that can't be streamed stay in the queue,
+/// marked so they are passed over.
+fn queued_track(track: Track) -> tracklist::Track {
+    let status = if track.available {
+        TrackStatus::Unplayed
+    } else {
+        TrackStatus::Unplayable
+    };
 
-    Ok(suggested_albums
-        .albums
-        .items
-        .into_iter()
-        .map(|x| x.into())
-        .collect())
+    tracklist::Track {
+        status,
+        ..track.into()
+    }
 }
 
 #[instrument]
-#[cached(size = 1, time = 600)]
+#[cached(
+    size = 1,
+    time = 600,
+    key = "AlbumFeaturedType",
+    convert = "{ featured_type.clone() }"
+)]
 /// Get featured albums
-pub async fn featured_albums(featured_type: AlbumFeaturedType) -> Result<Vec<Album>> {
-    let client = get_client().await;
+async fn featured_albums(client: &Client, featured_type: AlbumFeaturedType) -> Result<Vec<Album>> {
     let featured = client.featured_albums(featured_type).await?;
 
     Ok(featured
@@ -768,10 +2936,17 @@ pub async fn featured_albums(featured_type: AlbumFeaturedType) -> Result<Vec<Alb
 }
 
 #[instrument]
-#[cached(size = 1, time = 600)]
-/// Get featured albums
-pub async fn featured_playlists(featured_type: PlaylistFeaturedType) -> Result<Vec<Playlist>> {
-    let client = get_client().await;
+#[cached(
+    size = 1,
+    time = 600,
+    key = "(i64, PlaylistFeaturedType)",
+    convert = "{ (client.get_user_id(), featured_type.clone()) }"
+)]
+/// Get featured playlists
+async fn featured_playlists(
+    client: &Client,
+    featured_type: PlaylistFeaturedType,
+) -> Result<Vec<Playlist>> {
     let user_id = client.get_user_id();
     let featured = client.featured_playlists(featured_type).await?;
 
@@ -783,84 +2958,120 @@ pub async fn featured_playlists(featured_type: PlaylistFeaturedType) -> Result<V
         .collect())
 }
 
-#[instrument]
-/// Get playlist
-pub async fn playlist(id: i64) -> Result<Playlist> {
-    let client = get_client().await;
-    let user_id = client.get_user_id();
-    let playlist = client.playlist(id).await?;
-
-    Ok(models::parse_playlist(playlist, user_id))
-}
-
-#[instrument]
-#[cached(size = 10, time = 600)]
-/// Fetch the albums for a specific artist.
-pub async fn artist_albums(artist_id: u32) -> Result<Vec<Album>> {
-    let client = get_client().await;
-    let albums = client.artist_releases(artist_id, None).await?;
+/// Qobuz identifies tracks within a playlist by a separate id.
+async fn playlist_track_id(client: &Client, playlist_id: i64, position: usize) -> Result<i64> {
+    let playlist = client.playlist(playlist_id).await?;
 
-    Ok(albums.into_iter().map(|release| release.into()).collect())
+    playlist
+        .tracks
+        .and_then(|tracks| tracks.items.into_iter().nth(position))
+        .and_then(|track| track.playlist_track_id)
+        .ok_or(Error::TrackNotInPlaylist)
 }
 
 #[instrument]
-/// Add album to favorites
-pub async fn add_favorite_album(id: &str) -> Result<()> {
-    let client = get_client().await;
-    client.add_favorite_album(id).await?;
-
-    FAVORITES.lock().await.cache_clear();
-    Ok(())
+#[cached(
+    size = 100,
+    time = 3600,
+    key = "u32",
+    convert = "{ track_id }",
+    result = true
+)]
+/// Replaygain of a track, along with the id of its album.
+async fn track_gain(client: &Client, track_id: u32) -> Result<(Option<Gain>, Option<String>)> {
+    let track = client.track(track_id).await?;
+
+    Ok((Gain::from_track(&track), track.album.map(|album| album.id)))
 }
 
 #[instrument]
-/// Remove album from favorites
-pub async fn remove_favorite_album(id: &str) -> Result<()> {
-    let client = get_client().await;
-    client.remove_favorite_album(id).await?;
+#[cached(
+    size = 20,
+    time = 3600,
+    key = "String",
+    convert = "{ album_id.to_string() }",
+    result = true
+)]
+/// Replaygain of a whole album.
+async fn album_gain(client: &Client, album_id: &str) -> Result<Option<Gain>> {
+    let album = client.album(album_id).await?;
 
-    FAVORITES.lock().await.cache_clear();
-    Ok(())
+    Ok(album
+        .tracks
+        .and_then(|tracks| Gain::from_album_tracks(&tracks.items)))
 }
 
 #[instrument]
-/// Add artist to favorites
-pub async fn add_favorite_artist(id: &str) -> Result<()> {
-    let client = get_client().await;
-    client.add_favorite_artist(id).await?;
-
-    FAVORITES.lock().await.cache_clear();
-    Ok(())
+#[cached(
+    size = 100,
+    time = 3600,
+    key = "u32",
+    convert = "{ track_id }",
+    result = true
+)]
+/// The id of the album a track is on.
+async fn track_album(client: &Client, track_id: u32) -> Result<Option<String>> {
+    let track = client.track(track_id).await?;
+
+    Ok(track.album.map(|album| album.id))
 }
 
 #[instrument]
-/// Remove artist from favorites
-pub async fn remove_favorite_artist(id: &str) -> Result<()> {
-    let client = get_client().await;
-    client.remove_favorite_artist(id).await?;
+#[cached(size = 10, time = 600, key = "u32", convert = "{ artist_id }")]
+/// Fetch the albums for a specific artist.
+async fn artist_albums(client: &Client, artist_id: u32) -> Result<Vec<Album>> {
+    let albums = client.artist_releases(artist_id, None).await?;
 
-    FAVORITES.lock().await.cache_clear();
-    Ok(())
+    Ok(albums.into_iter().map(|release| release.into()).collect())
 }
 
-#[instrument]
-/// Add playlist to favorites
-pub async fn add_favorite_playlist(id: &str) -> Result<()> {
-    let client = get_client().await;
-    client.add_favorite_playlist(id).await?;
-
-    FAVORITES.lock().await.cache_clear();
-    Ok(())
-}
+/// The streamable top tracks of an artist and the tracks of one of their albums at random.
+async fn radio_candidates(
+    client: &Client,
+    artist_id: u32,
+    top_tracks: Weight,
+    album_tracks: Weight,
+) -> Result<Vec<Candidate>> {
+    let artist: ArtistPage = client.artist(artist_id).await?.into();
+
+    let mut candidates: Vec<Candidate> = artist
+        .top_tracks
+        .into_iter()
+        .filter(|t| t.available)
+        .map(|t| Candidate {
+            track: t.into(),
+            artist_id,
+            weight: top_tracks,
+        })
+        .collect();
 
-#[instrument]
-/// Remove playlist from favorites
-pub async fn remove_favorite_playlist(id: &str) -> Result<()> {
-    let client = get_client().await;
-    client.remove_favorite_playlist(id).await?;
+    let albums = artist_albums(client, artist_id).await?;
+    let album_ids: Vec<&str> = albums
+        .iter()
+        .filter(|album| album.available)
+        .map(|album| album.id.as_str())
+        .collect();
+    let album_id = album_ids
+        .choose(&mut rand::thread_rng())
+        .map(|id| id.to_string());
+
+    if let Some(album_id) = album_id {
+        let album: Album = client.album(&album_id).await?.into();
+
+        candidates.extend(
+            album
+                .tracks
+                .into_iter()
+                .filter(|t| t.available)
+                .map(|t| Candidate {
+                    track: t.into(),
+                    artist_id,
+                    weight: album_tracks,
+                }),
+        );
+    }
 
-    FAVORITES.lock().await.cache_clear();
-    Ok(())
+    Ok(candidates)
 }
 
 #[instrument]
@@ -878,210 +3089,66 @@ async fn user_playlists(client: &Client) -> Result<Vec<Playlist>> {
 }
 
 #[instrument]
-#[cached(size = 1, time = 600)]
+#[cached(
+    size = 1,
+    time = 600,
+    key = "i64",
+    convert = "{ client.get_user_id() }"
+)]
 /// Get favorites
-pub async fn favorites() -> Result<Favorites> {
-    let client = get_client().await;
+async fn favorites(client: &Client) -> Result<Favorites> {
     let (favorites, favorite_playlists) =
         tokio::join!(client.favorites(1000), user_playlists(client));
 
     let qobuz_player_client::qobuz_models::favorites::Favorites {
         albums,
-        tracks: _,
+        tracks,
         artists,
     } = favorites?;
     let albums = albums.items;
+    let tracks = tracks.items;
     let artists = artists.items;
 
     Ok(Favorites {
         albums: albums.into_iter().map(|x| x.into()).collect(),
+        tracks: tracks.into_iter().map(|x| x.into()).collect(),
         artists: artists.into_iter().map(|x| x.into()).collect(),
         playlists: favorite_playlists?,
     })
 }
 
-#[instrument]
-/// Inserts the most recent position into the state at a set interval.
-async fn clock_loop() {
-    debug!("starting clock loop");
-
-    let mut interval = tokio::time::interval(Duration::from_millis(250));
-    let mut last_position = ClockTime::default();
-
-    loop {
-        interval.tick().await;
-        if current_state() == State::Playing {
-            if let Some(position) = position() {
-                if position.seconds() != last_position.seconds() {
-                    last_position = position;
-
-                    BROADCAST_CHANNELS
-                        .tx
-                        .send(Notification::Position { clock: position })
-                        .expect("failed to send notification");
-                }
-            }
-        }
-    }
-}
-
-pub async fn quit() -> Result<()> {
-    debug!("stopping player");
-
-    SHOULD_QUIT.store(true, Ordering::Relaxed);
-
-    if is_playing() {
-        debug!("pausing player");
-        pause().await?;
-    }
-
-    if is_paused() {
-        debug!("readying player");
-        ready().await?;
-    }
-
-    if is_ready() {
-        debug!("stopping player");
-        stop().await?;
-    }
-
-    BROADCAST_CHANNELS
-        .tx
-        .send(Notification::Quit)
-        .expect("error sending broadcast");
-
-    Ok(())
-}
-
-#[instrument]
-/// Handles messages from GStreamer, receives player actions from external controls
-/// receives the about-to-finish event and takes necessary action.
-pub async fn player_loop(username: String, password: String) -> Result<()> {
-    USERNAME.set(username).unwrap();
-    PASSWORD.set(password).unwrap();
-
-    let mut messages = PLAYBIN.bus().unwrap().stream();
-    let mut about_to_finish = TRACK_ABOUT_TO_FINISH.rx.resubscribe();
-
-    let clock_loop = tokio::spawn(async { clock_loop().await });
-
-    loop {
-        if SHOULD_QUIT.load(Ordering::Relaxed) {
-            clock_loop.abort();
-            break;
-        }
-
-        select! {
-             Ok(almost_done) = about_to_finish.recv()=> {
-                if almost_done {
-                     prep_next_track().await.unwrap();
-                }
-            }
-            Some(msg) = messages.next() => {
-                    match handle_message(&msg).await {
-                        Ok(_) => {},
-                        Err(error) => debug!(?error),
-                    };
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn zones_keep_their_own_notifications() {
+        let account = Account::new(String::new(), String::new());
+        let kitchen = Player::new("kitchen", account.clone()).unwrap();
+        let office = Player::new("office", account).unwrap();
+        let mut kitchen_notifications = kitchen.notify_receiver();
+        let mut office_notifications = office.notify_receiver();
+
+        kitchen.set_volume(0.5);
+
+        assert!(matches!(
+            kitchen_notifications.try_recv(),
+            Ok(Notification::Volume { volume }) if volume == 0.5
+        ));
+        assert!(office_notifications.try_recv().is_err());
+        assert_eq!(kitchen.zone(), "kitchen");
+        assert_eq!(office.zone(), "office");
     }
-    Ok(())
-}
-
-async fn handle_message(msg: &Message) -> Result<()> {
-    match msg.view() {
-        MessageView::Eos(_) => {
-            tracing::debug!("END OF STREAM");
-            let mut tracklist = TRACKLIST.write().await;
-
-            if let Some(last_track) = tracklist.queue.last_mut() {
-                last_track.status = TrackStatus::Played;
-            };
-
-            if let Some(first_track) = tracklist.queue.first_mut() {
-                first_track.status = TrackStatus::Playing;
-                let client = get_client().await;
-                let track_url = client.track_url(first_track.id).await?;
-                PLAYBIN.set_property("uri", track_url);
-            };
-
-            ready().await?;
-            broadcast_track_list(&tracklist).await?;
-        }
-        MessageView::StreamStart(_) => {
-            tracing::debug!("STREAM START");
-            if is_playing() {
-                tracing::debug!("Starting next song");
-
-                let mut tracklist = TRACKLIST.write().await;
-                skip_to_next_track(&mut tracklist);
-                broadcast_track_list(&tracklist).await?;
-            }
-        }
-        MessageView::AsyncDone(msg) => {
-            tracing::debug!("ASYNC DONE");
-
-            let position = if let Some(p) = msg.running_time() {
-                p
-            } else {
-                position().unwrap_or_default()
-            };
-
-            BROADCAST_CHANNELS
-                .tx
-                .send(Notification::Position { clock: position })?;
-        }
-        MessageView::Buffering(buffering) => {
-            if IS_LIVE.load(Ordering::Relaxed) {
-                debug!("stream is live, ignore buffering");
-                return Ok(());
-            }
-            let percent = buffering.percent();
-
-            if percent < 100 && !is_paused() {
-                tracing::info!("Buffering");
-                pause().await?;
-            } else if percent >= 100 && is_paused() {
-                tracing::info!("Done buffering");
-                play().await?;
-            }
-        }
-        MessageView::StateChanged(state_changed) => {
-            let current_player_state = state_changed.current().to_value().get::<State>().unwrap();
-
-            let target_status = TARGET_STATUS.read().await;
-
-            if *target_status == current_player_state {
-                debug!("player state changed {:?}", current_player_state);
 
-                BROADCAST_CHANNELS.tx.send(Notification::Status {
-                    status: current_player_state,
-                })?;
-            }
-        }
-        MessageView::ClockLost(_) => {
-            tracing::warn!("clock lost, restarting playback");
-            pause().await?;
-            play().await?;
-        }
-        MessageView::Error(err) => {
-            BROADCAST_CHANNELS
-                .tx
-                .send(Notification::Error { error: err.into() })?;
+    #[tokio::test]
+    async fn player_loop_runs_again_after_quit() {
+        let player = Player::new("test", Account::new(String::new(), String::new())).unwrap();
+        player.quit().await.unwrap();
 
-            ready().await?;
-            pause().await?;
-            play().await?;
+        // A loop that keeps running until it is told to quit again.
+        let restarted =
+            tokio::time::timeout(Duration::from_millis(200), player.player_loop(None)).await;
 
-            tracing::error!(
-                "Error from {:?}: {} ({:?})",
-                err.src().map(|s| s.path_string()),
-                err.error(),
-                err.debug()
-            );
-        }
-        _ => (),
+        assert!(restarted.is_err());
     }
-
-    Ok(())
 }
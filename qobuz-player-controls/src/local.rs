@@ -0,0 +1,401 @@
+use lofty::{
+    file::{AudioFile, TaggedFileExt},
+    tag::{Accessor, ItemKey},
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::models::{Album, Artist, ArtistPage, SearchResults, Track, TrackAlbum};
+
+/// Ids of local tracks and artists have this bit set, Qobuz ids stay well below it.
+const LOCAL_ID: u32 = 1 << 31;
+
+/// Ids of local albums start with this, Qobuz album ids are alphanumeric.
+const LOCAL_ALBUM: &str = "local-";
+
+/// Files with these extensions are read when scanning.
+const EXTENSIONS: [&str; 9] = [
+    "flac", "mp3", "m4a", "aac", "ogg", "oga", "opus", "wav", "aiff",
+];
+
+/// How many of each kind of result a search returns, like a Qobuz search.
+const SEARCH_LIMIT: usize = 20;
+
+/// Whether a track or artist id belongs to the local library.
+pub fn is_local(id: u32) -> bool {
+    id & LOCAL_ID != 0
+}
+
+/// Whether an album id belongs to the local library.
+pub fn is_local_album(id: &str) -> bool {
+    id.starts_with(LOCAL_ALBUM)
+}
+
+#[derive(Debug)]
+struct LocalTrack {
+    path: PathBuf,
+    track: Track,
+}
+
+/// Audio files found in a few folders, with the metadata read from their tags.
+/// Local tracks, albums and artists get ids that can't be mistaken for Qobuz ones,
+/// see [`is_local`] and [`is_local_album`].
+#[derive(Debug, Default)]
+pub struct LocalLibrary {
+    tracks: HashMap<u32, LocalTrack>,
+}
+
+impl LocalLibrary {
+    /// Reads the tags of the audio files in `folders` and their subfolders.
+    /// Files that can't be read are skipped. This blocks while reading.
+    pub fn scan(folders: &[PathBuf]) -> Self {
+        let mut paths = vec![];
+        for folder in folders {
+            find_audio_files(folder, &mut paths);
+        }
+        // Ids of colliding paths and names depend on the order they are found in.
+        paths.sort();
+
+        let mut ids = ScanIds::default();
+        let tracks = paths
+            .into_iter()
+            .filter_map(|path| match read_track(&path, &mut ids) {
+                Ok(track) => Some((track.id, LocalTrack { path, track })),
+                Err(error) => {
+                    tracing::debug!("skipping {}: {error}", path.display());
+                    None
+                }
+            })
+            .collect();
+
+        Self { tracks }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    pub(crate) fn path(&self, track_id: u32) -> Option<&Path> {
+        self.tracks.get(&track_id).map(|track| track.path.as_path())
+    }
+
+    pub(crate) fn track(&self, track_id: u32) -> Option<Track> {
+        self.tracks.get(&track_id).map(|track| track.track.clone())
+    }
+
+    /// Every album, sorted by artist and title.
+    pub(crate) fn albums(&self) -> Vec<Album> {
+        let mut tracks_by_album: HashMap<&str, Vec<&Track>> = HashMap::new();
+        for track in self.tracks.values().map(|track| &track.track) {
+            if let Some(album) = &track.album {
+                tracks_by_album.entry(&album.id).or_default().push(track);
+            }
+        }
+
+        let mut albums: Vec<Album> = tracks_by_album.into_values().map(album).collect();
+        albums.sort_by(|a, b| {
+            (a.artist.name.to_lowercase(), a.title.to_lowercase())
+                .cmp(&(b.artist.name.to_lowercase(), b.title.to_lowercase()))
+        });
+
+        albums
+    }
+
+    pub(crate) fn album(&self, album_id: &str) -> Option<Album> {
+        let tracks: Vec<&Track> = self
+            .tracks
+            .values()
+            .map(|track| &track.track)
+            .filter(|track| {
+                track
+                    .album
+                    .as_ref()
+                    .is_some_and(|album| album.id == album_id)
+            })
+            .collect();
+
+        (!tracks.is_empty()).then(|| album(tracks))
+    }
+
+    /// Albums the artist is the album artist of.
+    pub(crate) fn artist_albums(&self, artist_id: u32) -> Vec<Album> {
+        self.albums()
+            .into_iter()
+            .filter(|album| album.artist.id == artist_id)
+            .collect()
+    }
+
+    /// The artist with all of their tracks as top tracks.
+    pub(crate) fn artist(&self, artist_id: u32) -> Option<ArtistPage> {
+        let mut tracks: Vec<Track> = self
+            .tracks
+            .values()
+            .map(|track| &track.track)
+            .filter(|track| track.artist.as_ref().is_some_and(|a| a.id == artist_id))
+            .cloned()
+            .collect();
+
+        let artist = tracks.first()?.artist.clone()?;
+        tracks.sort_by_key(track_order);
+
+        Some(ArtistPage {
+            id: artist.id,
+            name: artist.name,
+            image: None,
+            top_tracks: tracks,
+        })
+    }
+
+    /// Albums, artists and tracks with the query in their title or name, ignoring case.
+    pub(crate) fn search(&self, query: &str) -> SearchResults {
+        let needle = query.to_lowercase();
+        let matches = |text: &str| text.to_lowercase().contains(&needle);
+
+        let albums = self
+            .albums()
+            .into_iter()
+            .filter(|album| matches(&album.title) || matches(&album.artist.name))
+            .take(SEARCH_LIMIT)
+            .collect();
+
+        let mut artists: Vec<Artist> = self
+            .tracks
+            .values()
+            .filter_map(|track| track.track.artist.clone())
+            .filter(|artist| matches(&artist.name))
+            .collect();
+        artists.sort_by(|a, b| a.name.cmp(&b.name));
+        artists.dedup_by_key(|artist| artist.id);
+        artists.truncate(SEARCH_LIMIT);
+
+        let mut tracks: Vec<Track> = self
+            .tracks
+            .values()
+            .map(|track| &track.track)
+            .filter(|track| matches(&track.title))
+            .cloned()
+            .collect();
+        tracks.sort_by_key(track_order);
+        tracks.truncate(SEARCH_LIMIT);
+
+        SearchResults {
+            query: query.to_string(),
+            albums,
+            artists,
+            playlists: vec![],
+            tracks,
+        }
+    }
+}
+
+fn find_audio_files(folder: &Path, paths: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(error) => {
+            tracing::warn!("failed to read {}: {error}", folder.display());
+            return;
+        }
+    };
+
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.is_dir() {
+            find_audio_files(&path, paths);
+        } else if path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+        {
+            paths.push(path);
+        }
+    }
+}
+
+/// Ids handed out during a scan, one kind each.
+#[derive(Default)]
+struct ScanIds {
+    tracks: LocalIds,
+    albums: LocalIds,
+    artists: LocalIds,
+}
+
+/// Hands out the [`local_id`] of a key, or the next free one after it when a different key
+/// already has that id.
+#[derive(Default)]
+struct LocalIds {
+    taken: HashMap<u32, Vec<u8>>,
+}
+
+impl LocalIds {
+    fn id(&mut self, key: &[u8]) -> u32 {
+        let mut id = local_id(key);
+        let mut attempt: u32 = 0;
+
+        loop {
+            match self.taken.get(&id) {
+                Some(taken) if taken != key => {
+                    attempt += 1;
+                    id = local_id(&[key, &attempt.to_le_bytes()].concat());
+                }
+                Some(_) => return id,
+                None => {
+                    self.taken.insert(id, key.to_vec());
+                    return id;
+                }
+            }
+        }
+    }
+}
+
+fn read_track(path: &Path, ids: &mut ScanIds) -> lofty::error::Result<Track> {
+    let file = lofty::read_from_path(path)?;
+    let properties = file.properties();
+    let tag = file.primary_tag().or_else(|| file.first_tag());
+
+    let text = |value: Option<std::borrow::Cow<'_, str>>| {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let title = text(tag.and_then(|tag| tag.title()))
+        .or_else(|| text(path.file_stem().map(|stem| stem.to_string_lossy())))
+        .unwrap_or_default();
+    let artist_name =
+        text(tag.and_then(|tag| tag.artist())).unwrap_or_else(|| "Unknown artist".to_string());
+    let album_artist_name = tag
+        .and_then(|tag| tag.get_string(&ItemKey::AlbumArtist))
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| artist_name.clone());
+    // Untagged files are grouped by folder.
+    let album_title = text(tag.and_then(|tag| tag.album()))
+        .or_else(|| {
+            text(
+                path.parent()?
+                    .file_name()
+                    .map(|name| name.to_string_lossy()),
+            )
+        })
+        .unwrap_or_default();
+
+    let hires_available = properties.sample_rate().is_some_and(|rate| rate > 48_000)
+        || properties.bit_depth().is_some_and(|depth| depth > 16);
+
+    Ok(Track {
+        id: ids.tracks.id(path.to_string_lossy().as_bytes()),
+        number: tag.and_then(|tag| tag.track()).unwrap_or_default(),
+        disc_number: tag.and_then(|tag| tag.disk()).unwrap_or(1),
+        title,
+        album: Some(TrackAlbum {
+            id: format!(
+                "{LOCAL_ALBUM}{:x}",
+                ids.albums
+                    .id(format!("{album_artist_name}\0{album_title}").as_bytes())
+            ),
+            title: album_title,
+            artist: local_artist(album_artist_name, &mut ids.artists),
+            image: String::new(),
+        }),
+        artist: Some(local_artist(artist_name, &mut ids.artists)),
+        duration_seconds: properties.duration().as_secs() as u32,
+        explicit: false,
+        hires_available,
+        available: true,
+        cover_art: None,
+        cover_art_small: None,
+    })
+}
+
+fn local_artist(name: String, ids: &mut LocalIds) -> Artist {
+    Artist {
+        id: ids.id(name.to_lowercase().as_bytes()),
+        name,
+        image: None,
+    }
+}
+
+/// A local id from a stable hash, so ids stay the same between scans and saved sessions
+/// keep working.
+fn local_id(bytes: &[u8]) -> u32 {
+    // FNV-1a
+    let hash = bytes.iter().fold(0x811c9dc5_u32, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x01000193)
+    });
+
+    hash | LOCAL_ID
+}
+
+fn track_order(track: &Track) -> (String, u32, u32, String) {
+    (
+        track
+            .album
+            .as_ref()
+            .map(|album| album.title.to_lowercase())
+            .unwrap_or_default(),
+        track.disc_number,
+        track.number,
+        track.title.to_lowercase(),
+    )
+}
+
+/// An album from its tracks, which all have the same album.
+fn album(mut tracks: Vec<&Track>) -> Album {
+    tracks.sort_by_key(|track| track_order(track));
+    let track_album = tracks[0]
+        .album
+        .clone()
+        .expect("local tracks always have an album");
+
+    Album {
+        id: track_album.id,
+        title: track_album.title,
+        artist: track_album.artist,
+        release_year: 0,
+        hires_available: tracks.iter().any(|track| track.hires_available),
+        explicit: false,
+        total_tracks: tracks.len() as u32,
+        duration_seconds: tracks.iter().map(|track| track.duration_seconds).sum(),
+        tracks: tracks.into_iter().cloned().collect(),
+        available: true,
+        cover_art: String::new(),
+        cover_art_small: String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_ids_are_marked_local() {
+        let id = local_id(b"/music/track.flac");
+
+        assert!(is_local(id));
+        assert_eq!(id, local_id(b"/music/track.flac"));
+        assert!(!is_local(12345));
+    }
+
+    #[test]
+    fn colliding_keys_get_different_ids() {
+        // These two hash to the same id.
+        let first: &[u8] = b"/music/312399.flac";
+        let second: &[u8] = b"/music/1065476.flac";
+        assert_eq!(local_id(first), local_id(second));
+
+        let mut ids = LocalIds::default();
+        let first_id = ids.id(first);
+        let second_id = ids.id(second);
+
+        assert_eq!(first_id, local_id(first));
+        assert_ne!(first_id, second_id);
+        assert!(is_local(second_id));
+        assert_eq!(ids.id(first), first_id);
+        assert_eq!(ids.id(second), second_id);
+    }
+}
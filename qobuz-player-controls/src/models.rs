@@ -8,6 +8,7 @@ use qobuz_player_client::qobuz_models::{
     search_results::SearchAllResults,
     track::Track as QobuzTrack,
 };
+use serde::{Deserialize, Serialize};
 use std::{fmt::Debug, str::FromStr};
 
 pub fn parse_search_results(search_results: SearchAllResults, user_id: i64) -> SearchResults {
@@ -45,6 +46,7 @@ impl From<QobuzReleaseTrack> for Track {
         Self {
             id: value.id,
             number: value.physical_support.track_number,
+            disc_number: value.physical_support.media_number,
             title: value.title,
             album: None,
             artist: Some(Artist {
@@ -148,14 +150,38 @@ impl From<QobuzAlbum> for Album {
             .expect("failed to parse date")
             .format("%Y");
 
+        let artist: Artist = value.artist.into();
+        let track_album = TrackAlbum {
+            id: value.id.clone(),
+            title: value.title.clone(),
+            artist: artist.clone(),
+            image: value.image.small.clone(),
+        };
+
+        // Tracks listed on their album leave the album out.
         let tracks = value.tracks.map_or(Default::default(), |tracks| {
-            tracks.items.into_iter().map(|t| t.into()).collect()
+            tracks
+                .items
+                .into_iter()
+                .map(|t| {
+                    let mut track: Track = t.into();
+
+                    if track.album.is_none() {
+                        track.album = Some(track_album.clone());
+                        track.cover_art = Some(value.image.large.clone());
+                        track.cover_art_small = Some(value.image.small.clone());
+                    }
+                    track.artist.get_or_insert_with(|| artist.clone());
+
+                    track
+                })
+                .collect()
         });
 
         Self {
             id: value.id,
             title: value.title,
-            artist: value.artist.into(),
+            artist,
             total_tracks: value.tracks_count as u32,
             release_year: year
                 .to_string()
@@ -201,6 +227,7 @@ impl From<QobuzArtistPage> for ArtistPage {
                     Track {
                         id: t.id,
                         number: t.physical_support.track_number,
+                        disc_number: t.physical_support.media_number,
                         title: t.title,
                         album: Some(TrackAlbum {
                             id: t.album.id,
@@ -281,6 +308,7 @@ impl From<QobuzTrack> for Track {
         Self {
             id: value.id,
             number: value.track_number as u32,
+            disc_number: value.media_number as u32,
             title: value.title,
             album,
             artist,
@@ -300,7 +328,7 @@ impl From<&QobuzTrack> for Track {
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TrackStatus {
     Played,
     Playing,
@@ -313,6 +341,7 @@ pub enum TrackStatus {
 pub struct Track {
     pub id: u32,
     pub number: u32,
+    pub disc_number: u32,
     pub title: String,
     pub album: Option<TrackAlbum>,
     pub artist: Option<Artist>,
@@ -360,6 +389,7 @@ pub struct SearchResults {
 #[derive(Default, Debug, Clone)]
 pub struct Favorites {
     pub albums: Vec<Album>,
+    pub tracks: Vec<Track>,
     pub artists: Vec<Artist>,
     pub playlists: Vec<Playlist>,
 }
@@ -1,13 +1,69 @@
 use gstreamer::{ClockTime, State};
 
-use crate::{error, tracklist::Tracklist};
+use crate::{
+    equalizer::Equalizer, error, fade::Fades, models::Track, output::Output,
+    replaygain::ReplayGain, sleep_timer::SleepTimer, stream_format::StreamFormat,
+    tracklist::QueueDiff,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Notification {
-    Status { status: State },
-    Position { clock: ClockTime },
-    CurrentTrackList { list: Tracklist },
+    Status {
+        status: State,
+    },
+    Position {
+        clock: ClockTime,
+    },
+    /// The current track was seeked to a new position.
+    Seeked {
+        position: ClockTime,
+    },
+    /// A different track became current, with its metadata, or `None` when nothing is current.
+    TrackChanged {
+        track: Option<Track>,
+    },
+    /// The queue or its modes changed, `diff` brings a `TracklistCopy` up to date.
+    QueueChanged {
+        diff: QueueDiff,
+    },
+    /// How much of the stream is buffered, playback pauses until it reaches 100.
+    Buffering {
+        percent: i32,
+    },
     Quit,
-    Error { error: error::Error },
-    Volume { volume: f64 },
+    Error {
+        error: error::Error,
+    },
+    /// A track failed to play and was given up on.
+    PlaybackError {
+        track_id: u32,
+        error: error::Error,
+    },
+    Volume {
+        volume: f64,
+    },
+    PlaylistChanged {
+        id: i64,
+    },
+    ReplayGain {
+        replay_gain: ReplayGain,
+    },
+    Output {
+        output: Output,
+    },
+    BitPerfect {
+        bit_perfect: bool,
+    },
+    StreamFormat {
+        format: StreamFormat,
+    },
+    Equalizer {
+        equalizer: Equalizer,
+    },
+    Fades {
+        fades: Fades,
+    },
+    SleepTimer {
+        timer: Option<SleepTimer>,
+    },
 }
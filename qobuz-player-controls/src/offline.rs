@@ -0,0 +1,349 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+use tokio::sync::Mutex;
+
+use crate::{
+    error::Error,
+    models::TrackStatus,
+    tracklist::{self, TrackListType},
+};
+
+/// The file in the cache directory listing what is kept offline.
+const INDEX_FILE: &str = "offline.json";
+
+/// An album or playlist marked to be kept for offline use.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OfflineCollection {
+    /// The album or playlist, as it is shown while playing it.
+    pub list_type: TrackListType,
+    pub cover_art: Option<String>,
+    pub tracks: Vec<tracklist::Track>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct CachedFile {
+    size: u64,
+    /// Unix seconds of when the file was last downloaded or played.
+    used_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    collections: Vec<OfflineCollection>,
+    files: HashMap<u32, CachedFile>,
+}
+
+impl Index {
+    fn is_marked(&self, track_id: u32) -> bool {
+        self.collections
+            .iter()
+            .any(|collection| collection.tracks.iter().any(|track| track.id == track_id))
+    }
+
+    fn with_statuses(&self, collection: &OfflineCollection) -> OfflineCollection {
+        let tracks = collection
+            .tracks
+            .iter()
+            .map(|track| tracklist::Track {
+                status: if self.files.contains_key(&track.id) {
+                    TrackStatus::Unplayed
+                } else {
+                    TrackStatus::Unplayable
+                },
+                ..track.clone()
+            })
+            .collect();
+
+        OfflineCollection {
+            tracks,
+            ..collection.clone()
+        }
+    }
+}
+
+/// Downloaded tracks in a directory, kept under a size limit by removing the least recently
+/// played ones that aren't marked for offline use, and the albums and playlists marked to be kept
+/// for offline use.
+#[derive(Debug)]
+pub struct OfflineCache {
+    dir: PathBuf,
+    max_size: u64,
+    index: Mutex<Index>,
+    /// Held while tracks are being downloaded, so only one download runs at a time.
+    downloading: Mutex<()>,
+}
+
+impl OfflineCache {
+    /// Opens the cache in `dir`, creating it if needed. `max_size` is in bytes.
+    pub async fn open(dir: PathBuf, max_size: u64) -> io::Result<Self> {
+        tokio::fs::create_dir_all(&dir).await?;
+
+        let mut index: Index = match tokio::fs::read(dir.join(INDEX_FILE)).await {
+            Ok(contents) => serde_json::from_slice(&contents).unwrap_or_default(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Index::default(),
+            Err(error) => return Err(error),
+        };
+
+        // Files may have been removed by hand.
+        index
+            .files
+            .retain(|track_id, _| file_path(&dir, *track_id).exists());
+
+        Ok(Self {
+            dir,
+            max_size,
+            index: Mutex::new(index),
+            downloading: Mutex::new(()),
+        })
+    }
+
+    /// The albums and playlists kept for offline use.
+    /// Tracks that haven't been downloaded yet are marked unplayable.
+    pub async fn collections(&self) -> Vec<OfflineCollection> {
+        let index = self.index.lock().await;
+
+        index
+            .collections
+            .iter()
+            .map(|collection| index.with_statuses(collection))
+            .collect()
+    }
+
+    /// An album or playlist kept for offline use, like in [`OfflineCache::collections`].
+    pub async fn collection(&self, list_type: &TrackListType) -> Option<OfflineCollection> {
+        let index = self.index.lock().await;
+
+        index
+            .collections
+            .iter()
+            .find(|collection| same_collection(&collection.list_type, list_type))
+            .map(|collection| index.with_statuses(collection))
+    }
+
+    /// Whether an album or playlist is kept for offline use.
+    pub async fn is_marked(&self, list_type: &TrackListType) -> bool {
+        self.index
+            .lock()
+            .await
+            .collections
+            .iter()
+            .any(|collection| same_collection(&collection.list_type, list_type))
+    }
+
+    /// Whether a track has been downloaded.
+    pub async fn contains(&self, track_id: u32) -> bool {
+        self.index.lock().await.files.contains_key(&track_id)
+    }
+
+    /// Keeps an album or playlist for offline use, replacing it if it was marked before.
+    pub(crate) async fn mark(&self, collection: OfflineCollection) -> io::Result<()> {
+        let mut index = self.index.lock().await;
+        index
+            .collections
+            .retain(|marked| !same_collection(&marked.list_type, &collection.list_type));
+        index.collections.push(collection);
+
+        self.save(&index).await
+    }
+
+    /// Stops keeping an album or playlist offline.
+    /// Its tracks stay in the cache until they are evicted to make room.
+    pub(crate) async fn unmark(&self, list_type: &TrackListType) -> io::Result<()> {
+        let mut index = self.index.lock().await;
+        index
+            .collections
+            .retain(|collection| !same_collection(&collection.list_type, list_type));
+
+        self.save(&index).await
+    }
+
+    /// The downloaded file of a track, marking it as recently played.
+    pub(crate) async fn path(&self, track_id: u32) -> Option<PathBuf> {
+        let mut index = self.index.lock().await;
+        let file = index.files.get_mut(&track_id)?;
+        file.used_at = chrono::Utc::now().timestamp();
+
+        Some(file_path(&self.dir, track_id))
+    }
+
+    /// Tracks of the marked albums and playlists that have not been downloaded yet.
+    pub(crate) async fn missing(&self) -> Vec<u32> {
+        let index = self.index.lock().await;
+        let mut missing: Vec<u32> = index
+            .collections
+            .iter()
+            .flat_map(|collection| &collection.tracks)
+            .filter(|track| track.status != TrackStatus::Unplayable)
+            .filter(|track| !index.files.contains_key(&track.id))
+            .map(|track| track.id)
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+
+        missing
+    }
+
+    /// Where a track is written while it downloads, see [`OfflineCache::add`].
+    pub(crate) fn partial_path(&self, track_id: u32) -> PathBuf {
+        self.dir.join(format!("{track_id}.part"))
+    }
+
+    /// Moves a finished download into the cache, then removes the least recently played tracks
+    /// that aren't marked for offline use until the cache fits its size limit.
+    /// When the marked tracks alone don't fit, the download is dropped again and
+    /// [`Error::OfflineCacheFull`] returned.
+    pub(crate) async fn add(&self, track_id: u32) -> Result<(), Error> {
+        let path = file_path(&self.dir, track_id);
+        tokio::fs::rename(self.partial_path(track_id), &path).await?;
+        let size = tokio::fs::metadata(&path).await?.len();
+
+        let mut index = self.index.lock().await;
+        index.files.insert(
+            track_id,
+            CachedFile {
+                size,
+                used_at: chrono::Utc::now().timestamp(),
+            },
+        );
+
+        let mut total: u64 = index.files.values().map(|file| file.size).sum();
+        let mut candidates: Vec<(u32, CachedFile)> = index
+            .files
+            .iter()
+            .filter(|(id, _)| **id != track_id && !index.is_marked(**id))
+            .map(|(id, file)| (*id, *file))
+            .collect();
+        candidates.sort_by_key(|(_, file)| file.used_at);
+
+        for (id, file) in candidates {
+            if total <= self.max_size {
+                break;
+            }
+
+            tracing::info!("Removing track {id} from the offline cache to make room");
+            remove_file(&file_path(&self.dir, id)).await?;
+
+            index.files.remove(&id);
+            total -= file.size;
+        }
+
+        if total > self.max_size {
+            remove_file(&path).await?;
+            index.files.remove(&track_id);
+            self.save(&index).await?;
+
+            return Err(Error::OfflineCacheFull);
+        }
+
+        Ok(self.save(&index).await?)
+    }
+
+    /// Taken while downloading, `None` if a download is already running.
+    pub(crate) fn start_downloading(&self) -> Option<tokio::sync::MutexGuard<'_, ()>> {
+        self.downloading.try_lock().ok()
+    }
+
+    async fn save(&self, index: &Index) -> io::Result<()> {
+        let contents = serde_json::to_vec(index).map_err(io::Error::other)?;
+        tokio::fs::write(self.dir.join(INDEX_FILE), contents).await
+    }
+}
+
+/// Whether two tracklists are the same album or playlist, by id since titles may change.
+fn same_collection(a: &TrackListType, b: &TrackListType) -> bool {
+    match (a, b) {
+        (TrackListType::Album(a), TrackListType::Album(b)) => a.id == b.id,
+        (TrackListType::Playlist(a), TrackListType::Playlist(b)) => a.id == b.id,
+        _ => false,
+    }
+}
+
+/// Removes a file, if it is still there.
+pub(crate) async fn remove_file(path: &Path) -> io::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+fn file_path(dir: &Path, track_id: u32) -> PathBuf {
+    dir.join(track_id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracklist::AlbumTracklist;
+
+    /// An empty cache of `max_size` bytes in a fresh directory.
+    async fn cache(name: &str, max_size: u64) -> OfflineCache {
+        let dir = std::env::temp_dir().join(format!(
+            "qobuz-player-offline-{}-{name}",
+            std::process::id()
+        ));
+        _ = std::fs::remove_dir_all(&dir);
+
+        OfflineCache::open(dir, max_size).await.unwrap()
+    }
+
+    /// Downloads a track of `size` bytes and adds it to the cache.
+    async fn add(cache: &OfflineCache, track_id: u32, size: usize) -> Result<(), Error> {
+        tokio::fs::write(cache.partial_path(track_id), vec![0; size])
+            .await
+            .unwrap();
+
+        cache.add(track_id).await
+    }
+
+    async fn mark(cache: &OfflineCache, track_ids: &[u32]) {
+        let collection = OfflineCollection {
+            list_type: TrackListType::Album(AlbumTracklist {
+                id: "album".to_string(),
+                ..Default::default()
+            }),
+            cover_art: None,
+            tracks: track_ids
+                .iter()
+                .map(|&id| tracklist::Track {
+                    id,
+                    ..Default::default()
+                })
+                .collect(),
+        };
+
+        cache.mark(collection).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn add_makes_room_by_removing_unmarked_tracks() {
+        let cache = cache("unmarked", 10).await;
+        mark(&cache, &[1]).await;
+
+        add(&cache, 1, 4).await.unwrap();
+        add(&cache, 2, 4).await.unwrap();
+        add(&cache, 3, 4).await.unwrap();
+
+        assert!(cache.contains(1).await);
+        assert!(!cache.contains(2).await);
+        assert!(cache.contains(3).await);
+        assert!(!file_path(&cache.dir, 2).exists());
+    }
+
+    #[tokio::test]
+    async fn add_never_removes_marked_tracks() {
+        let cache = cache("marked", 6).await;
+        mark(&cache, &[1, 2]).await;
+
+        add(&cache, 1, 4).await.unwrap();
+        assert_eq!(add(&cache, 2, 4).await, Err(Error::OfflineCacheFull));
+
+        assert!(cache.contains(1).await);
+        assert!(!cache.contains(2).await);
+        assert!(!file_path(&cache.dir, 2).exists());
+        assert_eq!(cache.missing().await, vec![2]);
+    }
+}
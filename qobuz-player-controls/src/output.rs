@@ -0,0 +1,175 @@
+use gstreamer::{prelude::*, Device, DeviceMonitor, Element, ElementFactory};
+use std::{fmt::Display, path::PathBuf, str::FromStr};
+
+use crate::{error::Error, Result};
+
+/// Where a zone sends its audio.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub enum Output {
+    /// Whatever GStreamer picks as the system default.
+    #[default]
+    Auto,
+    /// A device found by the device monitor, by display name.
+    Device(String),
+    /// An ALSA device, like `hw:0`.
+    Alsa(String),
+    /// A PulseAudio sink.
+    Pulse(String),
+    /// A PipeWire node name or serial.
+    PipeWire(String),
+    /// A pattern matching the JACK ports to connect to.
+    Jack(String),
+    /// A wav file, for running without any audio hardware.
+    File(PathBuf),
+    /// Throws the audio away, in real time.
+    Null,
+}
+
+impl Display for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Output::Auto => f.write_str("auto"),
+            Output::Device(name) => write!(f, "device:{name}"),
+            Output::Alsa(device) => write!(f, "alsa:{device}"),
+            Output::Pulse(device) => write!(f, "pulse:{device}"),
+            Output::PipeWire(target) => write!(f, "pipewire:{target}"),
+            Output::Jack(ports) => write!(f, "jack:{ports}"),
+            Output::File(path) => write!(f, "file:{}", path.display()),
+            Output::Null => f.write_str("null"),
+        }
+    }
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let output = match s {
+            "" => return Err("output can not be empty".to_string()),
+            "auto" => Output::Auto,
+            "null" => Output::Null,
+            _ => match s.split_once(':') {
+                Some(("device", name)) => Output::Device(name.to_string()),
+                Some(("alsa", device)) => Output::Alsa(device.to_string()),
+                Some(("pulse", device)) => Output::Pulse(device.to_string()),
+                Some(("pipewire", target)) => Output::PipeWire(target.to_string()),
+                Some(("jack", ports)) => Output::Jack(ports.to_string()),
+                Some(("file", path)) => Output::File(path.into()),
+                // Zones have always taken bare ALSA device names like hw:0.
+                _ => Output::Alsa(s.to_string()),
+            },
+        };
+
+        Ok(output)
+    }
+}
+
+impl Output {
+    /// Builds the element to use as the playbin `audio-sink`.
+    pub(crate) fn sink(&self, bit_perfect: bool) -> Result<Element> {
+        if bit_perfect {
+            return self.bit_perfect_sink();
+        }
+
+        let sink = match self {
+            Output::Auto => ElementFactory::make("autoaudiosink").build()?,
+            Output::Device(name) => device(name)?.create_element(None)?,
+            Output::Alsa(device) => ElementFactory::make("alsasink")
+                .property("device", device.as_str())
+                .build()?,
+            Output::Pulse(device) => ElementFactory::make("pulsesink")
+                .property("device", device.as_str())
+                .build()?,
+            Output::PipeWire(target) => ElementFactory::make("pipewiresink")
+                .property("target-object", target.as_str())
+                .build()?,
+            Output::Jack(ports) => ElementFactory::make("jackaudiosink")
+                .property("port-pattern", ports.as_str())
+                .build()?,
+            Output::File(path) => {
+                let bin = gstreamer::parse::bin_from_description(
+                    "wavenc ! filesink name=file sync=true",
+                    true,
+                )?;
+
+                if let Some(file) = bin.by_name("file") {
+                    file.set_property("location", path.to_string_lossy().as_ref());
+                }
+
+                bin.upcast()
+            }
+            Output::Null => ElementFactory::make("fakesink")
+                .property("sync", true)
+                .build()?,
+        };
+
+        Ok(sink)
+    }
+
+    /// Whether two streams can play to the output at the same time, as they do while crossfading.
+    /// ALSA hw devices can only be opened once and a file only holds one stream.
+    pub(crate) fn can_overlap(&self) -> bool {
+        match self {
+            Output::Alsa(device) => !device.trim_start_matches("plug").starts_with("hw:"),
+            Output::File(_) => false,
+            _ => true,
+        }
+    }
+
+    /// An `alsasink` on the hw device itself, so ALSA doesn't convert anything either.
+    fn bit_perfect_sink(&self) -> Result<Element> {
+        let not_hw = || Error::BitPerfectOutput {
+            output: self.to_string(),
+        };
+
+        let Output::Alsa(device) = self else {
+            return Err(not_hw());
+        };
+
+        // plughw converts whatever it is given, hw refuses what the card can't take.
+        let device = device.strip_prefix("plug").unwrap_or(device);
+
+        if !device.starts_with("hw:") {
+            return Err(not_hw());
+        }
+
+        Ok(ElementFactory::make("alsasink")
+            .property("device", device)
+            .build()?)
+    }
+}
+
+/// Lists the outputs that can be picked without knowing device names,
+/// the default, every audio sink the device monitor finds and the null output.
+pub fn available() -> Result<Vec<Output>> {
+    let devices = audio_sinks()?
+        .into_iter()
+        .map(|device| Output::Device(device.display_name().to_string()));
+
+    Ok(std::iter::once(Output::Auto)
+        .chain(devices)
+        .chain(std::iter::once(Output::Null))
+        .collect())
+}
+
+fn device(name: &str) -> Result<Device> {
+    audio_sinks()?
+        .into_iter()
+        .find(|device| device.display_name().as_str() == name)
+        .ok_or_else(|| Error::OutputDevice {
+            name: name.to_string(),
+        })
+}
+
+fn audio_sinks() -> Result<Vec<Device>> {
+    gstreamer::init()?;
+
+    let monitor = DeviceMonitor::new();
+    monitor.add_filter(Some("Audio/Sink"), None);
+    monitor.start()?;
+
+    let devices = monitor.devices().into_iter().collect();
+    monitor.stop();
+
+    Ok(devices)
+}
@@ -0,0 +1,172 @@
+use crate::tracklist;
+use rand::seq::SliceRandom;
+use std::collections::VecDeque;
+
+/// How many tracks the radio adds at a time.
+pub(crate) const RADIO_TRACKS: usize = 10;
+
+/// How many similar artists are asked for tracks each time the radio refills.
+pub(crate) const RADIO_SIMILAR_ARTISTS: usize = 5;
+
+/// A track is not picked again until this many others have played.
+const TRACK_WINDOW: usize = 200;
+
+/// An artist is not picked again until this many other tracks have played.
+const ARTIST_WINDOW: usize = 2;
+
+/// How likely a track is to be picked, relative to the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Weight {
+    /// A top track of the radio's artist.
+    Top,
+    /// A track from one of the artist's albums.
+    Album,
+    /// A top track of a similar artist.
+    SimilarTop,
+    /// A track from an album of a similar artist.
+    SimilarAlbum,
+}
+
+impl Weight {
+    fn value(self) -> u32 {
+        match self {
+            Self::Top => 6,
+            Self::Album => 3,
+            Self::SimilarTop => 2,
+            Self::SimilarAlbum => 1,
+        }
+    }
+}
+
+/// A track the radio can pick.
+#[derive(Debug, Clone)]
+pub(crate) struct Candidate {
+    pub(crate) track: tracklist::Track,
+    pub(crate) artist_id: u32,
+    pub(crate) weight: Weight,
+}
+
+/// What the radio played recently, so tracks and artists don't repeat too soon.
+#[derive(Debug, Default)]
+pub(crate) struct RadioHistory {
+    tracks: VecDeque<u32>,
+    artists: VecDeque<u32>,
+}
+
+impl RadioHistory {
+    /// Picks up to `count` candidates at random, favouring higher weights.
+    /// Tracks in the history are skipped, and so are recent artists as long as others are left.
+    pub(crate) fn pick(
+        &mut self,
+        mut candidates: Vec<Candidate>,
+        count: usize,
+    ) -> Vec<tracklist::Track> {
+        let mut rng = rand::thread_rng();
+        let mut picked = vec![];
+
+        candidates.retain(|candidate| !self.tracks.contains(&candidate.track.id));
+
+        while picked.len() < count && !candidates.is_empty() {
+            let fresh: Vec<usize> = (0..candidates.len())
+                .filter(|&index| !self.artists.contains(&candidates[index].artist_id))
+                .collect();
+
+            let eligible = if fresh.is_empty() {
+                (0..candidates.len()).collect()
+            } else {
+                fresh
+            };
+
+            let Ok(&index) =
+                eligible.choose_weighted(&mut rng, |&index| candidates[index].weight.value())
+            else {
+                break;
+            };
+
+            let candidate = candidates.swap_remove(index);
+            candidates.retain(|other| other.track.id != candidate.track.id);
+
+            push_bounded(&mut self.tracks, candidate.track.id, TRACK_WINDOW);
+            push_bounded(&mut self.artists, candidate.artist_id, ARTIST_WINDOW);
+
+            picked.push(candidate.track);
+        }
+
+        picked
+    }
+}
+
+fn push_bounded<T>(items: &mut VecDeque<T>, item: T, length: usize) {
+    if items.len() == length {
+        items.pop_front();
+    }
+
+    items.push_back(item);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(track_id: u32, artist_id: u32) -> Candidate {
+        Candidate {
+            track: tracklist::Track {
+                id: track_id,
+                ..Default::default()
+            },
+            artist_id,
+            weight: Weight::Top,
+        }
+    }
+
+    #[test]
+    fn picks_a_track_once() {
+        let mut history = RadioHistory::default();
+        let candidates = vec![candidate(1, 1), candidate(1, 1), candidate(2, 2)];
+
+        let picked = history.pick(candidates.clone(), 10);
+        let mut ids: Vec<u32> = picked.iter().map(|track| track.id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![1, 2]);
+
+        assert!(history.pick(candidates, 10).is_empty());
+    }
+
+    #[test]
+    fn does_not_repeat_an_artist_while_others_are_left() {
+        let mut history = RadioHistory::default();
+        let candidates = vec![
+            candidate(1, 1),
+            candidate(2, 1),
+            candidate(3, 2),
+            candidate(4, 2),
+        ];
+
+        let picked = history.pick(candidates, 2);
+        let artists: Vec<u32> = picked.iter().map(|track| track.id.div_ceil(2)).collect();
+        assert_eq!(picked.len(), 2);
+        assert_ne!(artists[0], artists[1]);
+    }
+
+    #[test]
+    fn repeats_an_artist_when_no_other_is_left() {
+        let mut history = RadioHistory::default();
+        let picked = history.pick(vec![candidate(1, 1), candidate(2, 1)], 2);
+
+        assert_eq!(picked.len(), 2);
+    }
+
+    #[test]
+    fn forgets_tracks_outside_the_window() {
+        let mut history = RadioHistory::default();
+        history.pick(vec![candidate(0, 0)], 1);
+
+        for track_id in 1..TRACK_WINDOW as u32 {
+            history.pick(vec![candidate(track_id, track_id)], 1);
+        }
+        assert!(history.pick(vec![candidate(0, 0)], 1).is_empty());
+
+        history.pick(vec![candidate(TRACK_WINDOW as u32, 1)], 1);
+        assert_eq!(history.pick(vec![candidate(0, 0)], 1).len(), 1);
+    }
+}
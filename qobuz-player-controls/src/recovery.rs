@@ -0,0 +1,88 @@
+use std::time::{Duration, Instant};
+
+/// How many times a track is reloaded after failing before moving on to the next one.
+const MAX_RETRIES: u32 = 3;
+
+/// The wait before the first retry, doubled for every one after it.
+const FIRST_BACKOFF: Duration = Duration::from_secs(2);
+
+/// A track that plays this long after being reloaded counts as recovered.
+const RECOVERED_AFTER: Duration = Duration::from_secs(30);
+
+/// Failures of the current track, so a track that keeps failing is given up on.
+#[derive(Debug, Default)]
+pub(crate) struct Recovery {
+    track_id: Option<u32>,
+    retries: u32,
+    reloaded_at: Option<Instant>,
+}
+
+impl Recovery {
+    /// Counts a failure of a track and returns how long to wait before reloading it,
+    /// or `None` when it has failed too often.
+    pub(crate) fn retry(&mut self, track_id: u32) -> Option<Duration> {
+        if self.track_id != Some(track_id) {
+            *self = Self {
+                track_id: Some(track_id),
+                ..Default::default()
+            };
+        }
+
+        if self.retries == MAX_RETRIES {
+            *self = Self::default();
+            return None;
+        }
+
+        let backoff = FIRST_BACKOFF * 2u32.pow(self.retries);
+        self.retries += 1;
+        self.reloaded_at = Some(Instant::now() + backoff);
+
+        Some(backoff)
+    }
+
+    /// Forgets the failures once the track has played long enough since it was reloaded.
+    pub(crate) fn settle(&mut self) {
+        if self
+            .reloaded_at
+            .is_some_and(|reloaded_at| reloaded_at.elapsed() > RECOVERED_AFTER)
+        {
+            *self = Self::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_then_gives_up() {
+        let mut recovery = Recovery::default();
+
+        assert_eq!(recovery.retry(1), Some(FIRST_BACKOFF));
+        assert_eq!(recovery.retry(1), Some(FIRST_BACKOFF * 2));
+        assert_eq!(recovery.retry(1), Some(FIRST_BACKOFF * 4));
+        assert_eq!(recovery.retry(1), None);
+
+        // Giving up starts over for the next failure.
+        assert_eq!(recovery.retry(1), Some(FIRST_BACKOFF));
+    }
+
+    #[test]
+    fn counts_failures_per_track() {
+        let mut recovery = Recovery::default();
+        recovery.retry(1);
+        recovery.retry(1);
+
+        assert_eq!(recovery.retry(2), Some(FIRST_BACKOFF));
+    }
+
+    #[test]
+    fn keeps_failures_until_recovered() {
+        let mut recovery = Recovery::default();
+        recovery.retry(1);
+        recovery.settle();
+
+        assert_eq!(recovery.retry(1), Some(FIRST_BACKOFF * 2));
+    }
+}
@@ -0,0 +1,119 @@
+use qobuz_player_client::qobuz_models::track::Track as QobuzTrack;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    Track,
+    Album,
+}
+
+impl Display for ReplayGainMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayGainMode::Off => f.write_str("off"),
+            ReplayGainMode::Track => f.write_str("track"),
+            ReplayGainMode::Album => f.write_str("album"),
+        }
+    }
+}
+
+impl FromStr for ReplayGainMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(ReplayGainMode::Off),
+            "track" => Ok(ReplayGainMode::Track),
+            "album" => Ok(ReplayGainMode::Album),
+            _ => Err(format!(
+                "unknown replaygain mode {s}, expected off, track or album"
+            )),
+        }
+    }
+}
+
+/// How track loudness is normalized.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReplayGain {
+    pub mode: ReplayGainMode,
+    /// Extra gain in dB applied to tracks with replaygain information.
+    pub preamp: f64,
+    /// Lower the gain when the peak would otherwise clip.
+    pub prevent_clipping: bool,
+}
+
+impl Default for ReplayGain {
+    fn default() -> Self {
+        Self {
+            mode: ReplayGainMode::Off,
+            preamp: 0.0,
+            prevent_clipping: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Gain {
+    /// Gain in dB.
+    pub gain: f64,
+    /// Peak amplitude, where 1.0 is full scale.
+    pub peak: f64,
+}
+
+impl Gain {
+    pub(crate) fn from_track(track: &QobuzTrack) -> Option<Self> {
+        track.audio_info.replaygain_track_gain.map(|gain| Self {
+            gain,
+            peak: track.audio_info.replaygain_track_peak.unwrap_or(1.0),
+        })
+    }
+
+    /// Qobuz only reports track gains, so the album gain is derived from the
+    /// duration weighted loudness of its tracks and the album peak is the loudest track peak.
+    pub(crate) fn from_album_tracks(tracks: &[QobuzTrack]) -> Option<Self> {
+        let (energy, duration, peak) = tracks
+            .iter()
+            .filter_map(|track| Self::from_track(track).map(|gain| (gain, track.duration as f64)))
+            .fold(
+                (0.0, 0.0, 0.0_f64),
+                |(energy, duration, peak), (gain, length)| {
+                    (
+                        energy + length * 10_f64.powf(-gain.gain / 10.0),
+                        duration + length,
+                        peak.max(gain.peak),
+                    )
+                },
+            );
+
+        (duration > 0.0).then(|| Self {
+            gain: -10.0 * (energy / duration).log10(),
+            peak,
+        })
+    }
+}
+
+impl ReplayGain {
+    /// The linear volume to play a track at.
+    pub(crate) fn volume(&self, track: Option<Gain>, album: Option<Gain>) -> f64 {
+        let gain = match self.mode {
+            ReplayGainMode::Off => None,
+            ReplayGainMode::Track => track,
+            ReplayGainMode::Album => album.or(track),
+        };
+
+        let Some(gain) = gain else {
+            return 1.0;
+        };
+
+        let volume = 10_f64.powf((gain.gain + self.preamp) / 20.0);
+
+        if self.prevent_clipping && gain.peak > 0.0 {
+            volume.min(1.0 / gain.peak)
+        } else {
+            volume
+        }
+    }
+}
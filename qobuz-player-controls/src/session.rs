@@ -0,0 +1,11 @@
+use gstreamer::ClockTime;
+
+use crate::tracklist::Tracklist;
+
+/// Player state saved between runs, restored by `player_loop`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub tracklist: Tracklist,
+    pub position: ClockTime,
+    pub volume: f64,
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+/// How long playback fades out before a timed stop.
+pub(crate) const SLEEP_FADE: Duration = Duration::from_secs(10);
+
+/// When playback should stop by itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepTimer {
+    /// Fade out and pause at a point in time.
+    At { deadline: DateTime<Utc> },
+    /// Pause when the current track ends.
+    EndOfTrack,
+    /// Pause when the last track of the current album ends.
+    EndOfAlbum,
+}
+
+impl SleepTimer {
+    pub fn in_minutes(minutes: u32) -> Self {
+        Self::At {
+            deadline: Utc::now() + TimeDelta::minutes(minutes.into()),
+        }
+    }
+
+    /// Time left of a timed stop.
+    pub fn remaining(&self) -> Option<Duration> {
+        match self {
+            Self::At { deadline } => Some((*deadline - Utc::now()).to_std().unwrap_or_default()),
+            Self::EndOfTrack | Self::EndOfAlbum => None,
+        }
+    }
+}
+
+/// A countdown like 14:59 for timed stops, otherwise what playback stops after.
+impl Display for SleepTimer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.remaining() {
+            Some(remaining) => {
+                let seconds = remaining.as_secs();
+                let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+                if hours > 0 {
+                    write!(f, "{hours}:{minutes:02}:{seconds:02}")
+                } else {
+                    write!(f, "{minutes:02}:{seconds:02}")
+                }
+            }
+            None if *self == Self::EndOfTrack => f.write_str("after this track"),
+            None => f.write_str("after this album"),
+        }
+    }
+}
+
+/// Minutes from now, `track` or `album`.
+impl FromStr for SleepTimer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "track" => Ok(Self::EndOfTrack),
+            "album" => Ok(Self::EndOfAlbum),
+            minutes => minutes
+                .parse()
+                .map(Self::in_minutes)
+                .map_err(|_| format!("unknown sleep timer {s}, expected minutes, track or album")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_minutes_track_and_album() {
+        assert_eq!("track".parse(), Ok(SleepTimer::EndOfTrack));
+        assert_eq!("album".parse(), Ok(SleepTimer::EndOfAlbum));
+        assert!("soon".parse::<SleepTimer>().is_err());
+
+        let timer: SleepTimer = "30".parse().unwrap();
+        let remaining = timer.remaining().unwrap();
+        assert!(remaining <= Duration::from_secs(30 * 60));
+        assert!(remaining > Duration::from_secs(29 * 60));
+    }
+
+    #[test]
+    fn shows_the_time_left() {
+        let timer = SleepTimer::At {
+            deadline: Utc::now() + TimeDelta::seconds(90) + TimeDelta::milliseconds(500),
+        };
+        assert_eq!(timer.to_string(), "01:30");
+
+        let timer = SleepTimer::At {
+            deadline: Utc::now() + TimeDelta::minutes(61) + TimeDelta::milliseconds(500),
+        };
+        assert_eq!(timer.to_string(), "1:01:00");
+
+        assert_eq!(SleepTimer::EndOfTrack.to_string(), "after this track");
+    }
+}
@@ -0,0 +1,95 @@
+use gstreamer::{Caps, StructureRef};
+use std::fmt::Display;
+
+/// The format of the playing stream, as negotiated by its decoder.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StreamFormat {
+    /// Short codec name, like FLAC or MP3.
+    pub codec: Option<String>,
+    /// Samples per second.
+    pub sample_rate: Option<u32>,
+    /// Bits per sample, only known for lossless codecs.
+    pub bit_depth: Option<u32>,
+    pub channels: Option<u32>,
+}
+
+/// Shows the format like "FLAC 24/96" or "MP3 44.1kHz".
+impl Display for StreamFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.codec.as_deref().unwrap_or("PCM"))?;
+
+        let sample_rate = self.sample_rate.map(|rate| rate as f64 / 1000.0);
+
+        match (self.bit_depth, sample_rate) {
+            (Some(bit_depth), Some(sample_rate)) => write!(f, " {bit_depth}/{sample_rate}"),
+            (None, Some(sample_rate)) => write!(f, " {sample_rate}kHz"),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl StreamFormat {
+    /// Takes the codec from the caps going into the decoder.
+    pub(crate) fn set_encoded_caps(&mut self, caps: &Caps) {
+        if let Some(structure) = caps.structure(0) {
+            self.codec = Some(codec_name(structure));
+        }
+    }
+
+    /// Takes the rate, depth and channels from the caps coming out of the decoder.
+    pub(crate) fn set_decoded_caps(&mut self, caps: &Caps) {
+        let Some(structure) = caps.structure(0) else {
+            return;
+        };
+
+        self.sample_rate = structure.get::<i32>("rate").ok().map(|rate| rate as u32);
+        self.channels = structure
+            .get::<i32>("channels")
+            .ok()
+            .map(|channels| channels as u32);
+
+        // Lossy decoders output whatever depth they like, it says nothing about the stream.
+        self.bit_depth = if self.is_lossy() {
+            None
+        } else {
+            structure
+                .get::<&str>("format")
+                .ok()
+                .and_then(integer_bit_depth)
+        };
+    }
+
+    fn is_lossy(&self) -> bool {
+        matches!(
+            self.codec.as_deref(),
+            Some("MP3" | "AAC" | "Vorbis" | "Opus")
+        )
+    }
+}
+
+fn codec_name(structure: &StructureRef) -> String {
+    match structure.name().as_str() {
+        "audio/x-flac" => "FLAC".to_string(),
+        "audio/x-alac" => "ALAC".to_string(),
+        "audio/x-vorbis" => "Vorbis".to_string(),
+        "audio/x-opus" => "Opus".to_string(),
+        "audio/mpeg" => match structure.get::<i32>("mpegversion") {
+            Ok(1) => "MP3".to_string(),
+            _ => "AAC".to_string(),
+        },
+        name => name
+            .trim_start_matches("audio/")
+            .trim_start_matches("x-")
+            .to_uppercase(),
+    }
+}
+
+/// Bits per sample of an integer raw audio format, 24 for S24_32LE.
+fn integer_bit_depth(format: &str) -> Option<u32> {
+    let digits = format.strip_prefix(['S', 'U'])?;
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+
+    digits[..end].parse().ok()
+}
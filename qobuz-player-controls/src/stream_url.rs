@@ -0,0 +1,135 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long a stream url is assumed to be valid when it doesn't say.
+const DEFAULT_LIFETIME: Duration = Duration::from_secs(10 * 60);
+
+/// Urls are fetched again this long before they expire.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// How long to wait for a fetch before trying again.
+const RETRY_AFTER: Duration = Duration::from_secs(10);
+
+/// A signed stream url for a track, valid for a limited time.
+#[derive(Debug, Clone)]
+pub(crate) struct StreamUrl {
+    pub(crate) track_id: u32,
+    pub(crate) url: String,
+    expires_at: Instant,
+}
+
+impl StreamUrl {
+    pub(crate) fn new(track_id: u32, url: String) -> Self {
+        let lifetime = expiry(&url)
+            .and_then(|expiry| expiry.duration_since(SystemTime::now()).ok())
+            .unwrap_or(DEFAULT_LIFETIME);
+
+        Self {
+            track_id,
+            url,
+            expires_at: Instant::now() + lifetime,
+        }
+    }
+
+    /// Whether the url can still be handed to playbin.
+    pub(crate) fn is_fresh(&self) -> bool {
+        Instant::now() + EXPIRY_MARGIN < self.expires_at
+    }
+}
+
+/// Qobuz signs urls with their expiry as unix seconds in the `etsp` parameter.
+fn expiry(url: &str) -> Option<SystemTime> {
+    let (_, query) = url.split_once('?')?;
+    let seconds = query
+        .split('&')
+        .find_map(|parameter| parameter.strip_prefix("etsp="))?
+        .parse()
+        .ok()?;
+
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// The url of the track after the current one, fetched ahead of time.
+#[derive(Debug, Default)]
+pub(crate) struct Prefetch {
+    url: Option<StreamUrl>,
+    attempted_at: Option<Instant>,
+}
+
+impl Prefetch {
+    /// Whether the url for a track should be fetched, because there is none yet
+    /// or it is about to expire, and no fetch was started recently.
+    pub(crate) fn is_due(&self, track_id: u32) -> bool {
+        let has_fresh_url = self
+            .url
+            .as_ref()
+            .is_some_and(|url| url.track_id == track_id && url.is_fresh());
+        let attempted_recently = self
+            .attempted_at
+            .is_some_and(|attempted_at| attempted_at.elapsed() < RETRY_AFTER);
+
+        !has_fresh_url && !attempted_recently
+    }
+
+    pub(crate) fn set(&mut self, url: StreamUrl) {
+        self.url = Some(url);
+        self.attempted_at = None;
+    }
+
+    /// Marks a fetch as started, so it isn't started again while it runs or shortly after it failed.
+    pub(crate) fn attempt(&mut self) {
+        self.attempted_at = Some(Instant::now());
+    }
+
+    /// The url for a track if it was fetched and is still fresh.
+    pub(crate) fn take(&mut self, track_id: u32) -> Option<String> {
+        self.url
+            .take_if(|url| url.track_id == track_id && url.is_fresh())
+            .map(|url| url.url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url_expiring_in(lifetime: Duration) -> String {
+        let expiry = (SystemTime::now() + lifetime)
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        format!("https://streaming.qobuz.com/file?uid=1&eid=2&etsp={expiry}&hmac=abc")
+    }
+
+    #[test]
+    fn reads_the_expiry_from_the_url() {
+        assert_eq!(
+            expiry("https://streaming.qobuz.com/file?eid=2&etsp=1700000000&hmac=abc"),
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(expiry("https://streaming.qobuz.com/file?eid=2"), None);
+        assert_eq!(expiry("https://streaming.qobuz.com/file"), None);
+    }
+
+    #[test]
+    fn urls_about_to_expire_are_not_fresh() {
+        assert!(StreamUrl::new(1, url_expiring_in(Duration::from_secs(600))).is_fresh());
+        assert!(!StreamUrl::new(1, url_expiring_in(Duration::from_secs(30))).is_fresh());
+        assert!(StreamUrl::new(1, "https://example.com/track.flac".to_string()).is_fresh());
+    }
+
+    #[test]
+    fn prefetched_urls_are_only_taken_for_their_track() {
+        let mut prefetch = Prefetch::default();
+        assert!(prefetch.is_due(1));
+
+        prefetch.attempt();
+        assert!(!prefetch.is_due(1));
+
+        prefetch.set(StreamUrl::new(1, url_expiring_in(Duration::from_secs(600))));
+        assert!(!prefetch.is_due(1));
+        assert_eq!(prefetch.take(2), None);
+        assert!(prefetch.take(1).is_some());
+        assert_eq!(prefetch.take(1), None);
+    }
+}
@@ -1,37 +1,102 @@
 use crate::models::{self, TrackStatus};
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AlbumTracklist {
     pub title: String,
     pub id: String,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PlaylistTracklist {
     pub title: String,
     pub id: i64,
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtistTracklist {
+    pub name: String,
+    pub id: u32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TrackListType {
     Album(AlbumTracklist),
     Playlist(PlaylistTracklist),
+    ArtistTopTracks(ArtistTracklist),
+    ArtistRadio(ArtistTracklist),
+    Search(String),
+    Favorites,
+    Custom,
     #[default]
     Track,
 }
 
-#[derive(Default, Debug, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RepeatMode {
+    #[default]
+    None,
+    Track,
+    All,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Tracklist {
     pub queue: Vec<Track>,
     pub list_type: TrackListType,
+    pub repeat_mode: RepeatMode,
+    /// Keep playing related music when the queue runs out.
+    #[serde(default)]
+    pub autoplay: bool,
+    shuffled: bool,
+    /// Positions in `queue` of the tracks in the order they had before shuffling.
+    /// Positions rather than tracks, since the queue may contain the same track more than once.
+    #[serde(default)]
+    unshuffled_order: Vec<usize>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq)]
+/// A track in the queue, with the metadata needed to show it without looking it up.
+/// Fields missing from sessions saved by older versions are left empty.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Track {
     pub id: u32,
     pub title: String,
     pub status: TrackStatus,
+    /// Added by autoplay rather than picked by the user.
+    pub added_by_autoplay: bool,
+    pub artist_id: Option<u32>,
+    pub artist_name: Option<String>,
+    pub album_id: Option<String>,
+    pub album_title: Option<String>,
+    pub disc_number: u32,
+    pub track_number: u32,
+    pub duration_seconds: u32,
+    pub explicit: bool,
+    pub hires_available: bool,
+    pub cover_art: Option<String>,
+    pub cover_art_small: Option<String>,
+}
+
+/// What changed from one version of the queue to the next, sent instead of the whole queue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueDiff {
+    /// Counts up with every diff a player sends, so a copy can tell when it missed one.
+    pub version: u64,
+    /// Position of the first track that was removed or inserted.
+    pub start: u32,
+    /// How many tracks were removed from `start`.
+    pub removed: u32,
+    /// The tracks inserted at `start` in their place.
+    pub inserted: Vec<Track>,
+    /// Positions and new statuses of the other tracks whose status changed.
+    pub statuses: Vec<(u32, TrackStatus)>,
+    pub list_type: TrackListType,
+    pub repeat_mode: RepeatMode,
+    pub autoplay: bool,
+    pub shuffled: bool,
 }
 
 impl From<models::Track> for Track {
@@ -40,6 +105,54 @@ impl From<models::Track> for Track {
             id: value.id,
             title: value.title,
             status: TrackStatus::Unplayed,
+            added_by_autoplay: false,
+            artist_id: value.artist.as_ref().map(|artist| artist.id),
+            artist_name: value.artist.map(|artist| artist.name),
+            album_id: value.album.as_ref().map(|album| album.id.clone()),
+            album_title: value.album.map(|album| album.title),
+            disc_number: value.disc_number,
+            track_number: value.number,
+            duration_seconds: value.duration_seconds,
+            explicit: value.explicit,
+            hires_available: value.hires_available,
+            cover_art: value.cover_art,
+            cover_art_small: value.cover_art_small,
+        }
+    }
+}
+
+impl From<Track> for models::Track {
+    fn from(value: Track) -> Self {
+        let artist = value
+            .artist_id
+            .zip(value.artist_name)
+            .map(|(id, name)| models::Artist {
+                id,
+                name,
+                image: None,
+            });
+
+        Self {
+            id: value.id,
+            number: value.track_number,
+            disc_number: value.disc_number,
+            title: value.title,
+            album: value
+                .album_id
+                .zip(value.album_title)
+                .map(|(id, title)| models::TrackAlbum {
+                    id,
+                    title,
+                    artist: artist.clone().unwrap_or_default(),
+                    image: value.cover_art.clone().unwrap_or_default(),
+                }),
+            artist,
+            duration_seconds: value.duration_seconds,
+            explicit: value.explicit,
+            hires_available: value.hires_available,
+            available: value.status != TrackStatus::Unplayable,
+            cover_art: value.cover_art,
+            cover_art_small: value.cover_art_small,
         }
     }
 }
@@ -49,6 +162,10 @@ impl Tracklist {
         Self {
             queue: Default::default(),
             list_type: Default::default(),
+            repeat_mode: Default::default(),
+            autoplay: false,
+            shuffled: false,
+            unshuffled_order: Default::default(),
         }
     }
     pub fn total(&self) -> u32 {
@@ -79,4 +196,603 @@ impl Tracklist {
     pub fn current_track(&self) -> Option<&Track> {
         self.queue.iter().find(|t| t.status == TrackStatus::Playing)
     }
+
+    /// Position of the track to play after the current one, respecting the repeat mode.
+    /// Unplayable tracks are passed over.
+    pub fn next_position(&self) -> Option<u32> {
+        self.position_after_current(self.repeat_mode)
+    }
+
+    /// Position of the track to skip to from the current one, like [`Tracklist::next_position`]
+    /// except that skipping moves on from a repeated track.
+    pub fn skip_position(&self) -> Option<u32> {
+        let repeat_mode = match self.repeat_mode {
+            RepeatMode::Track => RepeatMode::None,
+            repeat_mode => repeat_mode,
+        };
+
+        self.position_after_current(repeat_mode)
+    }
+
+    fn position_after_current(&self, repeat_mode: RepeatMode) -> Option<u32> {
+        let current_position = self.current_position() as usize;
+        let total = self.queue.len();
+
+        let next_position = match repeat_mode {
+            RepeatMode::None => {
+                (current_position + 1..total).find(|&position| self.is_playable(position))
+            }
+            RepeatMode::Track => {
+                Some(current_position).filter(|&position| self.is_playable(position))
+            }
+            RepeatMode::All => (1..=total)
+                .map(|offset| (current_position + offset) % total)
+                .find(|&position| self.is_playable(position)),
+        };
+
+        next_position.map(|position| position as u32)
+    }
+
+    /// Position of the closest playable track before the current one.
+    pub fn previous_position(&self) -> Option<u32> {
+        (0..self.current_position())
+            .rev()
+            .find(|&position| self.is_playable(position as usize))
+    }
+
+    fn is_playable(&self, position: usize) -> bool {
+        self.queue
+            .get(position)
+            .is_some_and(|track| track.status != TrackStatus::Unplayable)
+    }
+
+    /// Whether autoplay or a radio should add more tracks, which is once the last one is playing.
+    pub fn needs_more_tracks(&self) -> bool {
+        let refills = self.autoplay || matches!(self.list_type, TrackListType::ArtistRadio(_));
+
+        refills && self.currently_playing().is_some() && self.next_position().is_none()
+    }
+
+    /// Adds tracks to the end of the queue.
+    pub fn append(&mut self, tracks: Vec<Track>) {
+        if self.shuffled {
+            let total = self.queue.len();
+            self.unshuffled_order.extend(total..total + tracks.len());
+        }
+
+        self.queue.extend(tracks);
+    }
+
+    /// Adds tracks directly after the current track.
+    pub fn insert_next(&mut self, tracks: Vec<Track>) {
+        let position = if self.queue.is_empty() {
+            0
+        } else {
+            self.current_position() as usize + 1
+        };
+
+        if self.shuffled {
+            let inserted = tracks.len();
+            let unshuffled_position = self
+                .unshuffled_order
+                .iter()
+                .position(|&p| p + 1 == position)
+                .map_or(0, |p| p + 1);
+
+            for p in self.unshuffled_order.iter_mut().filter(|p| **p >= position) {
+                *p += inserted;
+            }
+            self.unshuffled_order.splice(
+                unshuffled_position..unshuffled_position,
+                position..position + inserted,
+            );
+        }
+
+        self.queue.splice(position..position, tracks);
+    }
+
+    /// Removes the track at `position`. The current track can not be removed.
+    pub fn remove(&mut self, position: u32) -> Option<Track> {
+        let track = self.queue.get(position as usize)?;
+
+        if track.status == TrackStatus::Playing {
+            return None;
+        }
+
+        Some(self.remove_at(position as usize))
+    }
+
+    fn remove_at(&mut self, position: usize) -> Track {
+        if self.shuffled {
+            self.unshuffled_order.retain(|&p| p != position);
+            for p in self.unshuffled_order.iter_mut().filter(|p| **p > position) {
+                *p -= 1;
+            }
+        }
+
+        self.queue.remove(position)
+    }
+
+    /// Moves the track at `from` to `to`, updating what has been played around the current track.
+    pub fn move_track(&mut self, from: u32, to: u32) -> Option<()> {
+        let (from, to) = (from as usize, to as usize);
+
+        if from >= self.queue.len() || to >= self.queue.len() {
+            return None;
+        }
+
+        let track = self.queue.remove(from);
+        self.queue.insert(to, track);
+
+        if self.shuffled {
+            for p in self.unshuffled_order.iter_mut() {
+                *p = match *p {
+                    p if p == from => to,
+                    p if from < p && p <= to => p - 1,
+                    p if to <= p && p < from => p + 1,
+                    p => p,
+                };
+            }
+        }
+
+        if let Some(position) = self
+            .queue
+            .iter()
+            .position(|t| t.status == TrackStatus::Playing)
+        {
+            self.set_current_position(position);
+        }
+
+        Some(())
+    }
+
+    /// Removes every track after the current one.
+    pub fn clear_upcoming(&mut self) {
+        let upcoming = self.current_position() as usize + 1;
+        self.queue.truncate(upcoming);
+
+        if self.shuffled {
+            self.unshuffled_order.retain(|&p| p < upcoming);
+        }
+    }
+
+    /// Removes the tracks after the current one that autoplay added.
+    pub fn clear_autoplay(&mut self) {
+        let upcoming = self.current_position() as usize + 1;
+
+        for position in (upcoming..self.queue.len()).rev() {
+            if self.queue[position].added_by_autoplay {
+                self.remove_at(position);
+            }
+        }
+    }
+
+    /// Makes the first playable track from `position` on the current one and returns it.
+    /// Past the end of the queue, every track counts as played and `None` is returned.
+    pub(crate) fn skip_to(&mut self, position: u32) -> Option<&Track> {
+        let position = (position as usize..self.queue.len())
+            .find(|&position| self.is_playable(position))
+            .unwrap_or(self.queue.len());
+
+        self.set_current_position(position);
+        self.queue.get(position)
+    }
+
+    /// Marks every track played and makes the first playable one current,
+    /// ready to play the queue again from the start.
+    pub(crate) fn rewind(&mut self) -> Option<&Track> {
+        self.set_current_position(self.total() as usize);
+
+        let track = self
+            .queue
+            .iter_mut()
+            .find(|t| t.status != TrackStatus::Unplayable)?;
+        track.status = TrackStatus::Playing;
+
+        Some(track)
+    }
+
+    /// Marks the current track as unplayable, leaving no track current.
+    pub(crate) fn mark_unplayable(&mut self) {
+        let Some(track) = self
+            .queue
+            .iter_mut()
+            .find(|t| t.status == TrackStatus::Playing)
+        else {
+            return;
+        };
+
+        track.status = TrackStatus::Unplayable;
+    }
+
+    fn set_current_position(&mut self, position: usize) {
+        set_statuses(&mut self.queue, position);
+    }
+
+    /// The changes that turn `previous` into this tracklist, numbered `version`.
+    fn diff(&self, previous: &Tracklist, version: u64) -> QueueDiff {
+        let same =
+            |a: &Track, b: &Track| a.id == b.id && a.added_by_autoplay == b.added_by_autoplay;
+
+        let start = self
+            .queue
+            .iter()
+            .zip(&previous.queue)
+            .take_while(|(a, b)| same(a, b))
+            .count();
+        let end = self.queue[start..]
+            .iter()
+            .rev()
+            .zip(previous.queue[start..].iter().rev())
+            .take_while(|(a, b)| same(a, b))
+            .count();
+
+        let inserted = self.queue[start..self.queue.len() - end].to_vec();
+        let removed = previous.queue.len() - end - start;
+        let shift = |position: usize| {
+            if position < start {
+                position
+            } else {
+                position + removed - inserted.len()
+            }
+        };
+
+        let statuses = self
+            .queue
+            .iter()
+            .enumerate()
+            .filter(|(position, _)| *position < start || *position >= start + inserted.len())
+            .filter(|(position, track)| previous.queue[shift(*position)].status != track.status)
+            .map(|(position, track)| (position as u32, track.status.clone()))
+            .collect();
+
+        QueueDiff {
+            version,
+            start: start as u32,
+            removed: removed as u32,
+            inserted,
+            statuses,
+            list_type: self.list_type.clone(),
+            repeat_mode: self.repeat_mode,
+            autoplay: self.autoplay,
+            shuffled: self.shuffled,
+        }
+    }
+
+    /// Applies the changes from a [`QueueDiff`] to a copy of the tracklist.
+    fn apply(&mut self, diff: QueueDiff) {
+        let start = diff.start as usize;
+        let end = (start + diff.removed as usize).min(self.queue.len());
+        self.queue.splice(start..end, diff.inserted);
+
+        for (position, status) in diff.statuses {
+            if let Some(track) = self.queue.get_mut(position as usize) {
+                track.status = status;
+            }
+        }
+
+        self.list_type = diff.list_type;
+        self.repeat_mode = diff.repeat_mode;
+        self.autoplay = diff.autoplay;
+        self.shuffled = diff.shuffled;
+    }
+
+    pub fn is_shuffled(&self) -> bool {
+        self.shuffled
+    }
+
+    /// Randomizes the tracks after the current one.
+    /// The original order is kept so it can be restored with `unshuffle`.
+    pub fn shuffle(&mut self) {
+        self.shuffled = true;
+
+        let upcoming = self.current_position() as usize + 1;
+        let mut order: Vec<usize> = (0..self.queue.len()).collect();
+        if upcoming < order.len() {
+            order[upcoming..].shuffle(&mut rand::thread_rng());
+        }
+
+        let mut unshuffled_order = vec![0; order.len()];
+        for (position, &original) in order.iter().enumerate() {
+            unshuffled_order[original] = position;
+        }
+
+        self.queue = order.iter().map(|&p| self.queue[p].clone()).collect();
+        self.unshuffled_order = unshuffled_order;
+    }
+
+    /// Restores the order from before `shuffle`, continuing from the current track.
+    pub fn unshuffle(&mut self) {
+        if !self.shuffled {
+            return;
+        }
+
+        self.shuffled = false;
+
+        let order = std::mem::take(&mut self.unshuffled_order);
+        // The queue was replaced without shuffling it again, there is no order to restore.
+        if order.len() != self.queue.len() {
+            return;
+        }
+
+        let current_position = self.current_track().and_then(|_| {
+            let current_position = self.current_position() as usize;
+            order.iter().position(|&p| p == current_position)
+        });
+        let mut queue: Vec<Track> = order.iter().map(|&p| self.queue[p].clone()).collect();
+
+        if let Some(position) = current_position {
+            set_statuses(&mut queue, position);
+        }
+
+        self.queue = queue;
+    }
+}
+
+/// A copy of a player's queue, kept up to date with the [`QueueDiff`]s it broadcasts.
+/// Start from `Player::tracklist_copy`, after subscribing to the player's notifications.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct TracklistCopy {
+    tracklist: Tracklist,
+    version: u64,
+}
+
+impl TracklistCopy {
+    pub fn tracklist(&self) -> &Tracklist {
+        &self.tracklist
+    }
+
+    /// Applies a diff, unless the copy already has it.
+    /// Returns `false` when diffs before it were missed, then the copy has to be taken again.
+    pub fn apply(&mut self, diff: QueueDiff) -> bool {
+        if diff.version <= self.version {
+            return true;
+        }
+
+        if diff.version != self.version + 1 {
+            return false;
+        }
+
+        self.version = diff.version;
+        self.tracklist.apply(diff);
+        true
+    }
+
+    /// Brings the copy up to date with `tracklist` and returns the diff that did it.
+    pub(crate) fn update(&mut self, tracklist: &Tracklist) -> QueueDiff {
+        let diff = tracklist.diff(&self.tracklist, self.version + 1);
+        self.tracklist = tracklist.clone();
+        self.version = diff.version;
+
+        diff
+    }
+}
+
+/// Marks tracks before `position` played and after it unplayed. Unplayable tracks stay unplayable.
+fn set_statuses(queue: &mut [Track], position: usize) {
+    for (index, track) in queue.iter_mut().enumerate() {
+        if track.status == TrackStatus::Unplayable {
+            continue;
+        }
+
+        track.status = match index.cmp(&position) {
+            std::cmp::Ordering::Less => TrackStatus::Played,
+            std::cmp::Ordering::Equal => TrackStatus::Playing,
+            std::cmp::Ordering::Greater => TrackStatus::Unplayed,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tracklist of the given track ids, playing the one at `current`.
+    fn tracklist(ids: &[u32], current: usize) -> Tracklist {
+        let mut tracklist = Tracklist::new();
+        tracklist.queue = ids
+            .iter()
+            .map(|&id| Track {
+                id,
+                ..Default::default()
+            })
+            .collect();
+        tracklist.set_current_position(current);
+
+        tracklist
+    }
+
+    fn ids(tracklist: &Tracklist) -> Vec<u32> {
+        tracklist.queue.iter().map(|track| track.id).collect()
+    }
+
+    #[test]
+    fn next_position_follows_the_repeat_mode() {
+        let mut tracklist = tracklist(&[1, 2, 3], 2);
+
+        tracklist.repeat_mode = RepeatMode::None;
+        assert_eq!(tracklist.next_position(), None);
+
+        tracklist.repeat_mode = RepeatMode::Track;
+        assert_eq!(tracklist.next_position(), Some(2));
+
+        tracklist.repeat_mode = RepeatMode::All;
+        assert_eq!(tracklist.next_position(), Some(0));
+    }
+
+    #[test]
+    fn skip_position_wraps_past_unplayable_tracks() {
+        let mut tracklist = tracklist(&[1, 2, 3], 1);
+        tracklist.queue[2].status = TrackStatus::Unplayable;
+
+        tracklist.repeat_mode = RepeatMode::All;
+        assert_eq!(tracklist.skip_position(), Some(0));
+
+        tracklist.repeat_mode = RepeatMode::None;
+        assert_eq!(tracklist.skip_position(), None);
+    }
+
+    #[test]
+    fn skip_position_moves_on_from_a_repeated_track() {
+        let mut tracklist = tracklist(&[1, 2, 3], 1);
+        tracklist.repeat_mode = RepeatMode::Track;
+
+        assert_eq!(tracklist.next_position(), Some(1));
+        assert_eq!(tracklist.skip_position(), Some(2));
+    }
+
+    #[test]
+    fn next_position_passes_over_unplayable_tracks() {
+        let mut tracklist = tracklist(&[1, 2, 3, 4], 1);
+        tracklist.queue[0].status = TrackStatus::Unplayable;
+        tracklist.queue[2].status = TrackStatus::Unplayable;
+
+        assert_eq!(tracklist.next_position(), Some(3));
+
+        tracklist.skip_to(3);
+        assert_eq!(tracklist.next_position(), None);
+
+        tracklist.repeat_mode = RepeatMode::All;
+        assert_eq!(tracklist.next_position(), Some(1));
+    }
+
+    #[test]
+    fn shuffle_keeps_the_played_tracks_in_place() {
+        let mut tracklist = tracklist(&[1, 2, 3, 4, 5, 6], 2);
+        tracklist.shuffle();
+
+        assert!(tracklist.is_shuffled());
+        assert_eq!(ids(&tracklist)[..3], [1, 2, 3]);
+        assert_eq!(tracklist.currently_playing(), Some(3));
+
+        let mut upcoming = ids(&tracklist)[3..].to_vec();
+        upcoming.sort_unstable();
+        assert_eq!(upcoming, vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn unshuffle_restores_the_order_from_the_current_track() {
+        let mut tracklist = tracklist(&[1, 2, 3, 4, 5, 6], 0);
+        tracklist.shuffle();
+        tracklist.set_current_position(1);
+        let current = tracklist.currently_playing();
+
+        tracklist.unshuffle();
+
+        assert!(!tracklist.is_shuffled());
+        assert_eq!(ids(&tracklist), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(tracklist.currently_playing(), current);
+        let position = tracklist.current_position() as usize;
+        assert!(tracklist.queue[..position]
+            .iter()
+            .all(|track| track.status == TrackStatus::Played));
+        assert!(tracklist.queue[position + 1..]
+            .iter()
+            .all(|track| track.status == TrackStatus::Unplayed));
+    }
+
+    #[test]
+    fn unshuffle_without_a_shuffled_order_keeps_the_queue() {
+        let mut tracklist = tracklist(&[1, 2, 3], 0);
+        tracklist.unshuffle();
+
+        assert_eq!(ids(&tracklist), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn unshuffle_after_removing_a_duplicate_keeps_the_other_copy() {
+        let mut tracklist = tracklist(&[1, 2, 3, 2, 4], 0);
+        tracklist.shuffle();
+
+        let second_copy = tracklist
+            .queue
+            .iter()
+            .rposition(|track| track.id == 2)
+            .unwrap();
+        tracklist.remove(second_copy as u32);
+        tracklist.unshuffle();
+
+        assert_eq!(ids(&tracklist).iter().filter(|&&id| id == 2).count(), 1);
+        assert_eq!(tracklist.total(), 4);
+        assert_eq!(tracklist.currently_playing(), Some(1));
+    }
+
+    #[test]
+    fn unshuffle_after_moving_keeps_the_original_order() {
+        let mut tracklist = tracklist(&[1, 2, 3, 2, 4], 0);
+        tracklist.shuffle();
+        tracklist.move_track(4, 1);
+        tracklist.move_track(1, 3);
+        tracklist.unshuffle();
+
+        assert_eq!(ids(&tracklist), vec![1, 2, 3, 2, 4]);
+    }
+
+    #[test]
+    fn unshuffle_after_queue_edits() {
+        let mut tracklist = tracklist(&[1, 2, 3], 0);
+        tracklist.shuffle();
+        tracklist.insert_next(vec![Track {
+            id: 5,
+            ..Default::default()
+        }]);
+        tracklist.append(vec![Track {
+            id: 6,
+            ..Default::default()
+        }]);
+        tracklist.unshuffle();
+
+        assert_eq!(ids(&tracklist), vec![1, 5, 2, 3, 6]);
+    }
+
+    #[test]
+    fn clear_upcoming_while_shuffled() {
+        let mut tracklist = tracklist(&[1, 2, 3, 4], 1);
+        tracklist.shuffle();
+        tracklist.clear_upcoming();
+        tracklist.unshuffle();
+
+        assert_eq!(ids(&tracklist), vec![1, 2]);
+        assert_eq!(tracklist.currently_playing(), Some(2));
+    }
+
+    #[test]
+    fn copy_follows_diffs() {
+        let mut list = tracklist(&[1, 2, 3, 4], 0);
+        let mut source = TracklistCopy::default();
+        let mut copy = TracklistCopy::default();
+        assert!(copy.apply(source.update(&list)));
+
+        list.remove(2);
+        list.append(vec![Track {
+            id: 5,
+            ..Default::default()
+        }]);
+        list.set_current_position(1);
+        list.repeat_mode = RepeatMode::All;
+        assert!(copy.apply(source.update(&list)));
+
+        assert_eq!(ids(copy.tracklist()), vec![1, 2, 4, 5]);
+        assert_eq!(copy.tracklist().currently_playing(), Some(2));
+        assert_eq!(copy.tracklist().repeat_mode, RepeatMode::All);
+    }
+
+    #[test]
+    fn copy_asks_for_a_resync_after_a_missed_diff() {
+        let mut list = tracklist(&[1, 2, 3], 0);
+        let mut source = TracklistCopy::default();
+        let first = source.update(&list);
+
+        list.set_current_position(1);
+        let second = source.update(&list);
+
+        let mut copy = TracklistCopy::default();
+        assert!(!copy.apply(second.clone()));
+
+        assert!(copy.apply(first.clone()));
+        assert!(copy.apply(second));
+        // A diff the copy already has is left out.
+        assert!(copy.apply(first));
+        assert_eq!(copy.tracklist().currently_playing(), Some(2));
+    }
 }
@@ -1,21 +1,34 @@
 use mpris_server::{
     zbus::{self, fdo},
     LoopStatus, Metadata, PlaybackRate, PlaybackStatus, PlayerInterface, Property, RootInterface,
-    Server, Time, TrackId, Volume,
+    Server, Signal, Time, TrackId, Volume,
 };
-use qobuz_player_controls::{models::Track, notification::Notification, ClockTime, State};
-
-struct MprisPlayer;
+use qobuz_player_controls::{
+    models::Track,
+    notification::Notification,
+    stream_format::StreamFormat,
+    tracklist::{RepeatMode, Tracklist},
+    ClockTime, Player, State,
+};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::RecvError;
+
+struct MprisPlayer {
+    player: Player,
+    identity: String,
+    /// The track from the last `TrackChanged`.
+    current_track: Arc<Mutex<Option<Track>>>,
+}
 
 impl RootInterface for MprisPlayer {
     async fn identity(&self) -> fdo::Result<String> {
-        Ok("Quboz-player".into())
+        Ok(self.identity.clone())
     }
     async fn raise(&self) -> fdo::Result<()> {
         Err(fdo::Error::NotSupported("Not supported".into()))
     }
     async fn quit(&self) -> fdo::Result<()> {
-        match qobuz_player_controls::quit().await {
+        match self.player.quit().await {
             Ok(_) => Ok(()),
             Err(err) => Err(fdo::Error::Failed(err.to_string())),
         }
@@ -51,42 +64,42 @@ impl RootInterface for MprisPlayer {
 
 impl PlayerInterface for MprisPlayer {
     async fn next(&self) -> fdo::Result<()> {
-        match qobuz_player_controls::next().await {
+        match self.player.next().await {
             Ok(()) => Ok(()),
             Err(err) => Err(fdo::Error::Failed(err.to_string())),
         }
     }
 
     async fn previous(&self) -> fdo::Result<()> {
-        match qobuz_player_controls::previous().await {
+        match self.player.previous().await {
             Ok(()) => Ok(()),
             Err(err) => Err(fdo::Error::Failed(err.to_string())),
         }
     }
 
     async fn pause(&self) -> fdo::Result<()> {
-        match qobuz_player_controls::pause().await {
+        match self.player.pause().await {
             Ok(()) => Ok(()),
             Err(err) => Err(fdo::Error::Failed(err.to_string())),
         }
     }
 
     async fn play_pause(&self) -> fdo::Result<()> {
-        match qobuz_player_controls::play_pause().await {
+        match self.player.play_pause().await {
             Ok(()) => Ok(()),
             Err(err) => Err(fdo::Error::Failed(err.to_string())),
         }
     }
 
     async fn stop(&self) -> fdo::Result<()> {
-        match qobuz_player_controls::stop().await {
+        match self.player.stop().await {
             Ok(()) => Ok(()),
             Err(err) => Err(fdo::Error::Failed(err.to_string())),
         }
     }
 
     async fn play(&self) -> fdo::Result<()> {
-        match qobuz_player_controls::play().await {
+        match self.player.play().await {
             Ok(()) => Ok(()),
             Err(err) => Err(fdo::Error::Failed(err.to_string())),
         }
@@ -95,7 +108,7 @@ impl PlayerInterface for MprisPlayer {
     async fn seek(&self, offset: Time) -> fdo::Result<()> {
         let clock = ClockTime::from_seconds(offset.as_secs() as u64);
 
-        match qobuz_player_controls::seek(clock, None).await {
+        match self.player.seek(clock, None).await {
             Ok(()) => Ok(()),
             Err(err) => Err(fdo::Error::Failed(err.to_string())),
         }
@@ -110,7 +123,7 @@ impl PlayerInterface for MprisPlayer {
     }
 
     async fn playback_status(&self) -> fdo::Result<PlaybackStatus> {
-        let current_status = qobuz_player_controls::current_state();
+        let current_status = self.player.current_state();
 
         let status = match current_status {
             State::VoidPending => PlaybackStatus::Stopped,
@@ -124,11 +137,20 @@ impl PlayerInterface for MprisPlayer {
     }
 
     async fn loop_status(&self) -> fdo::Result<LoopStatus> {
-        Err(fdo::Error::NotSupported("Not supported".into()))
+        Ok(repeat_mode_to_loop_status(self.player.repeat_mode().await))
     }
 
-    async fn set_loop_status(&self, _loop_status: LoopStatus) -> zbus::Result<()> {
-        Err(zbus::Error::Unsupported)
+    async fn set_loop_status(&self, loop_status: LoopStatus) -> zbus::Result<()> {
+        let repeat_mode = match loop_status {
+            LoopStatus::None => RepeatMode::None,
+            LoopStatus::Track => RepeatMode::Track,
+            LoopStatus::Playlist => RepeatMode::All,
+        };
+
+        match self.player.set_repeat_mode(repeat_mode).await {
+            Ok(()) => Ok(()),
+            Err(err) => Err(zbus::Error::Failure(err.to_string())),
+        }
     }
 
     async fn rate(&self) -> fdo::Result<PlaybackRate> {
@@ -140,31 +162,36 @@ impl PlayerInterface for MprisPlayer {
     }
 
     async fn shuffle(&self) -> fdo::Result<bool> {
-        Ok(false)
+        Ok(self.player.shuffle().await)
     }
 
-    async fn set_shuffle(&self, _shuffle: bool) -> zbus::Result<()> {
-        Err(zbus::Error::Unsupported)
+    async fn set_shuffle(&self, shuffle: bool) -> zbus::Result<()> {
+        match self.player.set_shuffle(shuffle).await {
+            Ok(()) => Ok(()),
+            Err(err) => Err(zbus::Error::Failure(err.to_string())),
+        }
     }
 
     async fn metadata(&self) -> fdo::Result<Metadata> {
-        match qobuz_player_controls::current_track().await {
-            Ok(current_track) => Ok(track_to_metadata(current_track)),
-            Err(_) => Ok(Metadata::new()),
-        }
+        let stream_format = self.player.stream_format().await;
+        let track = self.current_track.lock().unwrap().clone();
+
+        Ok(track_to_metadata(track.as_ref(), stream_format))
     }
 
     async fn volume(&self) -> fdo::Result<Volume> {
-        Ok(qobuz_player_controls::volume())
+        Ok(self.player.volume())
     }
 
     async fn set_volume(&self, volume: Volume) -> zbus::Result<()> {
-        qobuz_player_controls::set_volume(volume);
+        self.player.set_volume(volume);
         Ok(())
     }
 
     async fn position(&self) -> fdo::Result<Time> {
-        let position_mseconds = qobuz_player_controls::position()
+        let position_mseconds = self
+            .player
+            .position()
             .map(|position| position.mseconds())
             .map_or(0, |p| p as i64);
         let time = Time::from_micros(position_mseconds);
@@ -205,94 +232,186 @@ impl PlayerInterface for MprisPlayer {
     }
 }
 
-pub async fn init() {
-    let server = Server::new("com.github.sofusa-quboz-player", MprisPlayer)
-        .await
-        .unwrap();
+/// Exposes every zone as its own MPRIS player. A single zone keeps the plain bus name.
+pub async fn init(players: Vec<Player>) {
+    let named_zones = players.len() > 1;
 
-    let mut receiver = qobuz_player_controls::notify_receiver();
+    futures::future::join_all(
+        players
+            .into_iter()
+            .map(|player| serve_zone(player, named_zones)),
+    )
+    .await;
+}
+
+async fn serve_zone(player: Player, named_zones: bool) {
+    let mut receiver = player.notify_receiver();
+
+    let (bus_name, identity) = if named_zones {
+        (
+            format!(
+                "com.github.sofusa-quboz-player.{}",
+                player.zone().replace('-', "_")
+            ),
+            format!("Quboz-player ({})", player.zone()),
+        )
+    } else {
+        (
+            "com.github.sofusa-quboz-player".to_string(),
+            "Quboz-player".to_string(),
+        )
+    };
+
+    let mut tracklist = player.tracklist_copy().await;
+    let current_track = Arc::new(Mutex::new(player.current_track().await.ok().flatten()));
+
+    let server = Server::new(
+        &bus_name,
+        MprisPlayer {
+            player: player.clone(),
+            identity,
+            current_track: current_track.clone(),
+        },
+    )
+    .await
+    .unwrap();
 
     loop {
-        if let Ok(notification) = receiver.recv().await {
-            match notification {
-                Notification::Quit => return,
-                Notification::Status { status } => {
-                    let (can_play, can_pause) = match status {
-                        State::VoidPending => (false, false),
-                        State::Null => (false, false),
-                        State::Ready => (false, false),
-                        State::Paused => (true, true),
-                        State::Playing => (true, true),
-                    };
-
-                    server
-                        .properties_changed([
-                            Property::CanPlay(can_play),
-                            Property::CanPause(can_pause),
-                        ])
-                        .await
-                        .unwrap();
-                }
-                Notification::Position { clock: _ } => {}
-                Notification::CurrentTrackList { list } => {
-                    let current_track = qobuz_player_controls::current_track().await.unwrap();
-                    let metadata = track_to_metadata(current_track);
-
-                    let current_position = list.current_position();
-                    let total_tracks = list.total();
-
-                    let can_previous = current_position != 0;
-                    let can_next = !(total_tracks != 0 && current_position == total_tracks - 1);
-
-                    server
-                        .properties_changed([
-                            Property::Metadata(metadata),
-                            Property::CanGoPrevious(can_previous),
-                            Property::CanGoNext(can_next),
-                        ])
-                        .await
-                        .unwrap();
-                }
-                Notification::Error { error: _ } => {}
-                Notification::Volume { volume } => {
-                    server
-                        .properties_changed([Property::Volume(volume)])
-                        .await
-                        .unwrap();
+        let notification = match receiver.recv().await {
+            Ok(notification) => notification,
+            Err(RecvError::Lagged(_)) => {
+                tracklist = player.tracklist_copy().await;
+                queue_properties_changed(&server, tracklist.tracklist()).await;
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        match notification {
+            Notification::Quit => return,
+            Notification::Status { status } => {
+                let (can_play, can_pause) = match status {
+                    State::VoidPending => (false, false),
+                    State::Null => (false, false),
+                    State::Ready => (false, false),
+                    State::Paused => (true, true),
+                    State::Playing => (true, true),
+                };
+
+                server
+                    .properties_changed([
+                        Property::CanPlay(can_play),
+                        Property::CanPause(can_pause),
+                    ])
+                    .await
+                    .unwrap();
+            }
+            Notification::Position { clock: _ } => {}
+            Notification::Seeked { position } => {
+                server
+                    .emit(Signal::Seeked {
+                        position: Time::from_micros(position.useconds() as i64),
+                    })
+                    .await
+                    .unwrap();
+            }
+            Notification::TrackChanged { track } => {
+                let metadata = track_to_metadata(track.as_ref(), player.stream_format().await);
+                *current_track.lock().unwrap() = track;
+
+                server
+                    .properties_changed([Property::Metadata(metadata)])
+                    .await
+                    .unwrap();
+            }
+            Notification::QueueChanged { diff } => {
+                if !tracklist.apply(diff) {
+                    tracklist = player.tracklist_copy().await;
                 }
+
+                queue_properties_changed(&server, tracklist.tracklist()).await;
+            }
+            Notification::Buffering { percent: _ } => {}
+            Notification::Error { error: _ } => {}
+            Notification::PlaybackError {
+                track_id: _,
+                error: _,
+            } => {}
+            Notification::Volume { volume } => {
+                server
+                    .properties_changed([Property::Volume(volume)])
+                    .await
+                    .unwrap();
+            }
+            Notification::PlaylistChanged { id: _ } => {}
+            Notification::ReplayGain { replay_gain: _ } => {}
+            Notification::Output { output: _ } => {}
+            Notification::BitPerfect { bit_perfect: _ } => {}
+            Notification::Equalizer { equalizer: _ } => {}
+            Notification::Fades { fades: _ } => {}
+            Notification::SleepTimer { timer: _ } => {}
+            Notification::StreamFormat { format } => {
+                let track = current_track.lock().unwrap().clone();
+                let metadata = track_to_metadata(track.as_ref(), Some(format));
+
+                server
+                    .properties_changed([Property::Metadata(metadata)])
+                    .await
+                    .unwrap();
             }
         }
     }
 }
 
-fn track_to_metadata(track: Option<Track>) -> Metadata {
+async fn queue_properties_changed(server: &Server<MprisPlayer>, list: &Tracklist) {
+    let current_position = list.current_position();
+    let total_tracks = list.total();
+
+    let can_previous = current_position != 0;
+    let can_next = list.repeat_mode == RepeatMode::All
+        || !(total_tracks != 0 && current_position == total_tracks - 1);
+
+    server
+        .properties_changed([
+            Property::CanGoPrevious(can_previous),
+            Property::CanGoNext(can_next),
+            Property::Shuffle(list.is_shuffled()),
+            Property::LoopStatus(repeat_mode_to_loop_status(list.repeat_mode)),
+        ])
+        .await
+        .unwrap();
+}
+
+fn track_to_metadata(track: Option<&Track>, stream_format: Option<StreamFormat>) -> Metadata {
     let mut metadata = Metadata::new();
-    let duration = track
-        .as_ref()
-        .map(|ct| mpris_server::Time::from_secs(ct.duration_seconds as i64));
+    let duration = track.map(|ct| mpris_server::Time::from_secs(ct.duration_seconds as i64));
     metadata.set_length(duration);
 
     // album
-    let (album_title, album_image) = track.as_ref().map_or((None, None), |ct| {
-        ct.album.as_ref().map_or((None, None), |a| {
-            (Some(a.title.clone()), Some(a.image.clone()))
-        })
-    });
-
-    metadata.set_album(album_title);
-    metadata.set_art_url(album_image);
+    metadata.set_album(track.and_then(|ct| ct.album.as_ref().map(|album| album.title.clone())));
+    metadata.set_art_url(track.and_then(|ct| ct.cover_art.clone()));
 
     // artist
-    let artist_name = track
-        .as_ref()
-        .and_then(|ct| ct.artist.as_ref().map(|a| a.name.clone()));
+    let artist_name = track.and_then(|ct| ct.artist.as_ref().map(|artist| artist.name.clone()));
 
     metadata.set_artist(artist_name.as_ref().map(|a| vec![a]));
     metadata.set_album_artist(artist_name.as_ref().map(|a| vec![a]));
 
     // track
-    metadata.set_title(track.as_ref().map(|ct| ct.title.clone()));
+    metadata.set_title(track.map(|ct| ct.title.clone()));
     metadata.set_track_number(track.map(|ct| ct.number as i32));
+    metadata.set_disc_number(track.map(|ct| ct.disc_number as i32));
+
+    // MPRIS has no field for the audio format, so it goes in the comment.
+    metadata.set_comment(stream_format.map(|format| vec![format.to_string()]));
 
     metadata
 }
+
+fn repeat_mode_to_loop_status(repeat_mode: RepeatMode) -> LoopStatus {
+    match repeat_mode {
+        RepeatMode::None => LoopStatus::None,
+        RepeatMode::Track => LoopStatus::Track,
+        RepeatMode::All => LoopStatus::Playlist,
+    }
+}
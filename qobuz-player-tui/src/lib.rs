@@ -1,5 +1,9 @@
-use std::sync::{Arc, OnceLock};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex, OnceLock,
+};
 
+use chrono::Local;
 use cursive::{
     align::HAlign,
     direction::Orientation,
@@ -15,11 +19,17 @@ use cursive::{
 };
 use futures::executor::block_on;
 use qobuz_player_controls::{
+    error::Error,
+    history::{Count, Period, Play},
     models::{Album, Artist, Favorites, Playlist, SearchResults, Track, TrackStatus},
     notification::Notification,
-    tracklist::{self, TrackListType},
-    ClockTime, State,
+    offline::OfflineCollection,
+    output::{self, Output},
+    sleep_timer::SleepTimer,
+    tracklist::{self, RepeatMode, TrackListType, Tracklist, TracklistCopy},
+    ClockTime, Player, State,
 };
+use tokio::sync::broadcast::{error::RecvError, Receiver};
 use tracing::debug;
 
 type CursiveSender = Sender<Box<dyn FnOnce(&mut Cursive) + Send>>;
@@ -28,11 +38,63 @@ static SINK: OnceLock<CursiveSender> = OnceLock::new();
 
 static UNSTREAMABLE: &str = "UNSTREAMABLE";
 
-pub async fn init() {
+/// The zones the TUI controls and which one is selected.
+#[derive(Clone)]
+struct Zones {
+    players: Arc<Vec<Player>>,
+    selected: Arc<AtomicUsize>,
+    /// The queue and current track of each zone, kept up to date from its notifications.
+    states: Arc<Vec<Mutex<ZoneState>>>,
+}
+
+struct ZoneState {
+    tracklist: TracklistCopy,
+    current_track: Option<Track>,
+}
+
+impl Zones {
+    fn selected(&self) -> usize {
+        self.selected.load(Ordering::Relaxed)
+    }
+
+    fn current(&self) -> Player {
+        self.players[self.selected()].clone()
+    }
+
+    fn title(&self) -> String {
+        if self.players.len() > 1 {
+            format!("player: {}", self.current().zone())
+        } else {
+            "player".to_string()
+        }
+    }
+}
+
+pub async fn init(players: Vec<Player>) {
     let mut siv = cursive::default();
 
+    // Subscribed before copying the queues, so no change falls in between.
+    let receivers: Vec<_> = players.iter().map(Player::notify_receiver).collect();
+
+    let mut states = vec![];
+    for player in &players {
+        states.push(Mutex::new(ZoneState {
+            tracklist: player.tracklist_copy().await,
+            current_track: player.current_track().await.ok().flatten(),
+        }));
+    }
+
+    let zones = Zones {
+        players: Arc::new(players),
+        selected: Arc::new(AtomicUsize::new(0)),
+        states: Arc::new(states),
+    };
+
     SINK.set(siv.cb_sink().clone()).expect("error setting sink");
-    tokio::spawn(async { receive_notifications().await });
+
+    for (zone, receiver) in receivers.into_iter().enumerate() {
+        tokio::spawn(receive_notifications(zones.clone(), zone, receiver));
+    }
 
     siv.set_theme(cursive::theme::Theme {
         shadow: false,
@@ -67,27 +129,47 @@ pub async fn init() {
         }),
     });
 
-    let player = player();
-    let search = search();
-
-    let favorites = qobuz_player_controls::favorites().await;
-
-    let Favorites {
-        albums,
-        artists,
-        playlists,
-    } = favorites.unwrap();
-
-    let favorite_albums = favorite_albums(albums);
-    let favorite_artists = favorite_artists(artists);
-    let favorite_playlists = favorite_playlists(playlists);
+    let now_playing = now_playing(zones.clone());
+    let search = search(zones.clone());
+    let local_albums = local_albums(zones.clone());
+
+    let favorites = zones.current().favorites().await;
+
+    // Without Qobuz only what is kept offline can be played.
+    let (favorite_albums, favorite_artists, favorite_playlists) =
+        if let Err(Error::Offline) = favorites {
+            let (albums, playlists): (Vec<_>, Vec<_>) = zones
+                .current()
+                .offline_collections()
+                .await
+                .into_iter()
+                .partition(|collection| matches!(collection.list_type, TrackListType::Album(_)));
+
+            (
+                offline_collections(albums, "albums", zones.clone()),
+                favorite_artists(vec![], zones.clone()),
+                offline_collections(playlists, "playlists", zones.clone()),
+            )
+        } else {
+            let Favorites {
+                albums,
+                artists,
+                playlists,
+            } = favorites.unwrap();
+
+            (
+                favorite_albums(albums, zones.clone()),
+                favorite_artists(artists, zones.clone()),
+                favorite_playlists(playlists, zones.clone()),
+            )
+        };
 
     siv.screen_mut().add_fullscreen_layer(PaddedView::lrtb(
         0,
         0,
         1,
         0,
-        player.resized(SizeConstraint::Full, SizeConstraint::Free),
+        now_playing.resized(SizeConstraint::Full, SizeConstraint::Free),
     ));
 
     siv.add_active_screen();
@@ -126,14 +208,23 @@ pub async fn init() {
         search.resized(SizeConstraint::Full, SizeConstraint::Free),
     ));
 
+    siv.add_active_screen();
+    siv.screen_mut().add_fullscreen_layer(PaddedView::lrtb(
+        0,
+        0,
+        1,
+        0,
+        local_albums.resized(SizeConstraint::Full, SizeConstraint::Free),
+    ));
+
     siv.set_screen(0);
 
-    global_events(&mut siv);
-    menubar(&mut siv);
+    menubar(&mut siv, &zones);
+    global_events(&mut siv, zones);
     siv.run();
 }
 
-fn player() -> LinearLayout {
+fn now_playing(zones: Zones) -> LinearLayout {
     let mut container = LinearLayout::new(Orientation::Vertical);
     let mut track_info = LinearLayout::new(Orientation::Horizontal);
 
@@ -182,7 +273,22 @@ fn player() -> LinearLayout {
                 .h_align(HAlign::Center)
                 .with_name("player_status"),
         )
-        .fixed_width(8);
+        .child(
+            TextView::new("")
+                .h_align(HAlign::Center)
+                .with_name("playback_modes"),
+        )
+        .child(
+            TextView::new("")
+                .h_align(HAlign::Center)
+                .with_name("stream_format"),
+        )
+        .child(
+            TextView::new("")
+                .h_align(HAlign::Center)
+                .with_name("sleep_timer"),
+        )
+        .fixed_width(12);
 
     let counter = Counter::new(0);
     let progress = ProgressBar::new()
@@ -206,16 +312,16 @@ fn player() -> LinearLayout {
 
     let mut track_list: SelectView<usize> = SelectView::new();
 
+    let title = zones.title();
+
     track_list.set_on_submit(move |_s, item| {
         let i = item.to_owned();
-        tokio::spawn(async move { qobuz_player_controls::skip_to_position(i as u32, true).await });
+        let player = zones.current();
+        tokio::spawn(async move { player.skip_to_position(i as u32, true).await });
     });
 
-    let mut layout = LinearLayout::new(Orientation::Vertical).child(
-        Panel::new(container)
-            .title("player")
-            .with_name("player_panel"),
-    );
+    let mut layout = LinearLayout::new(Orientation::Vertical)
+        .child(Panel::new(container).title(title).with_name("player_panel"));
 
     layout.add_child(Panel::new(
         HideableView::new(
@@ -231,7 +337,7 @@ fn player() -> LinearLayout {
     layout
 }
 
-fn global_events(s: &mut Cursive) {
+fn global_events(s: &mut Cursive, zones: Zones) {
     s.set_on_pre_event('q', move |s| {
         let dialog = Dialog::text("Do you want to quit?")
             .button("Yes", move |s: &mut Cursive| {
@@ -262,28 +368,82 @@ fn global_events(s: &mut Cursive) {
         s.set_screen(4);
     });
 
+    let z = zones.clone();
+    s.add_global_callback('6', move |s| {
+        show_local_albums(s, &z);
+    });
+
+    let z = zones.clone();
     s.add_global_callback(' ', move |_| {
-        block_on(async { qobuz_player_controls::play_pause().await.expect("") });
+        block_on(async { z.current().play_pause().await.expect("") });
     });
 
+    let z = zones.clone();
     s.add_global_callback('n', move |_| {
-        block_on(async { qobuz_player_controls::next().await.expect("") });
+        block_on(async { z.current().next().await.expect("") });
     });
 
+    let z = zones.clone();
     s.add_global_callback('p', move |_| {
-        block_on(async { qobuz_player_controls::previous().await.expect("") });
+        block_on(async { z.current().previous().await.expect("") });
     });
 
+    let z = zones.clone();
     s.add_global_callback('l', move |_| {
-        block_on(async { qobuz_player_controls::jump_forward().await.expect("") });
+        block_on(async { z.current().jump_forward().await.expect("") });
     });
 
+    let z = zones.clone();
     s.add_global_callback('h', move |_| {
-        block_on(async { qobuz_player_controls::jump_backward().await.expect("") });
+        block_on(async { z.current().jump_backward().await.expect("") });
+    });
+
+    let z = zones.clone();
+    s.add_global_callback('s', move |_| {
+        block_on(async { z.current().toggle_shuffle().await.expect("") });
+    });
+
+    let z = zones.clone();
+    s.add_global_callback('r', move |_| {
+        block_on(async { z.current().cycle_repeat_mode().await.expect("") });
+    });
+
+    let z = zones.clone();
+    s.add_global_callback('a', move |_| {
+        block_on(async { z.current().toggle_autoplay().await.expect("") });
+    });
+
+    let z = zones.clone();
+    s.add_global_callback('o', move |s| {
+        select_output(s, &z);
+    });
+
+    let z = zones.clone();
+    s.add_global_callback('b', move |s| {
+        toggle_bit_perfect(s, &z);
+    });
+
+    let z = zones.clone();
+    s.add_global_callback('t', move |s| {
+        select_sleep_timer(s, &z);
+    });
+
+    let z = zones.clone();
+    s.add_global_callback('y', move |s| {
+        show_history(s, &z);
+    });
+
+    let z = zones.clone();
+    s.add_global_callback('x', move |_| {
+        block_on(async { z.current().toggle_stop_after_current().await.expect("") });
+    });
+
+    s.add_global_callback('z', move |_| {
+        select_zone(&zones, (zones.selected() + 1) % zones.players.len());
     });
 }
 
-fn menubar(s: &mut Cursive) {
+fn menubar(s: &mut Cursive, zones: &Zones) {
     s.set_autohide_menu(false);
 
     s.menubar()
@@ -308,6 +468,62 @@ fn menubar(s: &mut Cursive) {
         })
         .add_delimiter();
 
+    let local_zones = zones.clone();
+    s.menubar()
+        .add_leaf("Local [6]", move |s| {
+            show_local_albums(s, &local_zones);
+        })
+        .add_delimiter();
+
+    let output_zones = zones.clone();
+    let bit_perfect_zones = zones.clone();
+    let sleep_timer_zones = zones.clone();
+    let stop_after_current_zones = zones.clone();
+    let autoplay_zones = zones.clone();
+    let clear_autoplay_zones = zones.clone();
+    let history_zones = zones.clone();
+    s.menubar()
+        .add_leaf("Output [o]", move |s| {
+            select_output(s, &output_zones);
+        })
+        .add_delimiter()
+        .add_leaf("Bit-perfect [b]", move |s| {
+            toggle_bit_perfect(s, &bit_perfect_zones);
+        })
+        .add_delimiter()
+        .add_leaf("Sleep timer [t]", move |s| {
+            select_sleep_timer(s, &sleep_timer_zones);
+        })
+        .add_delimiter()
+        .add_leaf("Stop after track [x]", move |_| {
+            let player = stop_after_current_zones.current();
+            block_on(async { player.toggle_stop_after_current().await.expect("") });
+        })
+        .add_delimiter()
+        .add_leaf("Autoplay [a]", move |_| {
+            let player = autoplay_zones.current();
+            block_on(async { player.toggle_autoplay().await.expect("") });
+        })
+        .add_delimiter()
+        .add_leaf("Clear autoplay", move |_| {
+            let player = clear_autoplay_zones.current();
+            block_on(async { player.clear_autoplay().await.expect("") });
+        })
+        .add_delimiter()
+        .add_leaf("History [y]", move |s| {
+            show_history(s, &history_zones);
+        })
+        .add_delimiter();
+
+    if zones.players.len() > 1 {
+        let zones = zones.clone();
+        s.menubar()
+            .add_leaf("Next zone [z]", move |_| {
+                select_zone(&zones, (zones.selected() + 1) % zones.players.len());
+            })
+            .add_delimiter();
+    }
+
     s.add_global_callback('1', move |s| {
         s.set_screen(0);
     });
@@ -319,7 +535,7 @@ fn menubar(s: &mut Cursive) {
     });
 }
 
-fn favorite_albums(favorite_albums: Vec<Album>) -> LinearLayout {
+fn favorite_albums(favorite_albums: Vec<Album>, zones: Zones) -> LinearLayout {
     let mut list_layout = LinearLayout::new(Orientation::Vertical);
 
     let mut album_list = SelectView::new();
@@ -329,7 +545,8 @@ fn favorite_albums(favorite_albums: Vec<Album>) -> LinearLayout {
 
     album_list.set_on_submit(move |_s: &mut Cursive, item: &String| {
         let item = item.clone();
-        tokio::spawn(async move { qobuz_player_controls::play_album(&item, 0).await });
+        let player = zones.current();
+        tokio::spawn(async move { player.play_album(&item, 0).await });
     });
 
     list_layout.add_child(
@@ -346,7 +563,7 @@ fn favorite_albums(favorite_albums: Vec<Album>) -> LinearLayout {
     list_layout
 }
 
-fn favorite_artists(favorite_artists: Vec<Artist>) -> LinearLayout {
+fn favorite_artists(favorite_artists: Vec<Artist>, zones: Zones) -> LinearLayout {
     let mut list_layout = LinearLayout::new(Orientation::Vertical);
 
     let mut artist_list = SelectView::new();
@@ -355,7 +572,7 @@ fn favorite_artists(favorite_artists: Vec<Artist>) -> LinearLayout {
     });
 
     artist_list.set_on_submit(move |s: &mut Cursive, item: &u32| {
-        submit_artist(s, *item);
+        submit_artist(s, *item, &zones);
     });
 
     list_layout.add_child(
@@ -372,7 +589,7 @@ fn favorite_artists(favorite_artists: Vec<Artist>) -> LinearLayout {
     list_layout
 }
 
-fn favorite_playlists(favorite_playlists: Vec<Playlist>) -> LinearLayout {
+fn favorite_playlists(favorite_playlists: Vec<Playlist>, zones: Zones) -> LinearLayout {
     let mut list_layout = LinearLayout::new(Orientation::Vertical);
 
     let mut playlist_list = SelectView::new();
@@ -382,7 +599,8 @@ fn favorite_playlists(favorite_playlists: Vec<Playlist>) -> LinearLayout {
 
     playlist_list.set_on_submit(move |_s: &mut Cursive, item: &u32| {
         let item = *item;
-        tokio::spawn(async move { qobuz_player_controls::play_playlist(item as i64, 0).await });
+        let player = zones.current();
+        tokio::spawn(async move { player.play_playlist(item as i64, 0).await });
     });
 
     list_layout.add_child(
@@ -399,11 +617,89 @@ fn favorite_playlists(favorite_playlists: Vec<Playlist>) -> LinearLayout {
     list_layout
 }
 
-fn search() -> LinearLayout {
+/// Albums or playlists kept offline, in place of the favorites while Qobuz can't be reached.
+fn offline_collections(
+    collections: Vec<OfflineCollection>,
+    name: &str,
+    zones: Zones,
+) -> LinearLayout {
+    let mut list_layout = LinearLayout::new(Orientation::Vertical);
+
+    let mut collection_list = SelectView::new();
+    collections.into_iter().for_each(|collection| {
+        let title = match &collection.list_type {
+            TrackListType::Album(album) => album.title.clone(),
+            TrackListType::Playlist(playlist) => playlist.title.clone(),
+            _ => return,
+        };
+
+        collection_list.add_item(title, collection.list_type);
+    });
+
+    collection_list.set_on_submit(move |_s: &mut Cursive, item: &TrackListType| {
+        let item = item.clone();
+        let player = zones.current();
+        tokio::spawn(async move { player.play_offline(&item, 0).await });
+    });
+
+    list_layout.add_child(
+        Panel::new(
+            collection_list
+                .scrollable()
+                .scroll_y(true)
+                .resized(SizeConstraint::Full, SizeConstraint::Free),
+        )
+        .title(format!("{name} (offline)"))
+        .with_name(name),
+    );
+
+    list_layout
+}
+
+fn local_albums(zones: Zones) -> LinearLayout {
+    let mut list_layout = LinearLayout::new(Orientation::Vertical);
+
+    let mut album_list = SelectView::new();
+    album_list.set_on_submit(move |_s: &mut Cursive, item: &String| {
+        let item = item.clone();
+        let player = zones.current();
+        tokio::spawn(async move { player.play_album(&item, 0).await });
+    });
+
+    list_layout.add_child(
+        Panel::new(
+            album_list
+                .with_name("local_albums")
+                .scrollable()
+                .scroll_y(true)
+                .resized(SizeConstraint::Full, SizeConstraint::Free),
+        )
+        .title("local albums"),
+    );
+
+    list_layout
+}
+
+/// Lists the local albums again before showing them, as the library is read in the background.
+fn show_local_albums(s: &mut Cursive, zones: &Zones) {
+    let albums = block_on(zones.current().local_albums());
+
+    s.call_on_name("local_albums", |view: &mut SelectView<String>| {
+        view.clear();
+        for album in albums {
+            view.add_item(format!("{} - {}", album.artist.name, album.title), album.id);
+        }
+    });
+
+    s.set_screen(5);
+}
+
+fn search(zones: Zones) -> LinearLayout {
     let mut layout = LinearLayout::new(Orientation::Vertical);
 
+    let z = zones.clone();
     let on_submit = move |s: &mut Cursive, item: &String| {
-        load_search_results(item, s);
+        load_search_results(item, s, &z);
     };
 
     let search_type = SelectView::new()
@@ -418,9 +714,10 @@ fn search() -> LinearLayout {
     let search_form = EditView::new()
         .on_submit_mut(move |_, item| {
             let item = item.to_string();
+            let zones = zones.clone();
 
             tokio::spawn(async move {
-                let results = qobuz_player_controls::search(&item).await;
+                let results = zones.current().search(&item).await;
 
                 SINK.get()
                     .unwrap()
@@ -429,7 +726,7 @@ fn search() -> LinearLayout {
 
                         if let Some(view) = s.find_name::<SelectView>("search_type") {
                             if let Some(value) = view.selection() {
-                                load_search_results(&value, s);
+                                load_search_results(&value, s, &zones);
                             }
                         }
                     }))
@@ -458,7 +755,7 @@ fn search() -> LinearLayout {
     layout
 }
 
-fn load_search_results(item: &str, s: &mut Cursive) {
+fn load_search_results(item: &str, s: &mut Cursive, zones: &Zones) {
     if let Some(mut search_results) = s.find_name::<SelectView>("search_results") {
         search_results.clear();
 
@@ -475,12 +772,12 @@ fn load_search_results(item: &str, s: &mut Cursive) {
                         search_results.add_item(a.list_item(), id);
                     }
 
+                    let zones = zones.clone();
                     search_results.set_on_submit(move |_s: &mut Cursive, item: &String| {
                         if item != UNSTREAMABLE {
                             let item = item.clone();
-                            tokio::spawn(async move {
-                                qobuz_player_controls::play_album(&item, 0).await
-                            });
+                            let player = zones.current();
+                            tokio::spawn(async move { player.play_album(&item, 0).await });
                         }
                     });
                 }
@@ -489,8 +786,13 @@ fn load_search_results(item: &str, s: &mut Cursive) {
                         search_results.add_item(a.name.clone(), a.id.to_string());
                     }
 
+                    let zones = zones.clone();
                     search_results.set_on_submit(move |s: &mut Cursive, item: &String| {
-                        submit_artist(s, item.parse::<u32>().expect("failed to parse string"));
+                        submit_artist(
+                            s,
+                            item.parse::<u32>().expect("failed to parse string"),
+                            &zones,
+                        );
                     });
                 }
                 "Playlists" => {
@@ -498,11 +800,11 @@ fn load_search_results(item: &str, s: &mut Cursive) {
                         search_results.add_item(p.title.clone(), p.id.to_string())
                     }
 
+                    let zones = zones.clone();
                     search_results.set_on_submit(move |_s: &mut Cursive, item: &String| {
                         let item = item.parse::<i64>().expect("failed to parse string");
-                        tokio::spawn(
-                            async move { qobuz_player_controls::play_playlist(item, 0).await },
-                        );
+                        let player = zones.current();
+                        tokio::spawn(async move { player.play_playlist(item, 0).await });
                     });
                 }
                 _ => {}
@@ -511,21 +813,36 @@ fn load_search_results(item: &str, s: &mut Cursive) {
     }
 }
 
-fn submit_artist(s: &mut Cursive, item: u32) {
-    let artist_albums =
-        block_on(async { qobuz_player_controls::artist_albums(item).await }).unwrap();
+fn submit_artist(s: &mut Cursive, item: u32, zones: &Zones) {
+    let artist_albums = block_on(async { zones.current().artist_albums(item).await }).unwrap();
 
     if !artist_albums.is_empty() {
         let mut tree = cursive::menu::Tree::new();
 
+        let radio_zones = zones.clone();
+        tree.add_leaf("Start radio", move |s: &mut Cursive| {
+            let player = radio_zones.current();
+            tokio::spawn(async move { player.play_artist_radio(item).await });
+
+            s.call_on_name(
+                "screens",
+                |screens: &mut ScreensView<ResizedView<LinearLayout>>| {
+                    screens.set_active_screen(0);
+                },
+            );
+        });
+        tree.add_delimiter();
+
         for a in artist_albums {
             if !a.available {
                 continue;
             }
 
+            let zones = zones.clone();
             tree.add_leaf(a.list_item(), move |s: &mut Cursive| {
                 let id = a.id.clone();
-                tokio::spawn(async move { qobuz_player_controls::play_album(&id, 0).await });
+                let player = zones.current();
+                tokio::spawn(async move { player.play_album(&id, 0).await });
 
                 s.call_on_name(
                     "screens",
@@ -546,6 +863,198 @@ fn submit_artist(s: &mut Cursive, item: u32) {
     }
 }
 
+fn select_output(s: &mut Cursive, zones: &Zones) {
+    let player = zones.current();
+    let current = block_on(async { player.output().await });
+
+    let mut outputs = output::available().unwrap_or_default();
+    if !outputs.contains(&current) {
+        outputs.push(current.clone());
+    }
+
+    let selected = outputs
+        .iter()
+        .position(|output| *output == current)
+        .unwrap_or_default();
+
+    let output_list: SelectView<Output> = SelectView::new()
+        .with_all(
+            outputs
+                .into_iter()
+                .map(|output| (output.to_string(), output)),
+        )
+        .selected(selected)
+        .on_submit(move |s, output: &Output| {
+            s.pop_layer();
+
+            if let Err(error) = block_on(async { player.set_output(output.clone()).await }) {
+                s.add_layer(Dialog::info(error.to_string()));
+            }
+        });
+
+    s.add_layer(
+        Dialog::around(output_list.scrollable())
+            .title("Output")
+            .dismiss_button("Cancel"),
+    );
+}
+
+fn toggle_bit_perfect(s: &mut Cursive, zones: &Zones) {
+    let player = zones.current();
+    let bit_perfect = !player.bit_perfect();
+
+    if let Err(error) = block_on(async { player.set_bit_perfect(bit_perfect).await }) {
+        s.add_layer(Dialog::info(error.to_string()));
+    }
+}
+
+fn select_sleep_timer(s: &mut Cursive, zones: &Zones) {
+    let player = zones.current();
+
+    let timers: SelectView<&str> = SelectView::new()
+        .item("15 minutes", "15")
+        .item("30 minutes", "30")
+        .item("45 minutes", "45")
+        .item("1 hour", "60")
+        .item("End of track", "track")
+        .item("End of album", "album")
+        .item("Off", "off")
+        .on_submit(move |s, timer: &str| {
+            s.pop_layer();
+
+            let timer = timer.parse::<SleepTimer>().ok();
+
+            if let Err(error) = block_on(async { player.set_sleep_timer(timer).await }) {
+                s.add_layer(Dialog::info(error.to_string()));
+            }
+        });
+
+    s.add_layer(
+        Dialog::around(timers)
+            .title("Sleep timer")
+            .dismiss_button("Cancel"),
+    );
+}
+
+const HISTORY_TOP_LIMIT: u32 = 10;
+const HISTORY_RECENT_LIMIT: u32 = 50;
+
+fn show_history(s: &mut Cursive, zones: &Zones) {
+    let player = zones.current();
+    let recently_played = block_on(async { player.recently_played(HISTORY_RECENT_LIMIT).await });
+
+    let stats = TextView::new(history_stats(&player, Period::default())).with_name("history_stats");
+
+    let mut dialog = Dialog::around(
+        LinearLayout::new(Orientation::Vertical)
+            .child(stats)
+            .child(TextView::new(recently_played_list(recently_played)))
+            .scrollable(),
+    )
+    .title("History");
+
+    for period in Period::ALL {
+        let player = player.clone();
+        dialog.add_button(period_label(period), move |s| {
+            let content = history_stats(&player, period);
+            s.call_on_name("history_stats", |view: &mut TextView| {
+                view.set_content(content);
+            });
+        });
+    }
+
+    s.add_layer(dialog.dismiss_button("Close"));
+}
+
+fn history_stats(player: &Player, period: Period) -> StyledString {
+    let stats = block_on(async { player.listening_stats(period, HISTORY_TOP_LIMIT).await });
+
+    let mut content = StyledString::styled(period_label(period), Effect::Bold);
+    content.append_plain(format!(
+        "\n{} plays, {}\n",
+        stats.plays,
+        format_listened(stats.listened)
+    ));
+
+    for (title, counts) in [
+        ("Top artists", stats.top_artists),
+        ("Top albums", stats.top_albums),
+        ("Top tracks", stats.top_tracks),
+    ] {
+        if counts.is_empty() {
+            continue;
+        }
+
+        content.append_styled(format!("\n{title}\n"), Effect::Bold);
+        for Count {
+            name,
+            plays,
+            listened,
+            ..
+        } in counts
+        {
+            content.append_plain(name);
+            content.append_styled(
+                format!(" {plays} plays, {}\n", format_listened(listened)),
+                Effect::Dim,
+            );
+        }
+    }
+
+    content
+}
+
+fn recently_played_list(plays: Vec<Play>) -> StyledString {
+    let mut content = StyledString::new();
+
+    if plays.is_empty() {
+        return content;
+    }
+
+    content.append_styled("\nRecently played\n", Effect::Bold);
+    for play in plays {
+        content.append_styled(
+            play.started_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M ")
+                .to_string(),
+            Effect::Dim,
+        );
+        content.append_plain(play.track_title);
+
+        if let Some(artist_name) = play.artist_name {
+            content.append_plain(" by ");
+            content.append_plain(artist_name);
+        }
+
+        if play.skipped {
+            content.append_styled(" skipped", Effect::Dim);
+        }
+
+        content.append_plain("\n");
+    }
+
+    content
+}
+
+fn period_label(period: Period) -> &'static str {
+    match period {
+        Period::Week => "Week",
+        Period::Month => "Month",
+        Period::Year => "Year",
+        Period::AllTime => "All time",
+    }
+}
+
+fn format_listened(listened: std::time::Duration) -> String {
+    let minutes = listened.as_secs() / 60;
+
+    match minutes / 60 {
+        0 => format!("{minutes} min"),
+        hours => format!("{hours} h {} min", minutes % 60),
+    }
+}
+
 fn set_current_track(s: &mut Cursive, track: &Track, lt: &TrackListType, current_position: u32) {
     if let (Some(mut track_num), Some(mut track_title), Some(mut progress)) = (
         s.find_name::<TextView>("current_track_number"),
@@ -556,10 +1065,13 @@ fn set_current_track(s: &mut Cursive, track: &Track, lt: &TrackListType, current
             TrackListType::Album(_) => {
                 track_num.set_content(format!("{:03}", track.number));
             }
-            TrackListType::Playlist(_) => {
-                track_num.set_content(format!("{:03}", current_position));
-            }
-            TrackListType::Track => {
+            TrackListType::Playlist(_)
+            | TrackListType::ArtistTopTracks(_)
+            | TrackListType::ArtistRadio(_)
+            | TrackListType::Search(_)
+            | TrackListType::Favorites
+            | TrackListType::Custom
+            | TrackListType::Track => {
                 track_num.set_content(format!("{:03}", current_position));
             }
         };
@@ -575,6 +1087,24 @@ fn set_current_track(s: &mut Cursive, track: &Track, lt: &TrackListType, current
     }
 }
 
+fn get_playback_modes(shuffled: bool, repeat_mode: RepeatMode, autoplay: bool) -> String {
+    let repeat = match repeat_mode {
+        RepeatMode::None => None,
+        RepeatMode::Track => Some("rep1"),
+        RepeatMode::All => Some("rep"),
+    };
+
+    [
+        shuffled.then_some("shuf"),
+        repeat,
+        autoplay.then_some("auto"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
 fn get_state_icon(state: State) -> String {
     match state {
         State::Playing => {
@@ -587,254 +1117,340 @@ fn get_state_icon(state: State) -> String {
     }
 }
 
-async fn receive_notifications() {
-    let mut broadcast_receiver = qobuz_player_controls::notify_receiver();
+async fn receive_notifications(
+    zones: Zones,
+    zone: usize,
+    mut broadcast_receiver: Receiver<Notification>,
+) {
+    let player = zones.players[zone].clone();
+    let state = &zones.states[zone];
 
     loop {
-        if let Ok(notification) = broadcast_receiver.recv().await {
-            match notification {
-                Notification::Quit => {
-                    debug!("exiting tui notification thread");
-                    return;
+        let notification = match broadcast_receiver.recv().await {
+            Ok(notification) => notification,
+            Err(RecvError::Lagged(_)) => {
+                let tracklist = player.tracklist_copy().await;
+                let list = tracklist.tracklist().clone();
+                state.lock().unwrap().tracklist = tracklist;
+
+                if zones.selected() == zone {
+                    show_tracklist(list);
                 }
-                Notification::Status { status } => {
-                    if SINK
-                        .get()
-                        .unwrap()
-                        .send(Box::new(move |s| {
-                            if let Some(mut view) = s.find_name::<TextView>("player_status") {
-                                view.set_content(get_state_icon(status));
-                                match status {
-                                    State::Ready => {
-                                        s.call_on_name("progress", |progress: &mut ProgressBar| {
-                                            progress.set_value(0);
-                                        });
-                                    }
-                                    State::Null => {
-                                        s.call_on_name("progress", |progress: &mut ProgressBar| {
-                                            progress.set_value(0);
-                                        });
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }))
-                        .is_ok()
-                    {}
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        match &notification {
+            Notification::Quit => {
+                debug!("exiting tui notification thread");
+                return;
+            }
+            Notification::QueueChanged { diff } => {
+                let applied = state.lock().unwrap().tracklist.apply(diff.clone());
+
+                if !applied {
+                    let tracklist = player.tracklist_copy().await;
+                    state.lock().unwrap().tracklist = tracklist;
                 }
-                Notification::Position { clock } => {
-                    if SINK
-                        .get()
-                        .unwrap()
-                        .send(Box::new(move |s| {
-                            if let Some(mut progress) = s.find_name::<ProgressBar>("progress") {
-                                progress.set_value(clock.seconds() as usize);
+            }
+            Notification::TrackChanged { track } => {
+                state.lock().unwrap().current_track = track.clone();
+            }
+            _ => {}
+        }
+
+        if zones.selected() == zone {
+            show_notification(notification, &player, state);
+        }
+    }
+}
+
+/// Switches the controls to another zone and redraws the player with its state.
+fn select_zone(zones: &Zones, zone: usize) {
+    zones.selected.store(zone, Ordering::Relaxed);
+
+    let title = zones.title();
+    let player = zones.current();
+    let zones = zones.clone();
+
+    tokio::spawn(async move {
+        let state = &zones.states[zone];
+        let (list, track) = {
+            let state = state.lock().unwrap();
+            (
+                state.tracklist.tracklist().clone(),
+                state.current_track.clone(),
+            )
+        };
+        let status = player.current_state();
+        let stream_format = player
+            .stream_format()
+            .await
+            .map(|format| format.to_string())
+            .unwrap_or_default();
+
+        SINK.get()
+            .unwrap()
+            .send(Box::new(move |s| {
+                s.call_on_name("player_panel", |panel: &mut Panel<LinearLayout>| {
+                    panel.set_title(title);
+                });
+                s.call_on_name("stream_format", |view: &mut TextView| {
+                    view.set_content(stream_format);
+                });
+            }))
+            .expect("failed to send update");
+
+        show_sleep_timer(player.sleep_timer().await);
+        show_notification(Notification::Status { status }, &player, state);
+        show_tracklist(list);
+        show_notification(Notification::TrackChanged { track }, &player, state);
+    });
+}
+
+fn show_notification(notification: Notification, player: &Player, state: &Mutex<ZoneState>) {
+    match notification {
+        Notification::Quit => {}
+        Notification::Status { status } => {
+            if SINK
+                .get()
+                .unwrap()
+                .send(Box::new(move |s| {
+                    if let Some(mut view) = s.find_name::<TextView>("player_status") {
+                        view.set_content(get_state_icon(status));
+                        match status {
+                            State::Ready => {
+                                s.call_on_name("progress", |progress: &mut ProgressBar| {
+                                    progress.set_value(0);
+                                });
                             }
-                        }))
-                        .is_ok()
-                    {}
-                }
-                Notification::CurrentTrackList { list } => {
-                    let total = list.total();
-                    match list.list_type {
-                        TrackListType::Album(album) => {
-                            if SINK
-                                .get()
-                                .unwrap()
-                                .send(Box::new(move |s| {
-                                    if let Some(mut list_view) = s
-                                        .find_name::<ScrollView<SelectView<usize>>>(
-                                            "current_track_list",
-                                        )
-                                    {
-                                        list_view.get_inner_mut().clear();
-
-                                        list.queue
-                                            .iter()
-                                            .filter(|t| t.status == TrackStatus::Unplayed)
-                                            .enumerate()
-                                            .for_each(|(i, t)| {
-                                                list_view
-                                                    .get_inner_mut()
-                                                    .add_item(t.track_list_item(), i);
-                                            });
-                                    }
-                                    if let (Some(mut entity_title), Some(mut total_tracks)) = (
-                                        s.find_name::<TextView>("entity_title"),
-                                        s.find_name::<TextView>("total_tracks"),
-                                    ) {
-                                        let mut title = StyledString::plain(album.title.clone());
-                                        title.append_plain(" ");
-
-                                        entity_title.set_content(title);
-                                        total_tracks.set_content(format!("{:03}", total.clone()));
-                                    }
-
-                                    for t in list.queue.iter() {
-                                        if t.status == TrackStatus::Playing {
-                                            let track_id = t.id;
-                                            tokio::spawn(async move {
-                                                let track = qobuz_player_controls::track(track_id)
-                                                    .await
-                                                    .unwrap();
-                                                let track_list =
-                                                    qobuz_player_controls::current_tracklist()
-                                                        .await;
-                                                let current_position =
-                                                    track_list.current_position();
-
-                                                SINK.get()
-                                                    .unwrap()
-                                                    .send(Box::new(move |s| {
-                                                        set_current_track(
-                                                            s,
-                                                            &track,
-                                                            &track_list.list_type,
-                                                            current_position,
-                                                        );
-                                                    }))
-                                                    .unwrap();
-                                            });
-                                            break;
-                                        }
-                                    }
-                                }))
-                                .is_ok()
-                            {}
-                        }
-                        TrackListType::Playlist(playlist) => {
-                            if SINK
-                                .get()
-                                .unwrap()
-                                .send(Box::new(move |s| {
-                                    if let Some(mut list_view) = s
-                                        .find_name::<ScrollView<SelectView<usize>>>(
-                                            "current_track_list",
-                                        )
-                                    {
-                                        list_view.get_inner_mut().clear();
-
-                                        list.queue
-                                            .iter()
-                                            .filter(|t| t.status == TrackStatus::Unplayed)
-                                            .enumerate()
-                                            .for_each(|(i, t)| {
-                                                list_view
-                                                    .get_inner_mut()
-                                                    .add_item(t.track_list_item(), i);
-                                            });
-                                    }
-
-                                    if let (Some(mut entity_title), Some(mut total_tracks)) = (
-                                        s.find_name::<TextView>("entity_title"),
-                                        s.find_name::<TextView>("total_tracks"),
-                                    ) {
-                                        entity_title.set_content(&playlist.title);
-                                        total_tracks.set_content(format!("{:03}", total.clone()));
-                                    }
-
-                                    for t in list.queue.iter() {
-                                        if t.status == TrackStatus::Playing {
-                                            let track_id = t.id;
-                                            tokio::spawn(async move {
-                                                let track = qobuz_player_controls::track(track_id)
-                                                    .await
-                                                    .unwrap();
-                                                let track_list =
-                                                    qobuz_player_controls::current_tracklist()
-                                                        .await;
-                                                let current_positiion =
-                                                    track_list.current_position();
-
-                                                SINK.get()
-                                                    .unwrap()
-                                                    .send(Box::new(move |s| {
-                                                        set_current_track(
-                                                            s,
-                                                            &track,
-                                                            &track_list.list_type,
-                                                            current_positiion,
-                                                        );
-                                                    }))
-                                                    .unwrap();
-                                            });
-                                            break;
-                                        }
-                                    }
-                                }))
-                                .is_ok()
-                            {}
-                        }
-                        TrackListType::Track => {
-                            if SINK
-                                .get()
-                                .unwrap()
-                                .send(Box::new(move |sink| {
-                                    if let Some(mut list_view) = sink
-                                        .find_name::<ScrollView<SelectView<usize>>>(
-                                            "current_track_list",
-                                        )
-                                    {
-                                        list_view.get_inner_mut().clear();
-
-                                        list.queue
-                                            .iter()
-                                            .filter(|t| t.status == TrackStatus::Unplayed)
-                                            .enumerate()
-                                            .for_each(|(i, t)| {
-                                                list_view
-                                                    .get_inner_mut()
-                                                    .add_item(t.track_list_item(), i);
-                                            });
-                                    }
-
-                                    if let Some(mut total_tracks) =
-                                        sink.find_name::<TextView>("total_tracks")
-                                    {
-                                        total_tracks.set_content(format!("{:03}", total.clone()));
-                                    }
-
-                                    for t in list.queue.iter() {
-                                        if t.status == TrackStatus::Playing {
-                                            let track_id = t.id;
-                                            tokio::spawn(async move {
-                                                let track = qobuz_player_controls::track(track_id)
-                                                    .await
-                                                    .unwrap();
-                                                let track_list =
-                                                    qobuz_player_controls::current_tracklist()
-                                                        .await;
-                                                let current_positiion =
-                                                    track_list.current_position();
-
-                                                SINK.get()
-                                                    .unwrap()
-                                                    .send(Box::new(move |s| {
-                                                        set_current_track(
-                                                            s,
-                                                            &track,
-                                                            &track_list.list_type,
-                                                            current_positiion,
-                                                        );
-                                                    }))
-                                                    .unwrap();
-                                            });
-                                            break;
-                                        }
-                                    }
-                                }))
-                                .is_ok()
-                            {}
+                            State::Null => {
+                                s.call_on_name("progress", |progress: &mut ProgressBar| {
+                                    progress.set_value(0);
+                                });
+                            }
+                            _ => {}
                         }
                     }
-                }
-                Notification::Error { error: _ } => {}
-                Notification::Volume { volume: _ } => {}
+                }))
+                .is_ok()
+            {}
+        }
+        Notification::Position { clock } => {
+            if SINK
+                .get()
+                .unwrap()
+                .send(Box::new(move |s| {
+                    if let Some(mut progress) = s.find_name::<ProgressBar>("progress") {
+                        progress.set_value(clock.seconds() as usize);
+                    }
+                }))
+                .is_ok()
+            {}
+
+            // Counts the sleep timer down along with the track.
+            let player = player.clone();
+            tokio::spawn(async move { show_sleep_timer(player.sleep_timer().await) });
+        }
+        Notification::Seeked { position: _ } => {}
+        Notification::TrackChanged { track } => {
+            if let Some(track) = track {
+                let (list_type, current_position) = {
+                    let state = state.lock().unwrap();
+                    let list = state.tracklist.tracklist();
+                    (list.list_type.clone(), list.current_position())
+                };
+
+                SINK.get()
+                    .unwrap()
+                    .send(Box::new(move |s| {
+                        set_current_track(s, &track, &list_type, current_position);
+                    }))
+                    .unwrap();
+            }
+        }
+        Notification::QueueChanged { diff: _ } => {
+            show_tracklist(state.lock().unwrap().tracklist.tracklist().clone());
+        }
+        Notification::Buffering { percent: _ } => {}
+        Notification::Error { error: _ } => {}
+        Notification::PlaybackError {
+            track_id: _,
+            error: _,
+        } => {}
+        Notification::Volume { volume: _ } => {}
+        Notification::PlaylistChanged { id: _ } => {}
+        Notification::ReplayGain { replay_gain: _ } => {}
+        Notification::Output { output: _ } => {}
+        Notification::BitPerfect { bit_perfect: _ } => {}
+        Notification::Equalizer { equalizer: _ } => {}
+        Notification::Fades { fades: _ } => {}
+        Notification::SleepTimer { timer } => show_sleep_timer(timer),
+        Notification::StreamFormat { format } => {
+            if SINK
+                .get()
+                .unwrap()
+                .send(Box::new(move |s| {
+                    s.call_on_name("stream_format", |view: &mut TextView| {
+                        view.set_content(format.to_string());
+                    });
+                }))
+                .is_ok()
+            {}
+        }
+    }
+}
+
+/// Redraws the queue and the playback modes.
+fn show_tracklist(list: Tracklist) {
+    let shuffled = list.is_shuffled();
+    let repeat_mode = list.repeat_mode;
+    let autoplay = list.autoplay;
+    if SINK
+        .get()
+        .unwrap()
+        .send(Box::new(move |s| {
+            if let Some(mut view) = s.find_name::<TextView>("playback_modes") {
+                view.set_content(get_playback_modes(shuffled, repeat_mode, autoplay));
             }
+        }))
+        .is_ok()
+    {}
+
+    let total = list.total();
+    match list.list_type {
+        TrackListType::Album(album) => {
+            if SINK
+                .get()
+                .unwrap()
+                .send(Box::new(move |s| {
+                    if let Some(mut list_view) =
+                        s.find_name::<ScrollView<SelectView<usize>>>("current_track_list")
+                    {
+                        list_view.get_inner_mut().clear();
+
+                        list.queue
+                            .iter()
+                            .filter(|t| {
+                                matches!(t.status, TrackStatus::Unplayed | TrackStatus::Unplayable)
+                            })
+                            .enumerate()
+                            .for_each(|(i, t)| {
+                                list_view.get_inner_mut().add_item(t.track_list_item(), i);
+                            });
+                    }
+                    if let (Some(mut entity_title), Some(mut total_tracks)) = (
+                        s.find_name::<TextView>("entity_title"),
+                        s.find_name::<TextView>("total_tracks"),
+                    ) {
+                        let mut title = StyledString::plain(album.title.clone());
+                        title.append_plain(" ");
+
+                        entity_title.set_content(title);
+                        total_tracks.set_content(format!("{:03}", total.clone()));
+                    }
+                }))
+                .is_ok()
+            {}
+        }
+        list_type @ (TrackListType::Playlist(_)
+        | TrackListType::ArtistTopTracks(_)
+        | TrackListType::ArtistRadio(_)
+        | TrackListType::Search(_)
+        | TrackListType::Favorites) => {
+            let title = match list_type {
+                TrackListType::Playlist(playlist) => playlist.title,
+                TrackListType::ArtistTopTracks(artist) => {
+                    format!("{} top tracks", artist.name)
+                }
+                TrackListType::ArtistRadio(artist) => format!("{} radio", artist.name),
+                TrackListType::Search(query) => format!("Search: {}", query),
+                _ => "Favorites".to_string(),
+            };
+
+            if SINK
+                .get()
+                .unwrap()
+                .send(Box::new(move |s| {
+                    if let Some(mut list_view) =
+                        s.find_name::<ScrollView<SelectView<usize>>>("current_track_list")
+                    {
+                        list_view.get_inner_mut().clear();
+
+                        list.queue
+                            .iter()
+                            .filter(|t| {
+                                matches!(t.status, TrackStatus::Unplayed | TrackStatus::Unplayable)
+                            })
+                            .enumerate()
+                            .for_each(|(i, t)| {
+                                list_view.get_inner_mut().add_item(t.track_list_item(), i);
+                            });
+                    }
+
+                    if let (Some(mut entity_title), Some(mut total_tracks)) = (
+                        s.find_name::<TextView>("entity_title"),
+                        s.find_name::<TextView>("total_tracks"),
+                    ) {
+                        entity_title.set_content(&title);
+                        total_tracks.set_content(format!("{:03}", total.clone()));
+                    }
+                }))
+                .is_ok()
+            {}
+        }
+        TrackListType::Custom | TrackListType::Track => {
+            if SINK
+                .get()
+                .unwrap()
+                .send(Box::new(move |sink| {
+                    if let Some(mut list_view) =
+                        sink.find_name::<ScrollView<SelectView<usize>>>("current_track_list")
+                    {
+                        list_view.get_inner_mut().clear();
+
+                        list.queue
+                            .iter()
+                            .filter(|t| {
+                                matches!(t.status, TrackStatus::Unplayed | TrackStatus::Unplayable)
+                            })
+                            .enumerate()
+                            .for_each(|(i, t)| {
+                                list_view.get_inner_mut().add_item(t.track_list_item(), i);
+                            });
+                    }
+
+                    if let Some(mut total_tracks) = sink.find_name::<TextView>("total_tracks") {
+                        total_tracks.set_content(format!("{:03}", total.clone()));
+                    }
+                }))
+                .is_ok()
+            {}
         }
     }
 }
 
+fn show_sleep_timer(timer: Option<SleepTimer>) {
+    let label = match timer {
+        Some(SleepTimer::At { .. }) => timer.map(|timer| format!("\u{263e} {timer}")),
+        Some(SleepTimer::EndOfTrack) => Some("\u{263e} track".to_string()),
+        Some(SleepTimer::EndOfAlbum) => Some("\u{263e} album".to_string()),
+        None => None,
+    };
+
+    if SINK
+        .get()
+        .unwrap()
+        .send(Box::new(move |s| {
+            s.call_on_name("sleep_timer", |view: &mut TextView| {
+                view.set_content(label.unwrap_or_default());
+            });
+        }))
+        .is_ok()
+    {}
+}
+
 trait CursiveFormat {
     fn list_item(&self) -> StyledString {
         StyledString::new()
@@ -846,7 +1462,33 @@ trait CursiveFormat {
 
 impl CursiveFormat for tracklist::Track {
     fn track_list_item(&self) -> StyledString {
-        StyledString::styled(self.title.trim(), Style::none())
+        let mut style = Style::none();
+
+        if self.status == TrackStatus::Unplayable {
+            style = style.combine(Effect::Dim).combine(Effect::Strikethrough);
+        }
+
+        let mut title = StyledString::styled(self.title.trim(), style);
+
+        if let Some(artist_name) = &self.artist_name {
+            title.append_styled(" by ", style);
+            title.append_styled(artist_name, style);
+        }
+
+        if self.duration_seconds > 0 {
+            let duration = format!(
+                " {}:{:02}",
+                self.duration_seconds / 60,
+                self.duration_seconds % 60
+            );
+            title.append_styled(duration, style.combine(Effect::Dim));
+        }
+
+        if self.added_by_autoplay {
+            title.append_styled(" autoplay", Effect::Dim);
+        }
+
+        title
     }
 }
 
@@ -1,7 +1,10 @@
 use leptos::{component, prelude::*, IntoView};
 use serde::Deserialize;
 
-use crate::{html, icons::Star};
+use crate::{
+    html,
+    icons::{ArrowDownTray, Star},
+};
 pub mod list;
 
 #[derive(Deserialize, Clone, PartialEq)]
@@ -10,6 +13,7 @@ pub enum Tab {
     Albums,
     Artists,
     Playlists,
+    Tracks,
 }
 
 pub struct Duration {
@@ -40,6 +44,25 @@ pub fn toggle_favorite(id: String, is_favorite: bool) -> impl IntoView {
     }
 }
 
+/// Keeps the album or playlist `id` for offline use, or stops keeping it.
+#[component]
+pub fn toggle_offline(id: String, is_offline: bool) -> impl IntoView {
+    html! {
+        <button
+            class="flex gap-2 justify-center items-center py-2 px-4 bg-blue-500 rounded cursor-pointer"
+            id="toggle-offline"
+            hx-swap="outerHTML"
+            hx-target="this"
+            hx-put=format!("{}/{}", id, if is_offline { "unset-offline" } else { "set-offline" })
+        >
+            <span class="size-6">
+                <ArrowDownTray solid=is_offline />
+            </span>
+            <span>Offline</span>
+        </button>
+    }
+}
+
 #[component]
 pub fn info(hires_available: bool, explicit: bool) -> impl IntoView {
     html! {
@@ -1,6 +1,9 @@
+#![recursion_limit = "256"]
+
 use assets::static_handler;
 use axum::{
-    extract::State,
+    extract::{FromRequestParts, State},
+    http::{header, request::Parts},
     response::{sse::Event, Sse},
     routing::get,
     Router,
@@ -8,10 +11,21 @@ use axum::{
 use futures::stream::Stream;
 use leptos::html::*;
 use leptos::*;
-use qobuz_player_controls::notification::Notification;
-use routes::{album, artist, discover, favorites, now_playing, playlist, queue, search};
-use std::{convert::Infallible, sync::Arc};
-use tokio::sync::broadcast::{self, Sender};
+use qobuz_player_controls::{
+    models::Track,
+    notification::Notification,
+    tracklist::{Tracklist, TracklistCopy},
+    Player,
+};
+use routes::{
+    album, artist, discover, equalizer, favorites, history, local, now_playing, offline, playlist,
+    queue, search, settings, sleep_timer, zones,
+};
+use std::{
+    convert::Infallible,
+    sync::{Arc, RwLock},
+};
+use tokio::sync::broadcast::{self, error::RecvError, Receiver, Sender};
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::StreamExt as _;
 
@@ -26,14 +40,13 @@ pub fn is_htmx_request(headers: &axum::http::HeaderMap) -> bool {
     headers.get("HX-Request").is_some() && headers.get("HX-Boosted").is_none()
 }
 
-pub async fn init(address: String) {
+pub async fn init(players: Vec<Player>, address: String) {
     println!("Listening on {address}");
-    let router = create_router().await;
+    let mut broadcast_receiver = players[0].notify_receiver();
+    let router = create_router(players).await;
     let listener = tokio::net::TcpListener::bind(address).await.unwrap();
     axum::serve(listener, router)
-        .with_graceful_shutdown(async {
-            let mut broadcast_receiver = qobuz_player_controls::notify_receiver();
-
+        .with_graceful_shutdown(async move {
             loop {
                 if let Ok(message) = broadcast_receiver.recv().await {
                     if message == Notification::Quit {
@@ -46,14 +59,32 @@ pub async fn init(address: String) {
         .unwrap();
 }
 
-async fn create_router() -> Router {
+async fn create_router(players: Vec<Player>) -> Router {
     let (tx, _rx) = broadcast::channel::<ServerSentEvent>(100);
-    let shared_state = Arc::new(AppState { tx: tx.clone() });
-    tokio::spawn(background_task(tx));
+
+    // Subscribed before copying the queues, so no change falls in between.
+    let receivers: Vec<_> = players.iter().map(Player::notify_receiver).collect();
+
+    let mut zones = vec![];
+    for player in &players {
+        zones.push(RwLock::new(ZoneState {
+            tracklist: player.tracklist_copy().await,
+            current_track: player.current_track().await.ok().flatten(),
+        }));
+    }
+
+    let shared_state = Arc::new(AppState {
+        tx: tx.clone(),
+        players,
+        zones,
+    });
+
+    for (zone, receiver) in receivers.into_iter().enumerate() {
+        tokio::spawn(background_task(shared_state.clone(), zone, receiver));
+    }
 
     axum::Router::new()
         .route("/sse", get(sse_handler))
-        .with_state(shared_state)
         .merge(now_playing::routes())
         .merge(search::routes())
         .merge(album::routes())
@@ -62,67 +93,138 @@ async fn create_router() -> Router {
         .merge(favorites::routes())
         .merge(queue::routes())
         .merge(discover::routes())
+        .merge(zones::routes())
+        .merge(settings::routes())
+        .merge(equalizer::routes())
+        .merge(sleep_timer::routes())
+        .merge(history::routes())
+        .merge(offline::routes())
+        .merge(local::routes())
         .route("/assets/{*file}", get(static_handler))
+        .with_state(shared_state)
 }
 
-async fn background_task(tx: Sender<ServerSentEvent>) {
-    let mut receiver = qobuz_player_controls::notify_receiver();
+async fn background_task(state: Arc<AppState>, index: usize, mut receiver: Receiver<Notification>) {
+    let tx = &state.tx;
+    let player = &state.players[index];
+    let zone_state = &state.zones[index];
+    let zone = player.zone().to_string();
+
+    let tracklist_event = || ServerSentEvent {
+        zone: zone.clone(),
+        event_name: "tracklist".into(),
+        event_data: Default::default(),
+    };
 
     loop {
-        if let Ok(notification) = receiver.recv().await {
-            match notification {
-                Notification::Status { status } => {
-                    let message_data = match status {
-                        qobuz_player_controls::State::VoidPending => "pause",
-                        qobuz_player_controls::State::Null => "pause",
-                        qobuz_player_controls::State::Ready => "pause",
-                        qobuz_player_controls::State::Paused => "pause",
-                        qobuz_player_controls::State::Playing => "play",
-                    };
-
-                    let event = ServerSentEvent {
-                        event_name: "status".into(),
-                        event_data: message_data.into(),
-                    };
-                    _ = tx.send(event);
-                }
-                Notification::Position { clock } => {
-                    let event = ServerSentEvent {
-                        event_name: "position".into(),
-                        event_data: clock.seconds().to_string(),
-                    };
-                    _ = tx.send(event);
-                }
-                Notification::CurrentTrackList { list: _ } => {
-                    let event = ServerSentEvent {
-                        event_name: "tracklist".into(),
-                        event_data: Default::default(),
-                    };
-                    _ = tx.send(event);
-                }
-                Notification::Quit => (),
-                Notification::Error { error: _ } => (),
-                Notification::Volume { volume } => {
-                    let event = ServerSentEvent {
-                        event_name: "volume".into(),
-                        event_data: volume.to_string(),
-                    };
-                    _ = tx.send(event);
+        let notification = match receiver.recv().await {
+            Ok(notification) => notification,
+            Err(RecvError::Lagged(_)) => {
+                let tracklist = player.tracklist_copy().await;
+                zone_state.write().unwrap().tracklist = tracklist;
+                _ = tx.send(tracklist_event());
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        match notification {
+            Notification::Status { status } => {
+                let message_data = match status {
+                    qobuz_player_controls::State::VoidPending => "pause",
+                    qobuz_player_controls::State::Null => "pause",
+                    qobuz_player_controls::State::Ready => "pause",
+                    qobuz_player_controls::State::Paused => "pause",
+                    qobuz_player_controls::State::Playing => "play",
+                };
+
+                let event = ServerSentEvent {
+                    zone: zone.clone(),
+                    event_name: "status".into(),
+                    event_data: message_data.into(),
+                };
+                _ = tx.send(event);
+            }
+            Notification::Position { clock } => {
+                let event = ServerSentEvent {
+                    zone: zone.clone(),
+                    event_name: "position".into(),
+                    event_data: clock.seconds().to_string(),
+                };
+                _ = tx.send(event);
+            }
+            Notification::Seeked { position } => {
+                let event = ServerSentEvent {
+                    zone: zone.clone(),
+                    event_name: "position".into(),
+                    event_data: position.seconds().to_string(),
+                };
+                _ = tx.send(event);
+            }
+            Notification::QueueChanged { diff } => {
+                let applied = zone_state.write().unwrap().tracklist.apply(diff);
+
+                if !applied {
+                    let tracklist = player.tracklist_copy().await;
+                    zone_state.write().unwrap().tracklist = tracklist;
                 }
-            };
-        }
+
+                _ = tx.send(tracklist_event());
+            }
+            Notification::TrackChanged { track } => {
+                zone_state.write().unwrap().current_track = track;
+            }
+            Notification::Buffering { percent: _ } => (),
+            Notification::Quit => (),
+            Notification::Error { error: _ } => (),
+            Notification::PlaybackError {
+                track_id: _,
+                error: _,
+            } => (),
+            Notification::PlaylistChanged { id: _ } => (),
+            Notification::ReplayGain { replay_gain: _ } => (),
+            Notification::Output { output: _ } => (),
+            Notification::BitPerfect { bit_perfect: _ } => (),
+            Notification::Equalizer { equalizer: _ } => (),
+            Notification::Fades { fades: _ } => (),
+            Notification::SleepTimer { timer } => {
+                let event = ServerSentEvent {
+                    zone: zone.clone(),
+                    event_name: "sleep-timer".into(),
+                    event_data: timer.map(|timer| timer.to_string()).unwrap_or_default(),
+                };
+                _ = tx.send(event);
+            }
+            Notification::StreamFormat { format } => {
+                let event = ServerSentEvent {
+                    zone: zone.clone(),
+                    event_name: "format".into(),
+                    event_data: format.to_string(),
+                };
+                _ = tx.send(event);
+            }
+            Notification::Volume { volume } => {
+                let event = ServerSentEvent {
+                    zone: zone.clone(),
+                    event_name: "volume".into(),
+                    event_data: volume.to_string(),
+                };
+                _ = tx.send(event);
+            }
+        };
     }
 }
 
 async fn sse_handler(
     State(state): State<Arc<AppState>>,
+    Zone(player): Zone,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
     let rx = state.tx.subscribe();
-    let stream = BroadcastStream::new(rx).filter_map(|result| match result {
-        Ok(event) => Some(Ok(Event::default()
+    let stream = BroadcastStream::new(rx).filter_map(move |result| match result {
+        Ok(event) if event.zone == player.zone() => Some(Ok(Event::default()
             .event(event.event_name)
             .data(event.event_data))),
-        Err(_) => None,
+        _ => None,
     });
 
     Sse::new(stream)
@@ -130,10 +232,76 @@ async fn sse_handler(
 
 pub struct AppState {
     pub tx: Sender<ServerSentEvent>,
+    pub players: Vec<Player>,
+    /// What the notifications of each player have said, in the order of `players`.
+    zones: Vec<RwLock<ZoneState>>,
+}
+
+impl AppState {
+    /// The queue of the player's zone, as of the last notification.
+    pub fn tracklist(&self, player: &Player) -> Tracklist {
+        self.zone_state(player)
+            .read()
+            .unwrap()
+            .tracklist
+            .tracklist()
+            .clone()
+    }
+
+    /// The current track of the player's zone, as of the last notification.
+    pub fn current_track(&self, player: &Player) -> Option<Track> {
+        self.zone_state(player)
+            .read()
+            .unwrap()
+            .current_track
+            .clone()
+    }
+
+    fn zone_state(&self, player: &Player) -> &RwLock<ZoneState> {
+        let index = self
+            .players
+            .iter()
+            .position(|zone| zone.zone() == player.zone())
+            .unwrap_or(0);
+
+        &self.zones[index]
+    }
+}
+
+struct ZoneState {
+    tracklist: TracklistCopy,
+    current_track: Option<Track>,
+}
+
+/// The player for the zone picked with the `zone` cookie, falling back to the first zone.
+pub struct Zone(pub Player);
+
+impl FromRequestParts<Arc<AppState>> for Zone {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let selected = parts
+            .headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .find_map(|cookie| cookie.trim().strip_prefix("zone="));
+
+        let player = selected
+            .and_then(|zone| state.players.iter().find(|player| player.zone() == zone))
+            .unwrap_or(&state.players[0]);
+
+        Ok(Zone(player.clone()))
+    }
 }
 
 #[derive(Clone)]
 pub struct ServerSentEvent {
+    zone: String,
     event_name: String,
     event_data: String,
 }
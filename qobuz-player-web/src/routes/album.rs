@@ -5,60 +5,80 @@ use axum::{
     Router,
 };
 use leptos::{component, prelude::*, IntoView};
-use qobuz_player_controls::models::{Album, Track};
+use qobuz_player_controls::{
+    local,
+    models::{Album, Track},
+    tracklist::{AlbumTracklist, TrackListType},
+};
+use std::sync::Arc;
 use tokio::join;
 
 use crate::{
     components::{
         list::{AlbumSort, ListAlbumsVertical, ListTracks, TrackNumberDisplay},
-        parse_duration, ToggleFavorite,
+        parse_duration, ToggleFavorite, ToggleOffline,
     },
     html,
     icons::Play,
     page::Page,
     view::render,
+    AppState, Zone,
 };
 
-pub fn routes() -> Router {
+pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/album/{id}", get(index))
         .route("/album/{id}/tracks", get(album_tracks_partial))
         .route("/album/{id}/set-favorite", put(set_favorite))
         .route("/album/{id}/unset-favorite", put(unset_favorite))
+        .route("/album/{id}/set-offline", put(set_offline))
+        .route("/album/{id}/unset-offline", put(unset_offline))
         .route("/album/{id}/play", put(play))
         .route("/album/{id}/play/{track_position}", put(play_track))
 }
 
-async fn play_track(Path((id, track_position)): Path<(String, u32)>) -> impl IntoResponse {
-    qobuz_player_controls::play_album(&id, track_position)
-        .await
-        .unwrap();
+async fn play_track(
+    Zone(player): Zone,
+    Path((id, track_position)): Path<(String, u32)>,
+) -> impl IntoResponse {
+    player.play_album(&id, track_position).await.unwrap();
 }
 
-async fn set_favorite(Path(id): Path<String>) -> impl IntoResponse {
-    qobuz_player_controls::add_favorite_album(&id)
-        .await
-        .unwrap();
+async fn set_favorite(Zone(player): Zone, Path(id): Path<String>) -> impl IntoResponse {
+    player.add_favorite_album(&id).await.unwrap();
     render(html! { <ToggleFavorite id=id is_favorite=true /> })
 }
 
-async fn unset_favorite(Path(id): Path<String>) -> impl IntoResponse {
-    qobuz_player_controls::remove_favorite_album(&id)
-        .await
-        .unwrap();
+async fn unset_favorite(Zone(player): Zone, Path(id): Path<String>) -> impl IntoResponse {
+    player.remove_favorite_album(&id).await.unwrap();
     render(html! { <ToggleFavorite id=id is_favorite=false /> })
 }
 
-async fn play(Path(id): Path<String>) -> impl IntoResponse {
-    qobuz_player_controls::play_album(&id, 0).await.unwrap();
+async fn set_offline(Zone(player): Zone, Path(id): Path<String>) -> impl IntoResponse {
+    player.set_album_offline(&id, true).await.unwrap();
+    render(html! { <ToggleOffline id=id is_offline=true /> })
+}
+
+async fn unset_offline(Zone(player): Zone, Path(id): Path<String>) -> impl IntoResponse {
+    player.set_album_offline(&id, false).await.unwrap();
+    render(html! { <ToggleOffline id=id is_offline=false /> })
 }
 
-async fn index(Path(id): Path<String>) -> impl IntoResponse {
-    let (album, suggested_albums, tracklist, favorites) = join!(
-        qobuz_player_controls::album(&id),
-        qobuz_player_controls::suggested_albums(&id),
-        qobuz_player_controls::current_tracklist(),
-        qobuz_player_controls::favorites()
+async fn play(Zone(player): Zone, Path(id): Path<String>) -> impl IntoResponse {
+    player.play_album(&id, 0).await.unwrap();
+}
+
+async fn index(Zone(player): Zone, Path(id): Path<String>) -> impl IntoResponse {
+    let list_type = TrackListType::Album(AlbumTracklist {
+        id: id.clone(),
+        ..Default::default()
+    });
+    let (album, suggested_albums, tracklist, favorites, is_offline) = join!(
+        player.album(&id),
+        player.suggested_albums(&id),
+        player.current_tracklist(),
+        player.favorites(),
+        player.is_offline(&list_type)
     );
 
     let album = album.unwrap();
@@ -74,17 +94,15 @@ async fn index(Path(id): Path<String>) -> impl IntoResponse {
                 album=album
                 suggested_albums=suggested_albums
                 is_favorite=is_favorite
+                is_offline=is_offline
                 now_playing_id=now_playing_id
             />
         </Page>
     })
 }
 
-async fn album_tracks_partial(Path(id): Path<String>) -> impl IntoResponse {
-    let (album, tracklist) = join!(
-        qobuz_player_controls::album(&id),
-        qobuz_player_controls::current_tracklist(),
-    );
+async fn album_tracks_partial(Zone(player): Zone, Path(id): Path<String>) -> impl IntoResponse {
+    let (album, tracklist) = join!(player.album(&id), player.current_tracklist(),);
 
     let album = album.unwrap();
 
@@ -124,9 +142,12 @@ fn album(
     album: Album,
     suggested_albums: Vec<Album>,
     is_favorite: bool,
+    is_offline: bool,
     now_playing_id: Option<u32>,
 ) -> impl IntoView {
     let duration = parse_duration(album.duration_seconds);
+    // Favorites and offline copies are kept by Qobuz, not for local files.
+    let is_local = local::is_local_album(&album.id);
 
     html! {
         <div class="flex flex-col justify-center items-center sm:p-4">
@@ -155,7 +176,11 @@ fn album(
                         </span>
                     </div>
 
-                    <div class="grid grid-cols-2 gap-4">
+                    <div class=if is_local {
+                        "grid grid-cols-1 gap-4"
+                    } else {
+                        "grid grid-cols-3 gap-4"
+                    }>
                         <button
                             class="flex gap-2 justify-center items-center py-2 px-4 bg-blue-500 rounded cursor-pointer"
                             hx-swap="none"
@@ -167,7 +192,13 @@ fn album(
                             <span>Play</span>
                         </button>
 
-                        <ToggleFavorite id=album.id.clone() is_favorite=is_favorite />
+                        {(!is_local)
+                            .then(|| {
+                                html! {
+                                    <ToggleFavorite id=album.id.clone() is_favorite=is_favorite />
+                                    <ToggleOffline id=album.id.clone() is_offline=is_offline />
+                                }
+                            })}
                     </div>
                 </div>
             </div>
@@ -5,7 +5,11 @@ use axum::{
     Router,
 };
 use leptos::prelude::*;
-use qobuz_player_controls::models::{self, Album, Artist, ArtistPage};
+use qobuz_player_controls::{
+    local,
+    models::{self, Album, Artist, ArtistPage},
+};
+use std::sync::Arc;
 use tokio::join;
 
 use crate::{
@@ -17,9 +21,10 @@ use crate::{
     icons::Play,
     page::Page,
     view::render,
+    AppState, Zone,
 };
 
-pub fn routes() -> Router {
+pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/artist/{id}", get(index))
         .route("/artist/{id}/top-tracks", get(top_tracks_partial))
@@ -29,13 +34,11 @@ pub fn routes() -> Router {
             "/artist/{artist_id}/play-top-track/{track_index}",
             put(play_top_track),
         )
+        .route("/artist/{id}/radio", put(play_radio))
 }
 
-async fn top_tracks_partial(Path(id): Path<u32>) -> impl IntoResponse {
-    let (artist, tracklist) = join!(
-        qobuz_player_controls::artist_page(id),
-        qobuz_player_controls::current_tracklist(),
-    );
+async fn top_tracks_partial(Zone(player): Zone, Path(id): Path<u32>) -> impl IntoResponse {
+    let (artist, tracklist) = join!(player.artist_page(id), player.current_tracklist(),);
 
     let now_playing_id = tracklist.currently_playing();
     let artist = artist.unwrap();
@@ -45,34 +48,38 @@ async fn top_tracks_partial(Path(id): Path<u32>) -> impl IntoResponse {
     )
 }
 
-async fn play_top_track(Path((artist_id, track_index)): Path<(u32, u32)>) -> impl IntoResponse {
-    qobuz_player_controls::play_top_tracks(artist_id, track_index)
+async fn play_top_track(
+    Zone(player): Zone,
+    Path((artist_id, track_index)): Path<(u32, u32)>,
+) -> impl IntoResponse {
+    player
+        .play_top_tracks(artist_id, track_index)
         .await
         .unwrap();
 }
 
-async fn set_favorite(Path(id): Path<String>) -> impl IntoResponse {
-    qobuz_player_controls::add_favorite_artist(&id)
-        .await
-        .unwrap();
+async fn play_radio(Zone(player): Zone, Path(id): Path<u32>) -> impl IntoResponse {
+    player.play_artist_radio(id).await.unwrap();
+}
+
+async fn set_favorite(Zone(player): Zone, Path(id): Path<String>) -> impl IntoResponse {
+    player.add_favorite_artist(&id).await.unwrap();
 
     render(html! { <ToggleFavorite id=id is_favorite=true /> })
 }
 
-async fn unset_favorite(Path(id): Path<String>) -> impl IntoResponse {
-    qobuz_player_controls::remove_favorite_artist(&id)
-        .await
-        .unwrap();
+async fn unset_favorite(Zone(player): Zone, Path(id): Path<String>) -> impl IntoResponse {
+    player.remove_favorite_artist(&id).await.unwrap();
     render(html! { <ToggleFavorite id=id is_favorite=false /> })
 }
 
-async fn index(Path(id): Path<u32>) -> impl IntoResponse {
+async fn index(Zone(player): Zone, Path(id): Path<u32>) -> impl IntoResponse {
     let (artist, albums, similar_artists, favorites, tracklist) = join!(
-        qobuz_player_controls::artist_page(id),
-        qobuz_player_controls::artist_albums(id),
-        qobuz_player_controls::similar_artists(id),
-        qobuz_player_controls::favorites(),
-        qobuz_player_controls::current_tracklist(),
+        player.artist_page(id),
+        player.artist_albums(id),
+        player.similar_artists(id),
+        player.favorites(),
+        player.current_tracklist(),
     );
 
     let now_playing_id = tracklist.currently_playing();
@@ -117,7 +124,25 @@ fn artist(
                     style=artist_image_style
                 ></div>
                 <h1 class="text-2xl">{artist.name}</h1>
-                <ToggleFavorite id=artist.id.to_string() is_favorite=is_favorite />
+                // Favorites and radios need the artist to be on Qobuz.
+                {(!local::is_local(artist.id))
+                    .then(|| {
+                        html! {
+                            <div class="flex gap-4 items-center">
+                                <ToggleFavorite id=artist.id.to_string() is_favorite=is_favorite />
+                                <button
+                                    class="flex gap-2 justify-center items-center py-2 px-4 bg-blue-500 rounded cursor-pointer"
+                                    hx-put=format!("{}/radio", artist.id)
+                                    hx-swap="none"
+                                >
+                                    <span class="size-6">
+                                        <Play />
+                                    </span>
+                                    <span>Start radio</span>
+                                </button>
+                            </div>
+                        }
+                    })}
             </div>
             <div class="flex flex-col gap-4">
                 <div
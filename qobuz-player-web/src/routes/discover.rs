@@ -1,6 +1,14 @@
-use axum::{response::IntoResponse, routing::get, Router};
+use axum::{
+    response::{IntoResponse, Redirect},
+    routing::get,
+    Router,
+};
 use leptos::prelude::*;
-use qobuz_player_controls::models::{Album, Playlist};
+use qobuz_player_controls::{
+    error::Error,
+    models::{Album, Playlist},
+};
+use std::sync::Arc;
 use tokio::try_join;
 
 use crate::{
@@ -8,31 +16,29 @@ use crate::{
     html,
     page::Page,
     view::render,
+    AppState, Zone,
 };
 
-pub fn routes() -> Router {
+pub fn routes() -> Router<Arc<AppState>> {
     Router::new().route("/discover", get(index))
 }
 
-async fn index() -> impl IntoResponse {
-    let (press_awards, new_releases, qobuzissims, ideal_discography, editor_picks) = try_join!(
-        qobuz_player_controls::featured_albums(
-            qobuz_player_controls::AlbumFeaturedType::PressAwards
-        ),
-        qobuz_player_controls::featured_albums(
-            qobuz_player_controls::AlbumFeaturedType::NewReleasesFull
-        ),
-        qobuz_player_controls::featured_albums(
-            qobuz_player_controls::AlbumFeaturedType::Qobuzissims
-        ),
-        qobuz_player_controls::featured_albums(
-            qobuz_player_controls::AlbumFeaturedType::IdealDiscography
-        ),
-        qobuz_player_controls::featured_playlists(
-            qobuz_player_controls::PlaylistFeaturedType::EditorPicks
-        ),
-    )
-    .unwrap();
+async fn index(Zone(player): Zone) -> impl IntoResponse {
+    let features = try_join!(
+        player.featured_albums(qobuz_player_controls::AlbumFeaturedType::PressAwards),
+        player.featured_albums(qobuz_player_controls::AlbumFeaturedType::NewReleasesFull),
+        player.featured_albums(qobuz_player_controls::AlbumFeaturedType::Qobuzissims),
+        player.featured_albums(qobuz_player_controls::AlbumFeaturedType::IdealDiscography),
+        player.featured_playlists(qobuz_player_controls::PlaylistFeaturedType::EditorPicks),
+    );
+
+    // Without Qobuz only what is kept offline can be shown.
+    if let Err(Error::Offline) = features {
+        return Redirect::to("/offline").into_response();
+    }
+
+    let (press_awards, new_releases, qobuzissims, ideal_discography, editor_picks) =
+        features.unwrap();
 
     render(html! {
         <Page active_page=Page::Discover>
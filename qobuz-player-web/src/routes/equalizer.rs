@@ -0,0 +1,140 @@
+use axum::{
+    response::IntoResponse,
+    routing::{get, post},
+    Form, Router,
+};
+use leptos::{component, prelude::*, IntoView};
+use qobuz_player_controls::equalizer::{Equalizer, FREQUENCIES, MAX_GAIN, MIN_GAIN, PRESETS};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{html, page::Page, view::render, AppState, Zone};
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/equalizer", get(index).post(set_bands))
+        .route("/equalizer/preset", post(set_preset))
+}
+
+async fn index(Zone(player): Zone) -> impl IntoResponse {
+    let equalizer = player.equalizer().await;
+    let output = player.output().await.to_string();
+
+    render(html! {
+        <Page active_page=Page::None>
+            <div class="flex flex-col gap-4 p-4 mx-auto max-w-screen-sm">
+                <p class="text-lg text-center">Equalizer</p>
+                <p class="text-center text-gray-500">{output}</p>
+                <EqualizerSettings equalizer=equalizer />
+            </div>
+        </Page>
+    })
+}
+
+/// Takes the bands as repeated `band` fields, lowest frequency first.
+async fn set_bands(
+    Zone(player): Zone,
+    Form(parameters): Form<Vec<(String, String)>>,
+) -> impl IntoResponse {
+    let gains = parameters
+        .into_iter()
+        .filter(|(name, _)| name == "band")
+        .filter_map(|(_, gain)| gain.parse::<f64>().ok())
+        .collect::<Vec<_>>();
+
+    if let Ok(bands) = gains.try_into() {
+        player.set_equalizer(Equalizer::new(bands)).await.unwrap();
+    }
+}
+
+#[derive(Deserialize)]
+struct PresetParameters {
+    preset: String,
+}
+
+async fn set_preset(
+    Zone(player): Zone,
+    Form(parameters): Form<PresetParameters>,
+) -> impl IntoResponse {
+    if let Some(equalizer) = Equalizer::preset(&parameters.preset) {
+        player.set_equalizer(equalizer).await.unwrap();
+    }
+
+    let equalizer = player.equalizer().await;
+
+    render(html! { <EqualizerSettings equalizer=equalizer /> })
+}
+
+#[component]
+fn equalizer_settings(equalizer: Equalizer) -> impl IntoView {
+    let current_preset = equalizer.preset_name();
+
+    html! {
+        <div id="equalizer" class="flex flex-col gap-4">
+            <form
+                hx-post="/equalizer/preset"
+                hx-trigger="change"
+                hx-target="#equalizer"
+                hx-swap="outerHTML"
+            >
+                <label class="flex justify-between items-center">
+                    <span>Preset</span>
+                    <select name="preset" class="p-2 text-black bg-white rounded">
+                        <option value="" selected=current_preset.is_none() disabled=true>
+                            Custom
+                        </option>
+                        {PRESETS
+                            .iter()
+                            .map(|(name, _)| {
+                                html! {
+                                    <option value=*name selected=current_preset == Some(*name)>
+                                        {*name}
+                                    </option>
+                                }
+                            })
+                            .collect::<Vec<_>>()}
+                    </select>
+                </label>
+            </form>
+            <form
+                class="flex justify-between"
+                hx-post="/equalizer"
+                hx-trigger="input delay:100ms"
+                hx-swap="none"
+            >
+                {FREQUENCIES
+                    .into_iter()
+                    .zip(equalizer.bands)
+                    .map(|(frequency, gain)| {
+                        html! {
+                            <label class="flex flex-col gap-2 items-center">
+                                <input
+                                    class="h-48"
+                                    style="writing-mode: vertical-lr; direction: rtl"
+                                    type="range"
+                                    name="band"
+                                    min=MIN_GAIN
+                                    max=MAX_GAIN
+                                    step="0.5"
+                                    value=gain
+                                />
+                                <span class="text-xs text-gray-500">
+                                    {frequency_label(frequency)}
+                                </span>
+                            </label>
+                        }
+                    })
+                    .collect::<Vec<_>>()}
+            </form>
+        </div>
+    }
+}
+
+/// Short band label, like 59 or 1.9k.
+fn frequency_label(frequency: u32) -> String {
+    if frequency < 1000 {
+        frequency.to_string()
+    } else {
+        format!("{:.1}k", frequency as f64 / 1000.0)
+    }
+}
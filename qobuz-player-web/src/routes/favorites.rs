@@ -1,37 +1,75 @@
-use axum::{extract::Path, response::IntoResponse, routing::get, Router};
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Redirect},
+    routing::{get, put},
+    Router,
+};
 use leptos::{component, prelude::*, IntoView};
-use qobuz_player_controls::models::Favorites;
+use qobuz_player_controls::{error::Error, models::Favorites, tracklist::TrackListType};
+use std::sync::Arc;
+use tokio::join;
 
 use crate::{
     components::{
-        list::{ListAlbums, ListArtists, ListPlaylists},
+        list::{ListAlbums, ListArtists, ListPlaylists, ListTracks, TrackNumberDisplay},
         Tab,
     },
     html,
     page::Page,
     view::render,
+    AppState, Zone,
 };
 
-pub fn routes() -> Router {
-    Router::new().route("/favorites/{tab}", get(index))
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/favorites/{tab}", get(index))
+        .route("/favorites/tracks/play/{index}", put(play_track))
 }
 
-async fn index(Path(tab): Path<Tab>) -> impl IntoResponse {
-    let favorites = qobuz_player_controls::favorites().await.unwrap();
+async fn index(Zone(player): Zone, Path(tab): Path<Tab>) -> impl IntoResponse {
+    let (favorites, tracklist) = join!(player.favorites(), player.current_tracklist());
+
+    // Without Qobuz only what is kept offline can be shown.
+    if let Err(Error::Offline) = favorites {
+        return Redirect::to("/offline").into_response();
+    }
+
+    let favorites = favorites.unwrap();
+    let now_playing_id = tracklist.currently_playing();
 
     render(html! {
         <Page active_page=Page::Favorites>
-            <Favorites favorites=favorites tab=tab />
+            <Favorites favorites=favorites tab=tab now_playing_id=now_playing_id />
         </Page>
     })
 }
 
+async fn play_track(Zone(player): Zone, Path(index): Path<u32>) -> impl IntoResponse {
+    let favorites = player.favorites().await.unwrap();
+    let track_ids: Vec<u32> = favorites.tracks.iter().map(|track| track.id).collect();
+
+    player
+        .play_tracks(&track_ids, index, TrackListType::Favorites)
+        .await
+        .unwrap();
+}
+
 #[component]
-fn favorites(favorites: Favorites, tab: Tab) -> impl IntoView {
+fn favorites(favorites: Favorites, tab: Tab, now_playing_id: Option<u32>) -> impl IntoView {
     html! {
         <div class="flex flex-col h-full">
             <div class="flex flex-col flex-grow gap-4 p-4 max-h-full">
-                <h1 class="text-2xl">Favorites</h1>
+                <div class="flex justify-between items-center">
+                    <h1 class="text-2xl">Favorites</h1>
+                    <div class="flex gap-4">
+                        <a href="/local" class="text-gray-500">
+                            Local
+                        </a>
+                        <a href="/offline" class="text-gray-500">
+                            Offline
+                        </a>
+                    </div>
+                </div>
 
                 <div class="flex justify-between group *:rounded-full *:px-2 *:py-1 *:transition-colors">
                     {html! {
@@ -55,6 +93,13 @@ fn favorites(favorites: Favorites, tab: Tab) -> impl IntoView {
                     }
                         .attr("preload", "mouseover")
                         .attr("preload-images", "true")}
+                    {html! {
+                        <a href="tracks" class=(tab == Tab::Tracks).then_some("bg-blue-800")>
+                            Tracks
+                        </a>
+                    }
+                        .attr("preload", "mouseover")
+                        .attr("preload-images", "true")}
                 </div>
             </div>
 
@@ -87,6 +132,18 @@ fn favorites(favorites: Favorites, tab: Tab) -> impl IntoView {
                         }
                             .into_any()
                     }
+                    Tab::Tracks => {
+                        html! {
+                            <ListTracks
+                                track_number_display=TrackNumberDisplay::Cover
+                                now_playing_id=now_playing_id
+                                tracks=favorites.tracks
+                                parent_id="tracks".to_string()
+                                show_artist=true
+                            />
+                        }
+                            .into_any()
+                    }
                 }}
             </div>
         </div>
@@ -0,0 +1,184 @@
+use axum::{extract::Query, response::IntoResponse, routing::get, Router};
+use chrono::Local;
+use leptos::{component, prelude::*, IntoView};
+use qobuz_player_controls::history::{Count, ListeningStats, Period, Play};
+use serde::Deserialize;
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    components::list::{List, ListItem},
+    html,
+    page::Page,
+    view::render,
+    AppState, Zone,
+};
+
+const TOP_LIMIT: u32 = 10;
+const RECENT_LIMIT: u32 = 50;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/history", get(index))
+}
+
+#[derive(Deserialize)]
+struct HistoryParameters {
+    period: Option<String>,
+}
+
+async fn index(
+    Zone(player): Zone,
+    Query(parameters): Query<HistoryParameters>,
+) -> impl IntoResponse {
+    let period = parameters
+        .period
+        .and_then(|period| period.parse::<Period>().ok())
+        .unwrap_or_default();
+
+    let stats = player.listening_stats(period, TOP_LIMIT).await;
+    let recently_played = player.recently_played(RECENT_LIMIT).await;
+
+    render(html! {
+        <Page active_page=Page::None>
+            <div class="flex flex-col gap-4 p-4 mx-auto max-w-screen-sm">
+                <p class="text-lg text-center">History</p>
+                <Periods period=period />
+                <Stats stats=stats />
+                <RecentlyPlayed plays=recently_played />
+            </div>
+        </Page>
+    })
+}
+
+#[component]
+fn periods(period: Period) -> impl IntoView {
+    html! {
+        <div class="flex gap-4 justify-center">
+            {Period::ALL
+                .into_iter()
+                .map(|option| {
+                    html! {
+                        <a
+                            href=format!("/history?period={}", option)
+                            class=if option == period { "text-blue-500" } else { "text-gray-500" }
+                        >
+                            {period_label(option)}
+                        </a>
+                    }
+                })
+                .collect::<Vec<_>>()}
+        </div>
+    }
+}
+
+#[component]
+fn stats(stats: ListeningStats) -> impl IntoView {
+    html! {
+        <p class="text-center text-gray-500">
+            {format!("{} plays, {}", stats.plays, format_listened(stats.listened))}
+        </p>
+        <TopList title="Top artists" counts=stats.top_artists link="artist" />
+        <TopList title="Top albums" counts=stats.top_albums link="album" />
+        <TopList title="Top tracks" counts=stats.top_tracks link="" />
+    }
+}
+
+/// Entries link to `/{link}/{id}` unless `link` is empty.
+#[component]
+fn top_list(title: &'static str, counts: Vec<Count>, link: &'static str) -> impl IntoView {
+    (!counts.is_empty()).then(|| {
+        html! {
+            <div class="flex flex-col gap-2">
+                <p class="text-lg">{title}</p>
+                <List>
+                    {counts
+                        .into_iter()
+                        .map(|count| {
+                            let summary = format!(
+                                "{} plays, {}",
+                                count.plays,
+                                format_listened(count.listened),
+                            );
+                            let name = if link.is_empty() {
+                                html! { <span class="truncate">{count.name}</span> }.into_any()
+                            } else {
+                                html! {
+                                    <a href=format!("/{}/{}", link, count.id) class="truncate">
+                                        {count.name}
+                                    </a>
+                                }
+                                    .into_any()
+                            };
+
+                            html! {
+                                <ListItem>
+                                    <div class="flex gap-4 justify-between">
+                                        {name}
+                                        <span class="text-sm text-gray-500 shrink-0">{summary}</span>
+                                    </div>
+                                </ListItem>
+                            }
+                        })
+                        .collect::<Vec<_>>()}
+                </List>
+            </div>
+        }
+    })
+}
+
+#[component]
+fn recently_played(plays: Vec<Play>) -> impl IntoView {
+    (!plays.is_empty()).then(|| {
+        html! {
+            <div class="flex flex-col gap-2">
+                <p class="text-lg">Recently played</p>
+                <List>
+                    {plays
+                        .into_iter()
+                        .map(|play| {
+                            let started_at = play
+                                .started_at
+                                .with_timezone(&Local)
+                                .format("%Y-%m-%d %H:%M")
+                                .to_string();
+
+                            html! {
+                                <ListItem>
+                                    <div class="flex gap-4 justify-between">
+                                        <div class="flex flex-col overflow-hidden">
+                                            <span class="truncate">{play.track_title}</span>
+                                            <span class="text-sm text-gray-500 truncate">
+                                                {play.artist_name}
+                                            </span>
+                                        </div>
+                                        <div class="flex flex-col items-end text-sm text-gray-500 shrink-0">
+                                            <span>{started_at}</span>
+                                            {play.skipped.then_some("Skipped")}
+                                        </div>
+                                    </div>
+                                </ListItem>
+                            }
+                        })
+                        .collect::<Vec<_>>()}
+                </List>
+            </div>
+        }
+    })
+}
+
+fn period_label(period: Period) -> &'static str {
+    match period {
+        Period::Week => "Week",
+        Period::Month => "Month",
+        Period::Year => "Year",
+        Period::AllTime => "All time",
+    }
+}
+
+fn format_listened(listened: Duration) -> String {
+    let minutes = listened.as_secs() / 60;
+
+    match minutes / 60 {
+        0 => format!("{} min", minutes),
+        hours => format!("{} h {} min", hours, minutes % 60),
+    }
+}
@@ -0,0 +1,30 @@
+use axum::{response::IntoResponse, routing::get, Router};
+use leptos::prelude::*;
+use std::sync::Arc;
+
+use crate::{
+    components::list::{AlbumSort, ListAlbums},
+    html,
+    page::Page,
+    view::render,
+    AppState, Zone,
+};
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/local", get(index))
+}
+
+async fn index(Zone(player): Zone) -> impl IntoResponse {
+    let albums = player.local_albums().await;
+
+    render(html! {
+        <Page active_page=Page::None>
+            <div class="flex flex-col h-full">
+                <h1 class="p-4 text-2xl">Local</h1>
+                <div class="overflow-auto h-full">
+                    <ListAlbums albums=albums sort=AlbumSort::Artist />
+                </div>
+            </div>
+        </Page>
+    })
+}
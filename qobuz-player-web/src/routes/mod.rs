@@ -1,8 +1,15 @@
 pub mod album;
 pub mod artist;
 pub mod discover;
+pub mod equalizer;
 pub mod favorites;
+pub mod history;
+pub mod local;
 pub mod now_playing;
+pub mod offline;
 pub mod playlist;
 pub mod queue;
 pub mod search;
+pub mod settings;
+pub mod sleep_timer;
+pub mod zones;
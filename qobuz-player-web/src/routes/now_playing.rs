@@ -1,26 +1,31 @@
 use axum::{
+    extract::State,
     response::IntoResponse,
     routing::{get, post, put},
     Router,
 };
 use leptos::{component, prelude::*, IntoView};
-use qobuz_player_controls::{
-    models,
-    tracklist::{TrackListType, Tracklist},
-};
+use qobuz_player_controls::tracklist::{RepeatMode, TrackListType, Tracklist};
+use std::sync::Arc;
 
 use crate::{
     components::Info,
     html,
-    icons::{Backward, Forward, Pause, Play},
+    icons::{
+        ArrowPathRoundedSquare, ArrowsRightLeft, Backward, Clock, Cog6Tooth, Forward, Moon, Pause,
+        Play, SpeakerWave,
+    },
     page::Page,
+    routes::search,
     view::render,
+    AppState, Zone,
 };
 
-pub fn routes() -> Router {
+pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/", get(index))
         .route("/progress", get(progress_partial))
+        .route("/stream-format", get(stream_format_partial))
         .route("/status", get(status_partial))
         .route("/volume-slider", get(volume_slider_partial))
         .route("/now-playing", get(now_playing_partial))
@@ -28,6 +33,8 @@ pub fn routes() -> Router {
         .route("/pause", put(pause))
         .route("/previous", put(previous))
         .route("/next", put(next))
+        .route("/shuffle", put(shuffle))
+        .route("/repeat", put(repeat))
         .route("/volume", post(set_volume))
 }
 
@@ -36,8 +43,8 @@ struct VolumeParameters {
     volume: i32,
 }
 
-async fn volume_slider_partial() -> impl IntoResponse {
-    let current_volume = (qobuz_player_controls::volume() * 100.0) as u32;
+async fn volume_slider_partial(Zone(player): Zone) -> impl IntoResponse {
+    let current_volume = (player.volume() * 100.0) as u32;
     render(html! { <VolumeSlider current_volume=current_volume /> })
 }
 
@@ -65,7 +72,10 @@ fn volume_slider(current_volume: u32) -> impl IntoView {
     }
 }
 
-async fn set_volume(axum::Form(parameters): axum::Form<VolumeParameters>) -> impl IntoResponse {
+async fn set_volume(
+    Zone(player): Zone,
+    axum::Form(parameters): axum::Form<VolumeParameters>,
+) -> impl IntoResponse {
     let mut volume = parameters.volume;
 
     if volume < 0 {
@@ -78,11 +88,11 @@ async fn set_volume(axum::Form(parameters): axum::Form<VolumeParameters>) -> imp
 
     let formatted_volume = volume as f64 / 100.0;
 
-    qobuz_player_controls::set_volume(formatted_volume);
+    player.set_volume(formatted_volume);
 }
 
-async fn status_partial() -> impl IntoResponse {
-    let status = qobuz_player_controls::current_state();
+async fn status_partial(Zone(player): Zone) -> impl IntoResponse {
+    let status = player.current_state();
 
     if status == qobuz_player_controls::State::Playing {
         render(html! { <PlayPause play=true /> })
@@ -109,70 +119,146 @@ fn play_pause(play: bool) -> impl IntoView {
     }
 }
 
-async fn play() -> impl IntoResponse {
-    match qobuz_player_controls::play().await {
+async fn play(Zone(player): Zone) -> impl IntoResponse {
+    match player.play().await {
         Ok(_) => render(html! { <PlayPause play=true /> }),
         Err(_) => render(html! { <PlayPause play=false /> }),
     }
 }
 
-async fn pause() -> impl IntoResponse {
-    match qobuz_player_controls::pause().await {
+async fn pause(Zone(player): Zone) -> impl IntoResponse {
+    match player.pause().await {
         Ok(_) => render(html! { <PlayPause play=false /> }),
         Err(_) => render(html! { <PlayPause play=true /> }),
     }
 }
 
-async fn previous() -> impl IntoResponse {
-    qobuz_player_controls::previous().await.unwrap();
+async fn previous(Zone(player): Zone) -> impl IntoResponse {
+    player.previous().await.unwrap();
+}
+
+async fn next(Zone(player): Zone) -> impl IntoResponse {
+    player.next().await.unwrap();
+}
+
+async fn shuffle(Zone(player): Zone) -> impl IntoResponse {
+    player.toggle_shuffle().await.unwrap();
 }
 
-async fn next() -> impl IntoResponse {
-    qobuz_player_controls::next().await.unwrap();
+#[component]
+fn shuffle_button(shuffled: bool) -> impl IntoView {
+    html! {
+        <button
+            hx-swap="none"
+            hx-put="shuffle"
+            class=format!(
+                "self-center transition-colors cursor-pointer size-6 {}",
+                if shuffled { "text-blue-500" } else { "text-gray-500" },
+            )
+        >
+            <ArrowsRightLeft />
+        </button>
+    }
+}
+
+async fn repeat(Zone(player): Zone) -> impl IntoResponse {
+    player.cycle_repeat_mode().await.unwrap();
+}
+
+#[component]
+fn repeat_button(repeat_mode: RepeatMode) -> impl IntoView {
+    html! {
+        <button
+            hx-swap="none"
+            hx-put="repeat"
+            class=format!(
+                "relative self-center transition-colors cursor-pointer size-6 {}",
+                if repeat_mode == RepeatMode::None { "text-gray-500" } else { "text-blue-500" },
+            )
+        >
+            <ArrowPathRoundedSquare />
+            {(repeat_mode == RepeatMode::Track)
+                .then_some(
+                    html! {
+                        <span class="absolute -top-2 -right-2 text-xs font-bold">1</span>
+                    },
+                )}
+        </button>
+    }
 }
 
-async fn index() -> impl IntoResponse {
-    let current_tracklist = qobuz_player_controls::current_tracklist().await;
-    let current_track = qobuz_player_controls::current_track().await.unwrap();
-    let position_mseconds = qobuz_player_controls::position().map(|position| position.mseconds());
-    let current_status = qobuz_player_controls::current_state();
-    let current_volume = (qobuz_player_controls::volume() * 100.0) as u32;
+async fn index(State(state): State<Arc<AppState>>, Zone(player): Zone) -> impl IntoResponse {
+    let current_tracklist = state.tracklist(&player);
+    let position_mseconds = player.position().map(|position| position.mseconds());
+    let current_status = player.current_state();
+    let current_volume = (player.volume() * 100.0) as u32;
+    let stream_format = player
+        .stream_format()
+        .await
+        .map(|format| format.to_string());
+    let sleep_timer = player.sleep_timer().await.map(|timer| timer.to_string());
+    let zone = (state.players.len() > 1).then(|| player.zone().to_string());
 
     render(html! {
         <Page active_page=Page::NowPlaying>
             <NowPlaying
                 current_tracklist=current_tracklist
-                current_track=current_track
                 position_mseconds=position_mseconds
                 current_status=current_status
                 current_volume=current_volume
+                stream_format=stream_format
+                sleep_timer=sleep_timer
+                zone=zone
             />
         </Page>
     })
 }
 
-async fn now_playing_partial() -> impl IntoResponse {
-    let current_tracklist = qobuz_player_controls::current_tracklist().await;
-    let current_track = qobuz_player_controls::current_track().await.unwrap();
-    let position_mseconds = qobuz_player_controls::position().map(|position| position.mseconds());
-    let current_status = qobuz_player_controls::current_state();
-    let current_volume = (qobuz_player_controls::volume() * 100.0) as u32;
+async fn now_playing_partial(
+    State(state): State<Arc<AppState>>,
+    Zone(player): Zone,
+) -> impl IntoResponse {
+    let current_tracklist = state.tracklist(&player);
+    let position_mseconds = player.position().map(|position| position.mseconds());
+    let current_status = player.current_state();
+    let current_volume = (player.volume() * 100.0) as u32;
+    let stream_format = player
+        .stream_format()
+        .await
+        .map(|format| format.to_string());
+    let sleep_timer = player.sleep_timer().await.map(|timer| timer.to_string());
+    let zone = (state.players.len() > 1).then(|| player.zone().to_string());
 
     render(html! {
         <NowPlaying
             current_tracklist=current_tracklist
-            current_track=current_track
             position_mseconds=position_mseconds
             current_status=current_status
             current_volume=current_volume
+            stream_format=stream_format
+            sleep_timer=sleep_timer
+            zone=zone
         />
     })
 }
 
-async fn progress_partial() -> impl IntoResponse {
-    let position_mseconds = qobuz_player_controls::position().map(|position| position.mseconds());
-    let current_track = qobuz_player_controls::current_track().await;
-    let duration_seconds = current_track.unwrap().map(|track| track.duration_seconds);
+async fn stream_format_partial(Zone(player): Zone) -> impl IntoResponse {
+    let stream_format = player
+        .stream_format()
+        .await
+        .map(|format| format.to_string());
+
+    render(html! { {stream_format} })
+}
+
+async fn progress_partial(
+    State(state): State<Arc<AppState>>,
+    Zone(player): Zone,
+) -> impl IntoResponse {
+    let position_mseconds = player.position().map(|position| position.mseconds());
+    let duration_seconds = state
+        .current_track(&player)
+        .map(|track| track.duration_seconds);
 
     render(
         html! { <Progress position_seconds=position_mseconds duration_seconds=duration_seconds /> },
@@ -207,18 +293,15 @@ fn progress(position_seconds: Option<u64>, duration_seconds: Option<u32>) -> imp
 #[component]
 pub fn now_playing(
     current_tracklist: Tracklist,
-    current_track: Option<models::Track>,
     position_mseconds: Option<u64>,
     current_status: qobuz_player_controls::State,
     current_volume: u32,
+    stream_format: Option<String>,
+    sleep_timer: Option<String>,
+    zone: Option<String>,
 ) -> impl IntoView {
-    let cover_image = current_track.as_ref().map(|track| track.cover_art.clone());
-    let album_artist_name = current_track
-        .as_ref()
-        .and_then(|track| track.album.as_ref().map(|album| album.artist.name.clone()));
-    let album_artist_id = current_track
-        .as_ref()
-        .and_then(|track| track.album.as_ref().map(|album| album.artist.id));
+    let current_track = current_tracklist.current_track();
+    let cover_image = current_track.and_then(|track| track.cover_art.clone());
 
     let current_position = current_tracklist.current_position();
 
@@ -231,27 +314,38 @@ pub fn now_playing(
             Some(playlist.title.clone()),
             Some(format!("/playlist/{}", playlist.id)),
         ),
-        TrackListType::Track => (
-            current_track
-                .as_ref()
-                .and_then(|track| track.album.as_ref().map(|album| album.title.clone())),
+        TrackListType::ArtistTopTracks(artist) => (
+            Some(format!("{} top tracks", artist.name)),
+            Some(format!("/artist/{}", artist.id)),
+        ),
+        TrackListType::ArtistRadio(artist) => (
+            Some(format!("{} radio", artist.name)),
+            Some(format!("/artist/{}", artist.id)),
+        ),
+        TrackListType::Search(query) => (
+            Some(format!("Search: {}", query)),
+            Some(format!("/search/tracks?{}", search::query_string(query))),
+        ),
+        TrackListType::Favorites => (
+            Some("Favorites".to_string()),
+            Some("/favorites/tracks".to_string()),
+        ),
+        TrackListType::Custom | TrackListType::Track => (
+            current_track.and_then(|track| track.album_title.clone()),
             current_track
-                .as_ref()
-                .and_then(|track| track.album.as_ref().map(|album| album.id.clone())),
+                .and_then(|track| track.album_id.as_ref())
+                .map(|id| format!("/album/{}", id)),
         ),
     };
 
     let (title, artist_name, artist_link, duration_seconds, explicit, hires_available) =
-        current_track.as_ref().map_or(
+        current_track.map_or(
             (String::default(), None, None, None, false, false),
             |track| {
                 (
                     track.title.clone(),
-                    album_artist_name
-                        .or(track.album.as_ref().map(|album| album.artist.name.clone())),
-                    album_artist_id
-                        .or(track.album.as_ref().map(|album| album.artist.id))
-                        .map(|id| format!("/artist/{}", id)),
+                    track.artist_name.clone(),
+                    track.artist_id.map(|id| format!("/artist/{}", id)),
                     Some(track.duration_seconds),
                     track.explicit,
                     track.hires_available,
@@ -260,6 +354,8 @@ pub fn now_playing(
         );
 
     let number_of_tracks = current_tracklist.total();
+    let shuffled = current_tracklist.is_shuffled();
+    let repeat_mode = current_tracklist.repeat_mode;
 
     html! {
         <div
@@ -269,6 +365,41 @@ pub fn now_playing(
             hx-trigger="sse:tracklist"
             hx-swap="outerHTML"
         >
+            <div class="flex justify-between items-center text-sm text-gray-400">
+                <span>
+                    {zone
+                        .map(|zone| {
+                            html! {
+                                <a href="/zones" class="flex gap-2 items-center">
+                                    <span class="size-5">
+                                        <SpeakerWave />
+                                    </span>
+                                    {zone}
+                                </a>
+                            }
+                        })}
+                </span>
+                <span class="flex gap-4 items-center">
+                    <a href="/sleep-timer" class="flex gap-2 items-center" aria-label="Sleep timer">
+                        <span
+                            hx-get="/sleep-timer/countdown"
+                            hx-trigger="sse:position, sse:sleep-timer"
+                            hx-swap="innerHTML"
+                        >
+                            {sleep_timer}
+                        </span>
+                        <span class="size-5">
+                            <Moon />
+                        </span>
+                    </a>
+                    <a href="/history" class="size-5" aria-label="History">
+                        <Clock />
+                    </a>
+                    <a href="/settings" class="size-5" aria-label="Settings">
+                        <Cog6Tooth />
+                    </a>
+                </span>
+            </div>
 
             <div class="flex overflow-hidden justify-center w-full aspect-square">
 
@@ -302,7 +433,17 @@ pub fn now_playing(
                 <div class="flex flex-col gap-y-4 w-full">
                     <div class="flex gap-2 justify-between items-center">
                         <span class="text-lg truncate">{title}</span>
-                        <Info explicit=explicit hires_available=hires_available />
+                        <div class="flex gap-2 items-center">
+                            <span
+                                class="text-sm text-gray-500 whitespace-nowrap"
+                                hx-get="stream-format"
+                                hx-trigger="sse:format"
+                                hx-swap="innerHTML"
+                            >
+                                {stream_format}
+                            </span>
+                            <Info explicit=explicit hires_available=hires_available />
+                        </div>
                     </div>
 
                     <div hx-get="progress" hx-trigger="sse:position" hx-swap="innerHTML">
@@ -315,6 +456,7 @@ pub fn now_playing(
 
                 <div class="flex flex-col gap-4">
                     <div class="flex flex-row gap-2 justify-center h-10">
+                        <ShuffleButton shuffled=shuffled />
                         <button
                             hx-swap="none"
                             hx-put="previous"
@@ -342,6 +484,7 @@ pub fn now_playing(
                         >
                             <Forward />
                         </button>
+                        <RepeatButton repeat_mode=repeat_mode />
                     </div>
                     <VolumeSlider current_volume=current_volume />
                 </div>
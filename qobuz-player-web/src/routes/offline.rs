@@ -0,0 +1,138 @@
+use axum::{
+    extract::Path,
+    response::IntoResponse,
+    routing::{get, put},
+    Router,
+};
+use leptos::{component, prelude::*, IntoView};
+use qobuz_player_controls::{
+    models::TrackStatus,
+    offline::OfflineCollection,
+    tracklist::{AlbumTracklist, PlaylistTracklist, TrackListType},
+};
+use std::sync::Arc;
+
+use crate::{
+    components::list::{List, ListItem},
+    html,
+    page::Page,
+    view::render,
+    AppState, Zone,
+};
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/offline", get(index))
+        .route("/offline/album/{id}/play/{index}", put(play_album))
+        .route("/offline/playlist/{id}/play/{index}", put(play_playlist))
+}
+
+async fn play_album(
+    Zone(player): Zone,
+    Path((id, index)): Path<(String, u32)>,
+) -> impl IntoResponse {
+    let list_type = TrackListType::Album(AlbumTracklist {
+        id,
+        ..Default::default()
+    });
+
+    player.play_offline(&list_type, index).await.unwrap();
+}
+
+async fn play_playlist(
+    Zone(player): Zone,
+    Path((id, index)): Path<(i64, u32)>,
+) -> impl IntoResponse {
+    let list_type = TrackListType::Playlist(PlaylistTracklist {
+        id,
+        ..Default::default()
+    });
+
+    player.play_offline(&list_type, index).await.unwrap();
+}
+
+async fn index(Zone(player): Zone) -> impl IntoResponse {
+    let collections = player.offline_collections().await;
+
+    render(html! {
+        <Page active_page=Page::None>
+            <div class="flex flex-col gap-4 p-4 mx-auto max-w-screen-sm">
+                <p class="text-lg text-center">Offline</p>
+                {collections
+                    .is_empty()
+                    .then(|| {
+                        html! {
+                            <p class="text-center text-gray-500">
+                                Albums and playlists kept offline show up here.
+                            </p>
+                        }
+                    })}
+                {collections
+                    .into_iter()
+                    .map(|collection| html! { <Collection collection=collection /> })
+                    .collect::<Vec<_>>()}
+            </div>
+        </Page>
+    })
+}
+
+#[component]
+fn collection(collection: OfflineCollection) -> impl IntoView {
+    let (title, play_url) = match &collection.list_type {
+        TrackListType::Album(album) => {
+            (album.title.clone(), format!("/offline/album/{}", album.id))
+        }
+        TrackListType::Playlist(playlist) => (
+            playlist.title.clone(),
+            format!("/offline/playlist/{}", playlist.id),
+        ),
+        _ => return None,
+    };
+
+    Some(html! {
+        <div class="flex flex-col gap-2">
+            <div class="flex gap-4 items-center">
+                <div
+                    class="bg-gray-800 bg-center bg-no-repeat bg-cover rounded-md aspect-square size-12 shrink-0"
+                    style=collection
+                        .cover_art
+                        .as_ref()
+                        .map(|url| format!("background-image: url({});", url))
+                ></div>
+                <p class="text-lg truncate">{title}</p>
+            </div>
+            <List>
+                {collection
+                    .tracks
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, track)| {
+                        let downloaded = track.status != TrackStatus::Unplayable;
+                        let play_url = format!("{}/play/{}", play_url, index);
+
+                        html! {
+                            <ListItem>
+                                <button
+                                    hx-swap="none"
+                                    hx-put=play_url
+                                    disabled=!downloaded
+                                    class="flex flex-row gap-4 items-center w-full text-left cursor-pointer disabled:text-gray-500 disabled:cursor-default"
+                                >
+                                    <div class="flex overflow-hidden flex-col">
+                                        <span class="truncate">{track.title}</span>
+                                        <span class="text-sm text-gray-400 truncate">
+                                            {track.artist_name}
+                                        </span>
+                                    </div>
+                                    <span class="ml-auto text-sm text-gray-500 whitespace-nowrap">
+                                        {if downloaded { "" } else { "Not downloaded yet" }}
+                                    </span>
+                                </button>
+                            </ListItem>
+                        }
+                    })
+                    .collect::<Vec<_>>()}
+            </List>
+        </div>
+    })
+}
@@ -1,65 +1,90 @@
 use axum::{
     extract::Path,
-    response::IntoResponse,
+    response::{IntoResponse, Redirect},
     routing::{get, put},
     Router,
 };
 use leptos::prelude::*;
-use qobuz_player_controls::models::{Playlist, Track};
+use qobuz_player_controls::{
+    error::Error,
+    models::{Playlist, Track},
+    tracklist::{PlaylistTracklist, TrackListType},
+};
+use std::sync::Arc;
 use tokio::join;
 
 use crate::{
     components::{
         list::{ListTracks, TrackNumberDisplay},
-        parse_duration, ToggleFavorite,
+        parse_duration, ToggleFavorite, ToggleOffline,
     },
     html,
     icons::Play,
     page::Page,
     view::render,
+    AppState, Zone,
 };
 
-pub fn routes() -> Router {
+pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/playlist/{id}", get(index))
         .route("/playlist/{id}/tracks", get(tracks_partial))
         .route("/playlist/{id}/set-favorite", put(set_favorite))
         .route("/playlist/{id}/unset-favorite", put(unset_favorite))
+        .route("/playlist/{id}/set-offline", put(set_offline))
+        .route("/playlist/{id}/unset-offline", put(unset_offline))
         .route("/playlist/{id}/play", put(play))
         .route("/playlist/{id}/play/{track_position}", put(play_track))
 }
 
-async fn play_track(Path((id, track_position)): Path<(i64, u32)>) -> impl IntoResponse {
-    qobuz_player_controls::play_playlist(id, track_position)
-        .await
-        .unwrap();
+async fn play_track(
+    Zone(player): Zone,
+    Path((id, track_position)): Path<(i64, u32)>,
+) -> impl IntoResponse {
+    player.play_playlist(id, track_position).await.unwrap();
 }
 
-async fn play(Path(id): Path<i64>) -> impl IntoResponse {
-    qobuz_player_controls::play_playlist(id, 0).await.unwrap();
+async fn play(Zone(player): Zone, Path(id): Path<i64>) -> impl IntoResponse {
+    player.play_playlist(id, 0).await.unwrap();
 }
 
-async fn set_favorite(Path(id): Path<String>) -> impl IntoResponse {
-    qobuz_player_controls::add_favorite_playlist(&id)
-        .await
-        .unwrap();
+async fn set_favorite(Zone(player): Zone, Path(id): Path<String>) -> impl IntoResponse {
+    player.add_favorite_playlist(&id).await.unwrap();
     render(html! { <ToggleFavorite id=id is_favorite=true /> })
 }
 
-async fn unset_favorite(Path(id): Path<String>) -> impl IntoResponse {
-    qobuz_player_controls::remove_favorite_playlist(&id)
-        .await
-        .unwrap();
+async fn unset_favorite(Zone(player): Zone, Path(id): Path<String>) -> impl IntoResponse {
+    player.remove_favorite_playlist(&id).await.unwrap();
     render(html! { <ToggleFavorite id=id is_favorite=false /> })
 }
 
-async fn index(Path(id): Path<i64>) -> impl IntoResponse {
-    let (playlist, tracklist, favorites) = join!(
-        qobuz_player_controls::playlist(id),
-        qobuz_player_controls::current_tracklist(),
-        qobuz_player_controls::favorites()
+async fn set_offline(Zone(player): Zone, Path(id): Path<i64>) -> impl IntoResponse {
+    player.set_playlist_offline(id, true).await.unwrap();
+    render(html! { <ToggleOffline id=id.to_string() is_offline=true /> })
+}
+
+async fn unset_offline(Zone(player): Zone, Path(id): Path<i64>) -> impl IntoResponse {
+    player.set_playlist_offline(id, false).await.unwrap();
+    render(html! { <ToggleOffline id=id.to_string() is_offline=false /> })
+}
+
+async fn index(Zone(player): Zone, Path(id): Path<i64>) -> impl IntoResponse {
+    let list_type = TrackListType::Playlist(PlaylistTracklist {
+        id,
+        ..Default::default()
+    });
+    let (playlist, tracklist, favorites, is_offline) = join!(
+        player.playlist(id),
+        player.current_tracklist(),
+        player.favorites(),
+        player.is_offline(&list_type)
     );
 
+    // Without Qobuz only what is kept offline can be shown.
+    if let (Err(Error::Offline), _) | (_, Err(Error::Offline)) = (&playlist, &favorites) {
+        return Redirect::to("/offline").into_response();
+    }
+
     let playlist = playlist.unwrap();
     let favorites = favorites.unwrap();
 
@@ -71,16 +96,18 @@ async fn index(Path(id): Path<i64>) -> impl IntoResponse {
 
     render(html! {
         <Page active_page=Page::None>
-            <Playlist playlist=playlist is_favorite=is_favorite now_playing_id=now_playing_id />
+            <Playlist
+                playlist=playlist
+                is_favorite=is_favorite
+                is_offline=is_offline
+                now_playing_id=now_playing_id
+            />
         </Page>
     })
 }
 
-async fn tracks_partial(Path(id): Path<i64>) -> impl IntoResponse {
-    let (playlist, tracklist) = join!(
-        qobuz_player_controls::playlist(id),
-        qobuz_player_controls::current_tracklist(),
-    );
+async fn tracks_partial(Zone(player): Zone, Path(id): Path<i64>) -> impl IntoResponse {
+    let (playlist, tracklist) = join!(player.playlist(id), player.current_tracklist(),);
     let playlist = playlist.unwrap();
 
     let now_playing_id = tracklist.currently_playing();
@@ -111,7 +138,12 @@ fn tracks(now_playing_id: Option<u32>, tracks: Vec<Track>, playlist_id: u32) ->
 }
 
 #[component]
-fn playlist(playlist: Playlist, is_favorite: bool, now_playing_id: Option<u32>) -> impl IntoView {
+fn playlist(
+    playlist: Playlist,
+    is_favorite: bool,
+    is_offline: bool,
+    now_playing_id: Option<u32>,
+) -> impl IntoView {
     let duration = parse_duration(playlist.duration_seconds);
 
     html! {
@@ -136,7 +168,11 @@ fn playlist(playlist: Playlist, is_favorite: bool, now_playing_id: Option<u32>)
                     {
                         let is_not_owned = !playlist.is_owned;
                         html! {
-                            <div class=is_not_owned.then_some({ "grid grid-cols-2 gap-4" })>
+                            <div class=if is_not_owned {
+                                "grid grid-cols-3 gap-4"
+                            } else {
+                                "grid grid-cols-2 gap-4"
+                            }>
                                 <button
                                     class="flex gap-2 justify-center items-center py-2 px-4 bg-blue-500 rounded cursor-pointer"
                                     hx-swap="none"
@@ -158,6 +194,7 @@ fn playlist(playlist: Playlist, is_favorite: bool, now_playing_id: Option<u32>)
                                         }
                                     })}
 
+                                <ToggleOffline id=playlist.id.to_string() is_offline=is_offline />
                             </div>
                         }
                     }
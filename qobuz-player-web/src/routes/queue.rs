@@ -1,7 +1,7 @@
 use axum::{
-    extract::Path,
+    extract::{Path, State},
     response::IntoResponse,
-    routing::{get, put},
+    routing::{delete, get, put},
     Router,
 };
 use leptos::{component, prelude::*, IntoView};
@@ -9,29 +9,57 @@ use qobuz_player_controls::{
     models::TrackStatus,
     tracklist::{TrackListType, Tracklist},
 };
+use std::sync::Arc;
 
 use crate::{
     components::list::{List, ListItem},
     html,
     page::Page,
     view::render,
+    AppState, Zone,
 };
 
-pub fn routes() -> Router {
+pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/queue", get(index))
         .route("/queue/list", get(queue_partial))
         .route("/queue/skip-to/{track_number}", put(skip_to))
+        .route("/queue/{position}", delete(remove))
+        .route("/queue/autoplay", put(autoplay))
 }
 
-async fn skip_to(Path(track_number): Path<u32>) -> impl IntoResponse {
-    qobuz_player_controls::skip_to_position(track_number, true)
-        .await
-        .unwrap();
+async fn skip_to(Zone(player): Zone, Path(track_number): Path<u32>) -> impl IntoResponse {
+    player.skip_to_position(track_number, true).await.unwrap();
 }
 
-async fn index() -> impl IntoResponse {
-    let current_tracklist = qobuz_player_controls::current_tracklist().await;
+async fn remove(Zone(player): Zone, Path(position): Path<u32>) -> impl IntoResponse {
+    player.remove_from_queue(position).await.unwrap();
+}
+
+async fn autoplay(Zone(player): Zone) -> impl IntoResponse {
+    player.toggle_autoplay().await.unwrap();
+
+    render(html! { <AutoplayButton autoplay=player.autoplay().await /> })
+}
+
+#[component]
+fn autoplay_button(autoplay: bool) -> impl IntoView {
+    html! {
+        <button
+            hx-put="/queue/autoplay"
+            hx-swap="outerHTML"
+            class=format!(
+                "text-sm transition-colors cursor-pointer {}",
+                if autoplay { "text-blue-500" } else { "text-gray-500" },
+            )
+        >
+            Autoplay
+        </button>
+    }
+}
+
+async fn index(State(state): State<Arc<AppState>>, Zone(player): Zone) -> impl IntoResponse {
+    let current_tracklist = state.tracklist(&player);
 
     render(html! {
         <Page active_page=Page::Queue>
@@ -45,8 +73,13 @@ fn queue(current_tracklist: Tracklist) -> impl IntoView {
     let entity_title = match current_tracklist.list_type() {
         TrackListType::Album(album) => Some(album.title.clone()),
         TrackListType::Playlist(playlist) => Some(playlist.title.clone()),
-        TrackListType::Track => None,
+        TrackListType::ArtistTopTracks(artist) => Some(format!("{} top tracks", artist.name)),
+        TrackListType::ArtistRadio(artist) => Some(format!("{} radio", artist.name)),
+        TrackListType::Search(query) => Some(format!("Search: {}", query)),
+        TrackListType::Favorites => Some("Favorites".to_string()),
+        TrackListType::Custom | TrackListType::Track => None,
     };
+    let autoplay = current_tracklist.autoplay;
 
     html! {
         <div
@@ -55,8 +88,9 @@ fn queue(current_tracklist: Tracklist) -> impl IntoView {
             hx-target="#queue-list"
             class="flex flex-col flex-grow gap-4 max-h-full"
         >
-            <div class="p-4 text-center">
+            <div class="flex flex-col gap-2 items-center p-4">
                 <p class="text-lg">{entity_title}</p>
+                <AutoplayButton autoplay=autoplay />
             </div>
 
             <div id="queue-list">
@@ -66,14 +100,26 @@ fn queue(current_tracklist: Tracklist) -> impl IntoView {
     }
 }
 
-async fn queue_partial() -> impl IntoResponse {
-    let current_tracklist = qobuz_player_controls::current_tracklist().await;
+async fn queue_partial(
+    State(state): State<Arc<AppState>>,
+    Zone(player): Zone,
+) -> impl IntoResponse {
+    let current_tracklist = state.tracklist(&player);
 
     render(html! { <QueueList current_tracklist=current_tracklist /> })
 }
 
 #[component]
 fn queue_list(current_tracklist: Tracklist) -> impl IntoView {
+    // Albums on several discs get a heading before each disc, unless the order is shuffled.
+    let show_discs = matches!(current_tracklist.list_type, TrackListType::Album(_))
+        && !current_tracklist.is_shuffled()
+        && current_tracklist
+            .queue
+            .iter()
+            .any(|track| track.disc_number > 1);
+    let mut disc = 0;
+
     html! {
         <List>
             {current_tracklist
@@ -81,19 +127,30 @@ fn queue_list(current_tracklist: Tracklist) -> impl IntoView {
                 .into_iter()
                 .enumerate()
                 .map(|(position, track)| {
+                    let disc_heading = (show_discs && track.disc_number != disc)
+                        .then(|| {
+                            html! {
+                                <li class="px-4 pt-6 pb-2 text-sm text-gray-500 border-b border-gray-700">
+                                    {format!("Disc {}", track.disc_number)}
+                                </li>
+                            }
+                        });
+                    disc = track.disc_number;
+
                     html! {
+                        {disc_heading}
                         <ListItem>
                             <button
                                 hx-swap="none"
                                 hx-put=format!("/queue/skip-to/{}", position)
+                                disabled=track.status == TrackStatus::Unplayable
                                 class=format!(
-                                    "cursor-pointer flex w-full items-center flex-row gap-4 text-left {}",
-                                    if track.status == TrackStatus::Playing {
-                                        "bg-blue-800"
-                                    } else if track.status == TrackStatus::Played {
-                                        "text-gray-500"
-                                    } else {
-                                        ""
+                                    "cursor-pointer disabled:cursor-default flex w-full items-center flex-row gap-4 text-left {}",
+                                    match track.status {
+                                        TrackStatus::Playing => "bg-blue-800",
+                                        TrackStatus::Played => "text-gray-500",
+                                        TrackStatus::Unplayable => "text-gray-500 line-through",
+                                        TrackStatus::Unplayed => "",
                                     },
                                 )
                             >
@@ -101,7 +158,41 @@ fn queue_list(current_tracklist: Tracklist) -> impl IntoView {
                                     <span class="text-gray-400">{position + 1}</span>
                                 </span>
 
-                                <span class="truncate">{track.title.clone()}</span>
+                                <div
+                                    class="bg-gray-800 bg-center bg-no-repeat bg-cover rounded-md aspect-square size-10 shrink-0"
+                                    style=track
+                                        .cover_art_small
+                                        .as_ref()
+                                        .map(|url| format!("background-image: url({});", url))
+                                ></div>
+
+                                <div class="flex overflow-hidden flex-col">
+                                    <span class="truncate">{track.title.clone()}</span>
+                                    <span class="text-sm text-gray-400 truncate">
+                                        {track.artist_name.clone()}
+                                    </span>
+                                </div>
+
+                                <span class="flex gap-4 items-center ml-auto text-sm text-gray-500 whitespace-nowrap">
+                                    {(track.added_by_autoplay && track.status == TrackStatus::Unplayed)
+                                        .then(|| {
+                                            html! {
+                                                <span class="flex gap-2 items-center">
+                                                    Autoplay
+                                                    <span
+                                                        class="px-1 hover:text-white"
+                                                        aria-label="Remove"
+                                                        hx-delete=format!("/queue/{}", position)
+                                                        hx-trigger="click consume"
+                                                        hx-swap="none"
+                                                    >
+                                                        "\u{2715}"
+                                                    </span>
+                                                </span>
+                                            }
+                                        })}
+                                    {format_duration(track.duration_seconds)}
+                                </span>
                             </button>
                         </ListItem>
                     }
@@ -110,3 +201,7 @@ fn queue_list(current_tracklist: Tracklist) -> impl IntoView {
         </List>
     }
 }
+
+fn format_duration(seconds: u32) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
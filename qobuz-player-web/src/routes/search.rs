@@ -5,8 +5,12 @@ use axum::{
     Form, Router,
 };
 use leptos::{component, prelude::*};
-use qobuz_player_controls::models::{self, SearchResults};
+use qobuz_player_controls::{
+    models::{self, SearchResults},
+    tracklist::TrackListType,
+};
 use serde::Deserialize;
+use std::sync::Arc;
 
 #[derive(Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -26,16 +30,31 @@ use crate::{
     icons::MagnifyingGlass,
     page::Page,
     view::render,
+    AppState, Zone,
 };
 
-pub fn routes() -> Router {
+pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/search/{tab}", get(index).post(search))
-        .route("/play-track/{track_id}", put(play_track))
+        .route("/search/play-track/{index}", put(play_track))
 }
 
-async fn play_track(Path(track_id): Path<u32>) -> impl IntoResponse {
-    qobuz_player_controls::play_track(track_id).await.unwrap();
+/// Plays the listed tracks, as they were shown, since searching again may give other results.
+async fn play_track(
+    Zone(player): Zone,
+    Path(index): Path<u32>,
+    Query(parameters): Query<PlayTrackParameters>,
+) -> impl IntoResponse {
+    let track_ids: Vec<u32> = parameters
+        .tracks
+        .split(',')
+        .filter_map(|id| id.parse().ok())
+        .collect();
+
+    player
+        .play_tracks(&track_ids, index, TrackListType::Search(parameters.query))
+        .await
+        .unwrap();
 }
 
 #[derive(Deserialize, Clone)]
@@ -43,7 +62,22 @@ struct SearchParameters {
     query: Option<String>,
 }
 
+#[derive(Deserialize, Clone)]
+struct PlayTrackParameters {
+    query: String,
+    /// Comma separated ids of the tracks in the search results.
+    tracks: String,
+}
+
+/// The query string of a search url, with the query percent-encoded.
+pub fn query_string(query: &str) -> String {
+    url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("query", query)
+        .finish()
+}
+
 async fn index(
+    Zone(player): Zone,
     Path(tab): Path<Tab>,
     Query(parameters): Query<SearchParameters>,
 ) -> impl IntoResponse {
@@ -51,7 +85,7 @@ async fn index(
         .query
         .and_then(|s| if s.is_empty() { None } else { Some(s) });
     let search_results = match &query {
-        Some(query) => qobuz_player_controls::search(query).await.unwrap(),
+        Some(query) => player.search(query).await.unwrap(),
         None => SearchResults::default(),
     };
 
@@ -65,6 +99,7 @@ async fn index(
 }
 
 async fn search(
+    Zone(player): Zone,
     Path(tab): Path<Tab>,
     Form(parameters): Form<SearchParameters>,
 ) -> impl IntoResponse {
@@ -72,7 +107,7 @@ async fn search(
         .query
         .and_then(|s| if s.is_empty() { None } else { Some(s) });
     let search_results = match &query {
-        Some(query) => qobuz_player_controls::search(query).await.unwrap(),
+        Some(query) => player.search(query).await.unwrap(),
         None => SearchResults::default(),
     };
 
@@ -109,20 +144,35 @@ fn search_partial(search_results: SearchResults, tab: Tab) -> impl IntoView {
             />
         }
         .into_any(),
-        Tab::Tracks => html! { <ListTracks tracks=search_results.tracks /> }.into_any(),
+        Tab::Tracks => html! {
+            <ListTracks query=search_results.query tracks=search_results.tracks />
+        }
+        .into_any(),
     }
 }
 
 #[component]
-fn list_tracks(tracks: Vec<models::Track>) -> impl IntoView {
+fn list_tracks(query: String, tracks: Vec<models::Track>) -> impl IntoView {
+    let track_ids = tracks
+        .iter()
+        .map(|track| track.id.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let play_query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("query", &query)
+        .append_pair("tracks", &track_ids)
+        .finish();
+
     html! {
         <List>
             {tracks
                 .into_iter()
-                .map(|track| {
+                .enumerate()
+                .map(|(index, track)| {
+                    let play_url = format!("/search/play-track/{}?{}", index, play_query);
                     html! {
                         <ListItem>
-                            <Track track=track />
+                            <Track track=track play_url=play_url />
                         </ListItem>
                     }
                 })
@@ -132,11 +182,11 @@ fn list_tracks(tracks: Vec<models::Track>) -> impl IntoView {
 }
 
 #[component]
-fn track(track: models::Track) -> impl IntoView {
+fn track(track: models::Track, play_url: String) -> impl IntoView {
     html! {
         <button
             class="flex gap-4 items-center w-full cursor-pointer"
-            hx-put=format!("/play-track/{}", track.id)
+            hx-put=play_url
             hx-swap="none"
         >
             <img
@@ -172,7 +222,7 @@ fn tab_bar(query: String, tab: Tab) -> impl IntoView {
         >
             {html! {
                 <a
-                    href=format!("albums?query={}", query)
+                    href=format!("albums?{}", query_string(&query))
                     class=(tab == Tab::Albums).then_some("bg-blue-800")
                 >
 
@@ -183,7 +233,7 @@ fn tab_bar(query: String, tab: Tab) -> impl IntoView {
                 .attr("preload-images", "true")}
             {html! {
                 <a
-                    href=format!("artists?query={}", query)
+                    href=format!("artists?{}", query_string(&query))
                     class=(tab == Tab::Artists).then_some("bg-blue-800")
                 >
                     Artists
@@ -193,7 +243,7 @@ fn tab_bar(query: String, tab: Tab) -> impl IntoView {
                 .attr("preload-images", "true")}
             {html! {
                 <a
-                    href=format!("playlists?query={}", query)
+                    href=format!("playlists?{}", query_string(&query))
                     class=(tab == Tab::Playlists).then_some("bg-blue-800")
                 >
                     Playlists
@@ -203,7 +253,7 @@ fn tab_bar(query: String, tab: Tab) -> impl IntoView {
                 .attr("preload-images", "true")}
             {html! {
                 <a
-                    href=format!("tracks?query={}", query)
+                    href=format!("tracks?{}", query_string(&query))
                     class=(tab == Tab::Tracks).then_some("bg-blue-800")
                 >
                     Tracks
@@ -0,0 +1,281 @@
+use axum::{
+    extract::State,
+    response::IntoResponse,
+    routing::{get, post},
+    Form, Router,
+};
+use leptos::{component, prelude::*, IntoView};
+use qobuz_player_controls::{
+    fade::{Fades, MAX_CROSSFADE},
+    output::{self, Output},
+    replaygain::{ReplayGain, ReplayGainMode},
+    Player,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{html, page::Page, view::render, AppState, Zone};
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/settings", get(index))
+        .route("/settings/output", post(set_output))
+        .route("/settings/replay-gain", post(set_replay_gain))
+        .route("/settings/fades", post(set_fades))
+}
+
+#[derive(Deserialize)]
+struct OutputParameters {
+    output: String,
+    bit_perfect: Option<String>,
+}
+
+async fn set_output(
+    Zone(player): Zone,
+    Form(parameters): Form<OutputParameters>,
+) -> impl IntoResponse {
+    let bit_perfect = parameters.bit_perfect.is_some();
+
+    // The form is posted on every change, so only one of the two differs.
+    let error = match parameters.output.parse::<Output>() {
+        Ok(output) if output != player.output().await => player
+            .set_output(output)
+            .await
+            .err()
+            .map(|error| error.to_string()),
+        Ok(_) => player
+            .set_bit_perfect(bit_perfect)
+            .await
+            .err()
+            .map(|error| error.to_string()),
+        Err(error) => Some(error),
+    };
+
+    let (outputs, current) = outputs(&player).await;
+
+    render(html! {
+        <OutputSettings
+            outputs=outputs
+            current=current
+            bit_perfect=player.bit_perfect()
+            error=error
+        />
+    })
+}
+
+/// The outputs to choose from, which include the current one even when it was given by name.
+async fn outputs(player: &Player) -> (Vec<Output>, Output) {
+    let current = player.output().await;
+    let mut outputs = output::available().unwrap_or_default();
+
+    if !outputs.contains(&current) {
+        outputs.push(current.clone());
+    }
+
+    (outputs, current)
+}
+
+#[derive(Deserialize)]
+struct ReplayGainParameters {
+    mode: String,
+    preamp: f64,
+    prevent_clipping: Option<String>,
+}
+
+async fn set_replay_gain(
+    State(state): State<Arc<AppState>>,
+    Form(parameters): Form<ReplayGainParameters>,
+) -> impl IntoResponse {
+    let replay_gain = ReplayGain {
+        mode: parameters.mode.parse().unwrap_or_default(),
+        preamp: parameters.preamp.clamp(-15.0, 15.0),
+        prevent_clipping: parameters.prevent_clipping.is_some(),
+    };
+
+    for player in &state.players {
+        player.set_replay_gain(replay_gain).await.unwrap();
+    }
+
+    render(html! { <ReplayGainSettings replay_gain=replay_gain /> })
+}
+
+#[derive(Deserialize)]
+struct FadesParameters {
+    crossfade: f64,
+    soft: Option<String>,
+}
+
+async fn set_fades(
+    State(state): State<Arc<AppState>>,
+    Form(parameters): Form<FadesParameters>,
+) -> impl IntoResponse {
+    let fades = Fades::new(parameters.crossfade, parameters.soft.is_some());
+
+    for player in &state.players {
+        player.set_fades(fades).await.unwrap();
+    }
+
+    render(html! { <FadeSettings fades=fades /> })
+}
+
+async fn index(Zone(player): Zone) -> impl IntoResponse {
+    let replay_gain = player.replay_gain().await;
+    let fades = player.fades().await;
+    let (outputs, current) = outputs(&player).await;
+    let equalizer_preset = player.equalizer().await.preset_name().unwrap_or("Custom");
+
+    render(html! {
+        <Page active_page=Page::None>
+            <div class="flex flex-col gap-4 p-4 mx-auto max-w-screen-sm">
+                <p class="text-lg text-center">Settings</p>
+                <OutputSettings
+                    outputs=outputs
+                    current=current
+                    bit_perfect=player.bit_perfect()
+                    error=None
+                />
+                <a href="/equalizer" class="flex justify-between items-center">
+                    <span>Equalizer</span>
+                    <span class="text-gray-500">
+                        {equalizer_preset}
+                    </span>
+                </a>
+                <ReplayGainSettings replay_gain=replay_gain />
+                <FadeSettings fades=fades />
+            </div>
+        </Page>
+    })
+}
+
+#[component]
+fn output_settings(
+    outputs: Vec<Output>,
+    current: Output,
+    bit_perfect: bool,
+    error: Option<String>,
+) -> impl IntoView {
+    html! {
+        <form
+            class="flex flex-col gap-4"
+            hx-post="/settings/output"
+            hx-trigger="change"
+            hx-swap="outerHTML"
+        >
+            <h3 class="text-gray-400">Output</h3>
+            <select name="output" class="p-2 w-full text-black bg-white rounded">
+                {outputs
+                    .into_iter()
+                    .map(|output| {
+                        let selected = output == current;
+                        let value = output.to_string();
+                        let label = value.clone();
+
+                        html! {
+                            <option value=value selected=selected>
+                                {label}
+                            </option>
+                        }
+                    })
+                    .collect::<Vec<_>>()}
+            </select>
+            <label class="flex justify-between items-center">
+                <span>Bit-perfect</span>
+                <input type="checkbox" name="bit_perfect" checked=bit_perfect />
+            </label>
+            {error.map(|error| html! { <p class="text-red-500">{error}</p> })}
+        </form>
+    }
+}
+
+#[component]
+fn replay_gain_settings(replay_gain: ReplayGain) -> impl IntoView {
+    let modes = [
+        (ReplayGainMode::Off, "Off"),
+        (ReplayGainMode::Track, "Track"),
+        (ReplayGainMode::Album, "Album"),
+    ];
+
+    html! {
+        <form
+            class="flex flex-col gap-4"
+            hx-post="/settings/replay-gain"
+            hx-trigger="change"
+            hx-swap="outerHTML"
+        >
+            <h3 class="text-gray-400">ReplayGain</h3>
+            <label class="flex justify-between items-center">
+                <span>Mode</span>
+                <select name="mode" class="p-2 text-black bg-white rounded">
+                    {modes
+                        .into_iter()
+                        .map(|(mode, label)| {
+                            html! {
+                                <option value=mode.to_string() selected=mode == replay_gain.mode>
+                                    {label}
+                                </option>
+                            }
+                        })
+                        .collect::<Vec<_>>()}
+                </select>
+            </label>
+            <label class="flex flex-col gap-2">
+                <span class="flex justify-between">
+                    <span>Preamp</span>
+                    <span class="text-gray-500">{format!("{:+.1} dB", replay_gain.preamp)}</span>
+                </span>
+                <input
+                    class="w-full"
+                    type="range"
+                    name="preamp"
+                    min="-15"
+                    max="15"
+                    step="0.5"
+                    value=replay_gain.preamp
+                />
+            </label>
+            <label class="flex justify-between items-center">
+                <span>Prevent clipping</span>
+                <input type="checkbox" name="prevent_clipping" checked=replay_gain.prevent_clipping />
+            </label>
+        </form>
+    }
+}
+
+#[component]
+fn fade_settings(fades: Fades) -> impl IntoView {
+    let crossfade = if fades.crossfade > 0.0 {
+        format!("{:.1} s", fades.crossfade)
+    } else {
+        "Off".to_string()
+    };
+
+    html! {
+        <form
+            class="flex flex-col gap-4"
+            hx-post="/settings/fades"
+            hx-trigger="change"
+            hx-swap="outerHTML"
+        >
+            <h3 class="text-gray-400">Fades</h3>
+            <label class="flex flex-col gap-2">
+                <span class="flex justify-between">
+                    <span>Crossfade</span>
+                    <span class="text-gray-500">{crossfade}</span>
+                </span>
+                <input
+                    class="w-full"
+                    type="range"
+                    name="crossfade"
+                    min="0"
+                    max=MAX_CROSSFADE
+                    step="0.5"
+                    value=fades.crossfade
+                />
+            </label>
+            <label class="flex justify-between items-center">
+                <span>Fade on play, pause and skip</span>
+                <input type="checkbox" name="soft" checked=fades.soft />
+            </label>
+        </form>
+    }
+}
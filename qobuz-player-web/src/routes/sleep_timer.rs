@@ -0,0 +1,109 @@
+use axum::{response::IntoResponse, routing::get, Form, Router};
+use leptos::{component, prelude::*, IntoView};
+use qobuz_player_controls::sleep_timer::SleepTimer;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{html, page::Page, view::render, AppState, Zone};
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/sleep-timer", get(index).post(set_sleep_timer))
+        .route("/sleep-timer/countdown", get(countdown_partial))
+}
+
+async fn index(Zone(player): Zone) -> impl IntoResponse {
+    let sleep_timer = player.sleep_timer().await;
+
+    render(html! {
+        <Page active_page=Page::None>
+            <div class="flex flex-col gap-4 p-4 mx-auto max-w-screen-sm">
+                <p class="text-lg text-center">Sleep timer</p>
+                <SleepTimerSettings sleep_timer=sleep_timer />
+            </div>
+        </Page>
+    })
+}
+
+#[derive(Deserialize)]
+struct SleepTimerParameters {
+    /// Minutes, track, album or off.
+    timer: String,
+}
+
+async fn set_sleep_timer(
+    Zone(player): Zone,
+    Form(parameters): Form<SleepTimerParameters>,
+) -> impl IntoResponse {
+    // Anything else, like off, cancels the timer.
+    let timer = parameters.timer.parse::<SleepTimer>().ok();
+
+    player.set_sleep_timer(timer).await.unwrap();
+
+    render(html! { <SleepTimerSettings sleep_timer=timer /> })
+}
+
+async fn countdown_partial(Zone(player): Zone) -> impl IntoResponse {
+    let countdown = player.sleep_timer().await.map(|timer| timer.to_string());
+
+    render(html! { {countdown} })
+}
+
+#[component]
+fn sleep_timer_settings(sleep_timer: Option<SleepTimer>) -> impl IntoView {
+    let options = [
+        ("15", "15 minutes"),
+        ("30", "30 minutes"),
+        ("45", "45 minutes"),
+        ("60", "1 hour"),
+        ("track", "End of track"),
+        ("album", "End of album"),
+    ];
+
+    html! {
+        <form
+            id="sleep-timer"
+            class="flex flex-col gap-4"
+            hx-post="/sleep-timer"
+            hx-swap="outerHTML"
+        >
+            <p
+                class="text-center text-gray-500"
+                hx-get="/sleep-timer/countdown"
+                hx-trigger="sse:position, sse:sleep-timer"
+                hx-swap="innerHTML"
+            >
+                {sleep_timer.map(|timer| timer.to_string())}
+            </p>
+            <div class="grid grid-cols-2 gap-2">
+                {options
+                    .into_iter()
+                    .map(|(value, label)| {
+                        html! {
+                            <button
+                                class="p-2 bg-gray-800 rounded cursor-pointer"
+                                name="timer"
+                                value=value
+                            >
+                                {label}
+                            </button>
+                        }
+                    })
+                    .collect::<Vec<_>>()}
+            </div>
+            {sleep_timer
+                .is_some()
+                .then(|| {
+                    html! {
+                        <button
+                            class="p-2 text-red-500 rounded cursor-pointer"
+                            name="timer"
+                            value="off"
+                        >
+                            Cancel
+                        </button>
+                    }
+                })}
+        </form>
+    }
+}
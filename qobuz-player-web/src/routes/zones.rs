@@ -0,0 +1,102 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{get, put},
+    Router,
+};
+use leptos::{component, prelude::*, IntoView};
+use std::sync::Arc;
+
+use crate::{
+    components::list::{List, ListItem},
+    html,
+    icons::SpeakerWave,
+    page::Page,
+    view::render,
+    AppState, Zone,
+};
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/zones", get(index))
+        .route("/zones/{zone}", put(select))
+}
+
+async fn select(State(state): State<Arc<AppState>>, Path(zone): Path<String>) -> impl IntoResponse {
+    if !state.players.iter().any(|player| player.zone() == zone) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    (
+        [
+            (
+                header::SET_COOKIE,
+                format!("zone={zone}; Path=/; Max-Age=31536000; SameSite=Lax"),
+            ),
+            (header::HeaderName::from_static("hx-redirect"), "/".into()),
+        ],
+        StatusCode::OK,
+    )
+        .into_response()
+}
+
+async fn index(State(state): State<Arc<AppState>>, Zone(player): Zone) -> impl IntoResponse {
+    let zones = state
+        .players
+        .iter()
+        .map(|player| {
+            (
+                player.zone().to_string(),
+                player.current_state() == qobuz_player_controls::State::Playing,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    render(html! {
+        <Page active_page=Page::None>
+            <Zones zones=zones selected=player.zone().to_string() />
+        </Page>
+    })
+}
+
+#[component]
+fn zones(zones: Vec<(String, bool)>, selected: String) -> impl IntoView {
+    html! {
+        <div class="flex flex-col gap-4">
+            <div class="p-4 text-center">
+                <p class="text-lg">Zones</p>
+            </div>
+            <List>
+                {zones
+                    .into_iter()
+                    .map(|(zone, playing)| {
+                        let class = format!(
+                            "flex gap-4 items-center w-full cursor-pointer {}",
+                            if zone == selected { "text-blue-500" } else { "" },
+                        );
+                        let url = format!("/zones/{}", zone);
+
+                        html! {
+                            <ListItem>
+                                <button
+                                    class=class
+                                    hx-put=url
+                                    hx-swap="none"
+                                >
+                                    <span class="size-6">
+                                        <SpeakerWave />
+                                    </span>
+                                    <span class="flex-grow text-left">{zone}</span>
+                                    <span class="text-gray-500">
+                                        {if playing { "Playing" } else { "" }}
+                                    </span>
+                                </button>
+                            </ListItem>
+                        }
+                    })
+                    .collect::<Vec<_>>()}
+            </List>
+        </div>
+    }
+}